@@ -0,0 +1,58 @@
+//! Compares the owned response line parser (`Response::to_response_lines`) with 
+//! the borrowing parser (`Response::menu_lines`) on large directory listings.
+//! 
+//! Run with `cargo bench --bench menu_parse`.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant}
+};
+
+use gopher::gopher::response::{Response, ResponseOutcome};
+
+const ITERATIONS: u32 = 20;
+
+/// Builds a directory listing with `nlines` entries of mixed item types
+fn listing(nlines: usize) -> Response {
+    let mut buffer = String::new();
+    for i in 0..nlines {
+        let item_type = ['0', '1', '9', 'i'][i % 4];
+        buffer.push_str(&format!(
+            "{item_type}Item number {i}\t/some/fairly/long/selector/{i}\tgopher.example.org\t70\r\n"
+        ));
+    }
+    Response::new(buffer.into_bytes(), ResponseOutcome::Complete)
+}
+
+/// Runs `f` `ITERATIONS` times and returns the mean duration of a single run
+fn time<F: FnMut() -> usize>(mut f: F) -> Duration {
+    // Warm up
+    black_box(f());
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    for nlines in [100, 10_000, 100_000] {
+        let response = listing(nlines);
+
+        let owned = time(|| {
+            response.to_response_lines().into_iter().filter(|line| line.is_ok()).count()
+        });
+        let borrowed = time(|| {
+            response.menu_lines().filter(|line| line.is_ok()).count()
+        });
+
+        println!(
+            "{:>7} lines: owned {:>10.3?} ({:>6.1} ns/line), borrowed {:>10.3?} ({:>6.1} ns/line), speedup {:.2}x",
+            nlines,
+            owned, owned.as_nanos() as f64 / nlines as f64,
+            borrowed, borrowed.as_nanos() as f64 / nlines as f64,
+            owned.as_secs_f64() / borrowed.as_secs_f64(),
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    net::IpAddr,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH}
+};
+
+// Chrono imports for data-time functionality
+use chrono::{DateTime, Utc};
+
+use crate::gopher::{
+    request::Request,
+    response::{ItemType, Response, ResponseOutcome}
+};
+
+use crate::session::outcome_name;
+use crate::transport::Transport;
+use crate::CRLF;
+
+/// Version line which starts every WARC record
+const WARC_VERSION: &str = "WARC/1.1";
+
+/// Header holding the outcome of a Gopher transaction. WARC allows custom headers.
+const OUTCOME_HEADER: &str = "WARC-Gopher-Outcome";
+
+/// Represents a single record of a WARC file.
+///
+/// * `headers`: Named fields of the record in order, e.g. ("WARC-Type", "response")
+/// * `block`: Content block of the record
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WarcRecord {
+    pub headers: Vec<(String, String)>,
+    pub block: Vec<u8>,
+}
+
+/// Transport which archives every transaction of an inner transport to a WARC file.
+///
+/// Each request is stored as a `request` record holding the selector line, and
+/// each response as a `response` record holding the raw bytes received, including
+/// the last line `.`CRLF of complete text files and menus. Requests which failed
+/// with an IO error are followed by a `metadata` record holding the error instead,
+/// and connection attempts without a request are stored as `metadata` records.
+///
+/// * `inner`: Transport which does the actual work
+/// * `file`: WARC file being written
+/// * `records`: Number of records written so far, used for record ids
+pub struct ArchivingTransport<T: Transport> {
+    inner: T,
+    file: BufWriter<File>,
+    records: u64,
+}
+
+impl WarcRecord {
+    /// Returns the value of the first header with the given name, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Writes the record in WARC format
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "{}{}", WARC_VERSION, CRLF)?;
+        for (name, value) in &self.headers {
+            write!(out, "{}: {}{}", name, value, CRLF)?;
+        }
+        write!(out, "Content-Length: {}{}{}", self.block.len(), CRLF, CRLF)?;
+        out.write_all(&self.block)?;
+        write!(out, "{}{}", CRLF, CRLF)
+    }
+}
+
+impl<T: Transport> ArchivingTransport<T> {
+    /// Constructs a new `ArchivingTransport`, creating (or truncating) the WARC file.
+    /// A `warcinfo` record describing the crawler is written first.
+    ///
+    /// # Arguments
+    /// * `inner`: Transport which does the actual work
+    /// * `path`: Path of the WARC file
+    pub fn create(inner: T, path: &Path) -> io::Result<ArchivingTransport<T>> {
+        let mut archive = ArchivingTransport {
+            inner,
+            file: BufWriter::new(File::create(path)?),
+            records: 0,
+        };
+
+        let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let info = format!("software: gopher/{}{}format: WARC File Format 1.1{}", env!("CARGO_PKG_VERSION"), CRLF, CRLF);
+        archive.write(vec![
+            (String::from("WARC-Type"), String::from("warcinfo")),
+            (String::from("WARC-Filename"), file_name),
+            (String::from("Content-Type"), String::from("application/warc-fields")),
+        ], info.into_bytes(), Utc::now())?;
+        Ok(archive)
+    }
+
+    /// Writes a record with a new record id and date. Returns the record id.
+    fn write(&mut self, mut headers: Vec<(String, String)>, block: Vec<u8>, date: DateTime<Utc>) -> io::Result<String> {
+        let record_id = self.record_id();
+        headers.insert(0, (String::from("WARC-Record-ID"), record_id.clone()));
+        headers.insert(1, (String::from("WARC-Date"), date.format("%Y-%m-%dT%H:%M:%SZ").to_string()));
+
+        WarcRecord { headers, block }.write_to(&mut self.file)?;
+        self.file.flush()?;
+        Ok(record_id)
+    }
+
+    /// Generates a unique record id in the form of a (version 4 style) UUID URN
+    fn record_id(&mut self) -> String {
+        self.records += 1;
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_nanos()).unwrap_or_default();
+
+        // Mix the time, process and record number so ids differ between files
+        let mut state = nanos ^ ((std::process::id() as u128) << 64) ^ (self.records as u128).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        let mut bytes = [0u8; 16];
+        for byte in bytes.iter_mut() {
+            state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+            *byte = (state >> 64) as u8;
+        }
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        let hex = bytes.iter().map(|byte| format!("{byte:02x}")).collect::<String>();
+        format!("<urn:uuid:{}-{}-{}-{}-{}>", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+    }
+}
+
+impl<T: Transport> Transport for ArchivingTransport<T> {
+    fn send_and_recv(&mut self, request: &Request) -> io::Result<Response> {
+        let date = Utc::now();
+        let result = self.inner.send_and_recv(request);
+        let target_uri = gopher_uri(&request.server_details, request.item_type, &request.selector);
+
+        let request_id = self.write(vec![
+            (String::from("WARC-Type"), String::from("request")),
+            (String::from("WARC-Target-URI"), target_uri.clone()),
+            (String::from("Content-Type"), String::from("application/gopher-request")),
+        ], format!("{}{}", request.selector, CRLF).into_bytes(), date)?;
+
+        // Requests which failed with an IO error did not receive a response
+        let response = match result {
+            Ok(response) => response,
+            Err(error) => {
+                let outcome = outcome_name(ResponseOutcome::ConnectionFailed);
+                self.write(vec![
+                    (String::from("WARC-Type"), String::from("metadata")),
+                    (String::from("WARC-Target-URI"), target_uri),
+                    (String::from("WARC-Concurrent-To"), request_id),
+                    (String::from(OUTCOME_HEADER), outcome.to_string()),
+                    (String::from("Content-Type"), String::from("application/warc-fields")),
+                ], format!("connection: {}{}error: {}{}", outcome, CRLF, error, CRLF).into_bytes(), date)?;
+                return Err(error)
+            },
+        };
+
+        // Put back the last line which `gopher::recv` removed
+        let mut raw = response.buffer.clone();
+        if response.response_outcome == ResponseOutcome::Complete
+            && matches!(request.item_type, ItemType::Txt | ItemType::Dir) {
+            raw.extend_from_slice(b".");
+            raw.extend_from_slice(CRLF.as_bytes());
+        }
+
+        self.write(vec![
+            (String::from("WARC-Type"), String::from("response")),
+            (String::from("WARC-Target-URI"), target_uri),
+            (String::from("WARC-Concurrent-To"), request_id),
+            (String::from(OUTCOME_HEADER), outcome_name(response.response_outcome).to_string()),
+            (String::from("Content-Type"), String::from("application/gopher-response")),
+        ], raw, date)?;
+        Ok(response)
+    }
+
+    fn connect(&mut self, server_details: &str) -> io::Result<()> {
+        let date = Utc::now();
+        let result = self.inner.connect(server_details);
+
+        let outcome = match result {
+            Ok(_) => "connected",
+            Err(_) => outcome_name(ResponseOutcome::ConnectionFailed),
+        };
+        self.write(vec![
+            (String::from("WARC-Type"), String::from("metadata")),
+            (String::from("WARC-Target-URI"), format!("gopher://{}/", server_details)),
+            (String::from(OUTCOME_HEADER), outcome.to_string()),
+            (String::from("Content-Type"), String::from("application/warc-fields")),
+        ], format!("connection: {}{}", outcome, CRLF).into_bytes(), date)?;
+        result
+    }
+
+    fn resolve(&mut self, server_details: &str) -> io::Result<Vec<IpAddr>> {
+        self.inner.resolve(server_details)
+    }
+}
+
+/// Reads every record of a WARC file, in the order they were written.
+///
+/// # Returns
+/// The records if the file is a valid (uncompressed) WARC file. Otherwise, returns
+/// an IO error.
+pub fn read_archive(path: &Path) -> io::Result<Vec<WarcRecord>> {
+    let contents = fs::read(path)?;
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), message));
+
+    let mut records = Vec::new();
+    let mut remaining = &contents[..];
+    while !remaining.is_empty() {
+        let header_end = find(remaining, b"\r\n\r\n")
+            .ok_or_else(|| invalid(format!("record {} has no end of headers", records.len() + 1)))?;
+        let header = String::from_utf8_lossy(&remaining[..header_end]);
+        let mut lines = header.split(CRLF);
+
+        if lines.next() != Some(WARC_VERSION) {
+            return Err(invalid(format!("record {} does not start with {}", records.len() + 1, WARC_VERSION)));
+        }
+        let mut headers = Vec::new();
+        let mut content_length = None;
+        for line in lines {
+            let (name, value) = line.split_once(':')
+                .ok_or_else(|| invalid(format!("malformed header: {line}")))?;
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.parse::<usize>().ok();
+            } else {
+                headers.push((name.to_string(), value.to_string()));
+            }
+        }
+
+        let content_length = content_length.ok_or_else(|| invalid(String::from("missing Content-Length")))?;
+        let block_start = header_end + 4;
+        let block_end = block_start + content_length;
+        if remaining.len() < block_end + 4 {
+            return Err(invalid(format!("record {} is truncated", records.len() + 1)));
+        }
+
+        records.push(WarcRecord { headers, block: remaining[block_start..block_end].to_vec() });
+        remaining = &remaining[block_end + 4..];
+    }
+    Ok(records)
+}
+
+/// Returns the `response` record of the item with the given target URI, if it
+/// was archived. If the item was requested more than once, the first response
+/// is returned.
+pub fn find_response<'a>(records: &'a [WarcRecord], target_uri: &str) -> Option<&'a WarcRecord> {
+    records.iter().find(|record| {
+        record.header("WARC-Type") == Some("response") && record.header("WARC-Target-URI") == Some(target_uri)
+    })
+}
+
+/// Returns the gopher URI (RFC 4266) of an item
+///
+/// # Arguments
+/// * `server_details`: hostname:port of the server providing the item
+/// * `item_type`: Type of the item
+/// * `selector`: String being used to request the item
+pub fn gopher_uri(server_details: &str, item_type: ItemType, selector: &str) -> String {
+    let mut uri = format!("gopher://{}/{}", server_details, item_type.to_char());
+    for byte in selector.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/!$&'()*+,;=:@".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    uri
+}
+
+/// Returns the position of the first occurance of `needle` in `haystack`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
@@ -1,11 +1,26 @@
+pub mod analysis;
+pub mod canonical;
+pub mod checkpoint;
+pub mod diff;
+pub mod export;
+pub mod graph;
+pub mod html;
+pub mod incremental;
+pub mod item;
+pub mod report;
+pub mod stats;
+pub mod trap;
+
 use std::{
-    cmp::min, 
-    fs::{File, Metadata}, 
-    io::Write, 
+    io, 
+    path::{Path, PathBuf},
     str,
-    rc::Rc
+    rc::Rc,
+    time::{Duration, Instant}
 };
 
+use serde::{Deserialize, Serialize};
+
 // Chrono imports for data-time functionality
 use chrono::Local;
 #[allow(unused_imports)]
@@ -14,78 +29,166 @@ use chrono::Timelike;
 use::debug_print::{debug_println, debug_eprintln};
 
 use crate::gopher::{
-    self, 
+    menu::{Menu, MenuItem},
     request::Request, 
-    response::{ItemType, ResponseLine, ResponseLineError, ResponseOutcome}
+    response::{ItemType, MenuLine, MenuLines, Response, ResponseLine, ResponseLineError, ResponseOutcome}
+};
+
+use self::{
+    canonical::{CanonicalRules, Canonicalizer},
+    checkpoint::Checkpoint,
+    incremental::PreviousCrawl,
+    item::{CrawledItem, CrawledItems, ItemOutcome, Referrer},
+    stats::DEFAULT_TOP_N,
+    trap::{SpiderTraps, DEFAULT_TRAP_THRESHOLD}
 };
+use crate::mirror::Mirror;
+use crate::storage::{content::content_hash, DuplicateGroup, Storage, StorageState};
+use crate::transport::{TcpTransport, Transport};
+use crate::{OUTPUT_FOLDER, TAB};
 
-use crate::{MAX_FILENAME_LEN, OUTPUT_FOLDER};
+/// Location of a line within a menu (server details, menu selector, line number)
+type MenuLocation = (Rc<String>, Rc<String>, usize);
 
 /// Represents a Gopher server craweler. 
 /// 
 /// * `root_server_name`: Hostname of the root (start) server
 /// * `root_server_port`: Port number of the root (start) server
+/// * `transport`: Used to exchange transactions with Gopher servers
 /// 
-/// * `ndir`: Number of directories
-/// * `dirs`: List of all directories (server details, directory) pairs
+/// * `items`: Every discovered directory, text file and binary file, in the 
+///   order they were discovered. Directory and file counts, sizes and the 
+///   visited set are derived from them.
+/// * `smallest_contents`: Size and contents of the smallest text file, nothing 
+///   before the first one is downloaded. Files are not kept in memory, so it is 
+///   updated whenever a new smallest text file is downloaded. Invalid UTF-8 
+///   sequences are replaced, so the size of the file is kept as well.
 /// 
-/// * `ntxt`: Number of text files
-/// * `txt_files`: List of all simple text files (server details, text file) pairs
+/// * `error_items`: List of unique error (type 3) items and every location they 
+///   appeared at (display string, [(server details, menu selector, line number)]) pairs
+/// * `external_references`: List of external servers and if they accepted
+///   a connection (server name, server port, connected) triples
+/// * `invalid_references`: List of invalid references 
+///   (details of the request, response outcome, origin) triples
 /// 
-/// * `nbin`: Number of binary files
-/// * `bin_files`: List of all binary files (server details, binary file) pairs
+/// * `menus`: List of all sucessfully crawled menus
+/// * `open_menus`: Stack of menus whose lines are still being processed, the 
+///   innermost on top. Lines are moved into their menu once processed.
+/// * `storage`: Where downloaded files and saved menus are kept
+/// * `storage_state`: State of the storage in the checkpoint the crawl was 
+///   resumed from, restored into every storage the crawl is given
 /// 
-/// * `smallest_contets`: Contents of the smallest text file
-/// * `smallest_txt`: Size of the smallest text file (bytes)
-/// * `largest_txt`: Size of the largest text file (bytes)
+/// * `frontier`: Stack of work still to be done, the top is done next
+/// * `checkpoint`: Where and how often the state of the crawl is saved 
+///   (state file, interval, time of the last checkpoint) triple
 /// 
-/// * `smallest_bin`: Size of the smallest binary file
-/// * `largest_bin`: Size of the largest binary file
+/// * `previous`: Snapshot of an earlier crawl, for incremental crawls
+/// * `unchanged`: List of menus with the same contents as in the earlier crawl 
+///   (server details, menu selector) pairs
+/// * `carried`: List of items taken from the earlier crawl instead of being 
+///   requested (server details, selector) pairs
 /// 
-/// * `smallest_txt_selector`: The selector of the smallest text file 
-/// (server details, text file selector) pairs
-/// * `largest_txt_selector`: The selector of the largest text file 
-/// (server details, text file selector) pairs
-/// * `smallest_bin_selector`: The selector of the smallest binary file 
-/// (server details, binary file selector) pairs
-/// * `largest_bin_selector`: The selector of the largest binary file 
-/// (server details, binary file selector) pairs
+/// * `top_n`: Number of smallest and largest items of each type in the report
 /// 
-/// * `nerr`: The number of unique invalid references (error types)
-/// * `external_references`: List of external servers and if they accepted
-/// a connection (server name, server port, connected) triples
-/// * `invalid_references`: List of invalid references 
-/// (details of the request, response outcome) pairs
-/// * `used`: List of used selectors (server name, server port, selector) tripless
+/// * `traps`: Every group of menus matching the same pattern, and the menus cut
+///   off as suspected spider traps
+/// * `trap_threshold`: Number of menus matching a pattern which are crawled 
+///   before the rest are cut off
+/// 
+/// * `canonical`: Maps every reference to an item to the first equivalent 
+///   reference found, and keeps the references which were merged
 pub struct Crawler {
     root_server_name: Rc<String>,
     root_server_port: u16,
+    transport: Box<dyn Transport>,
 
-    ndir: u32,
-    dirs: Vec<(Rc<String>, Rc<String>)>,
-
-    ntxt: u32,
-    txt_files: Vec<(Rc<String>, Rc<String>)>,
-    
-    nbin: u32,
-    bin_files: Vec<(Rc<String>, Rc<String>)>,
-    
-    smallest_contents: String,
-    smallest_txt: u64,
-    largest_txt: u64,
-    
-    smallest_bin: u64,
-    largest_bin: u64,
+    items: CrawledItems,
+    smallest_contents: Option<(usize, String)>,
     
-    smallest_txt_selector: (Rc<String>, Rc<String>),
-    largest_txt_selector:  (Rc<String>, Rc<String>),
-    smallest_bin_selector: (Rc<String>, Rc<String>),
-    largest_bin_selector:  (Rc<String>, Rc<String>),
-    
-    nerr: u32,
+    error_items: Vec<(String, Vec<MenuLocation>)>,
     external_servers: Vec<(Rc<String>, u16, bool)>,
-    invalid_references: Vec<(String, ResponseOutcome)>,
-    used: Vec<(Rc<String>, u16, Rc<String>)>,
+    invalid_references: Vec<(String, ResponseOutcome, ReferenceOrigin)>,
+
+    menus: Vec<CrawledMenu>,
+    open_menus: Vec<CrawledMenu>,
+    storage: Box<dyn Storage>,
+    storage_state: Option<StorageState>,
+
+    frontier: Vec<Task>,
+    checkpoint: Option<(PathBuf, Duration, Instant)>,
+
+    previous: Option<PreviousCrawl>,
+    unchanged: Vec<(Rc<String>, Rc<String>)>,
+    carried: Vec<(Rc<String>, Rc<String>)>,
+
+    top_n: usize,
+
+    traps: SpiderTraps,
+    trap_threshold: usize,
+
+    canonical: Canonicalizer,
+}
+
+/// Represents a unit of work in the frontier of a crawl.
+/// 
+/// * `Menu(selector, server name, server port)`: Request a menu and queue its lines
+/// * `Line(menu server details, menu selector, line number, line)`: Process a link
+///   of a menu, or record a line as invalid
+/// * `MenuDone`: Record the innermost open menu once all of its lines (and 
+///   sub-menus) were processed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum Task {
+    Menu(Rc<String>, Rc<String>, u16),
+    Line(Rc<String>, Rc<String>, usize, Result<ResponseLine, ResponseLineError>),
+    MenuDone,
+}
+
+/// Represents where an invalid reference was found.
+/// 
+/// * `Item(server details, selector)`: An item which could not be received. It
+///   is linked from the referrers of the item.
+/// * `Line(referrer)`: A menu line which could not be parsed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum ReferenceOrigin {
+    Item(Rc<String>, Rc<String>),
+    Line(Referrer),
+}
+
+/// Represents a menu (directory) that was sucessfully crawled.
+/// 
+/// * `server_details`: hostname:port of the server providing the menu
+/// * `selector`: String used to request the menu
+/// * `contents`: The menu as it was received. Its lines are parsed again when 
+///   needed, so no line is copied out of the response.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CrawledMenu {
+    pub server_details: Rc<String>,
+    pub selector: Rc<String>,
+    contents: Vec<u8>,
+}
+
+impl CrawledMenu {
+    /// Returns every valid response line of the menu in order, including info 
+    /// lines. Malformed lines are skipped.
+    pub fn lines(&self) -> impl Iterator<Item = MenuLine<'_>> {
+        MenuLines::new(&self.contents).filter_map(Result::ok)
+    }
+
+    /// Returns the informational text of the menu, one info line per line
+    pub fn info_text(&self) -> String {
+        self.lines()
+            .filter(|line| line.item_type == ItemType::Info)
+            .map(|line| line.display_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Reconstructs the menu as it was served
+    pub fn to_menu(&self) -> Menu {
+        Menu { 
+            items: self.lines().map(MenuItem::from).collect() 
+        }
+    }
 }
 
 impl Default for Crawler {
@@ -93,32 +196,33 @@ impl Default for Crawler {
         Crawler {
             root_server_name: Rc::new(String::from("comp3310.ddns.net")),
             root_server_port: 70,
+            transport: Box::new(TcpTransport),
 
-            ndir: 0,   
-            dirs: Vec::new(),
-            
-            ntxt:  0,
-            txt_files: Vec::new(), 
-            
-            nbin:  0,
-            bin_files: Vec::new(),
+            items: CrawledItems::new(),
+            smallest_contents: None,
             
-            smallest_contents: String::new(),
-            smallest_txt: u64::MAX,
-            largest_txt: 0, 
-            
-            smallest_bin: u64::MAX, 
-            largest_bin: 0,
-            
-            smallest_txt_selector: (Rc::new(String::new()), Rc::new(String::new())),
-            largest_txt_selector:  (Rc::new(String::new()), Rc::new(String::new())),
-            smallest_bin_selector: (Rc::new(String::new()), Rc::new(String::new())),
-            largest_bin_selector:  (Rc::new(String::new()), Rc::new(String::new())),
-            
-            nerr: 0,
+            error_items: Vec::new(),
             external_servers: Vec::new(),
             invalid_references: Vec::new(),
-            used: Vec::new(),
+
+            menus: Vec::new(),
+            open_menus: Vec::new(),
+            storage: Box::new(Mirror::new(Path::new(OUTPUT_FOLDER))),
+            storage_state: None,
+
+            frontier: Vec::new(),
+            checkpoint: None,
+
+            previous: None,
+            unchanged: Vec::new(),
+            carried: Vec::new(),
+
+            top_n: DEFAULT_TOP_N,
+
+            traps: SpiderTraps::new(),
+            trap_threshold: DEFAULT_TRAP_THRESHOLD,
+
+            canonical: Canonicalizer::new(CanonicalRules::default()),
         }
     }
 }
@@ -155,123 +259,233 @@ impl Crawler {
         }
     }
 
-    /// Reports the outcome of a server crawl
-    pub fn report(&self) {
-        let format_server_selector = |(server_details, selector): &(Rc<String>, Rc<String>)| {
-            format!("{}: {}", *server_details, *selector)
-        };
-    
-        let format_external_server = |(server_name, server_port, conn_result): &(Rc<String>, u16, bool)| {
-            let status = if *conn_result {
-                "connected successfully"
-            } else {
-                "did not connect"
-            };
-            format!("{}:{} {}", *server_name, *server_port, status)
-        };
-    
-        let format_invalid_reference = |(response_details, response_outcome): &(String, ResponseOutcome)| {
-            format!("{} {}", response_outcome.to_string(), response_details)
-        };
+    /// Replaces the transport used to exchange transactions with Gopher servers.
+    /// 
+    /// By default, servers are contacted over TCP with `TcpTransport`.
+    pub fn with_transport(mut self, transport: impl Transport + 'static) -> Crawler {
+        self.transport = Box::new(transport);
+        self
+    }
 
-        let sort_alphabetically = |mut v: Vec<String>| {
-            v.sort_by(|a, b| {
-                a.to_lowercase().cmp(&b.to_lowercase())
-            });
-            v
-        };
+    /// Replaces the storage downloaded files and saved menus are kept in.
+    /// 
+    /// By default, items are mirrored into `OUTPUT_FOLDER` with `Mirror`. A 
+    /// resumed crawl restores the state of its storage from the checkpoint.
+    pub fn with_storage(mut self, mut storage: impl Storage + 'static) -> Crawler {
+        if let Some(state) = &self.storage_state {
+            storage.restore(state.clone());
+        }
+        self.storage = Box::new(storage);
+        self
+    }
 
-        let sorted_dirs = sort_alphabetically(
-            self.dirs.iter().map(format_server_selector).collect::<Vec<String>>()
-        );
-        let sorted_txt_files = sort_alphabetically(
-            self.txt_files.iter().map(format_server_selector).collect::<Vec<String>>()
-        );
-        let sorted_bin_files = sort_alphabetically(
-            self.bin_files.iter().map(format_server_selector).collect::<Vec<String>>()
-        );
-        let sorted_external_servers = sort_alphabetically(
-            self.external_servers.iter().map(format_external_server).collect::<Vec<_>>()
-        );
-        let sorted_invalid_references = sort_alphabetically(
-            self.invalid_references.iter().map(format_invalid_reference).collect::<Vec<_>>()
-        );
+    /// Saves the state of the crawl to `path` every `interval`, and once the crawl
+    /// completes. The crawl can be continued from the last checkpoint with
+    /// `Crawler::resume`.
+    pub fn with_checkpoint(mut self, path: &Path, interval: Duration) -> Crawler {
+        self.checkpoint = Some((path.to_path_buf(), interval, Instant::now()));
+        self
+    }
 
-        println!(
-            "\nSTART CRAWLER REPORT\n\n\
-            \tNumber of Gopher directories: {}\n\
-            \t\t{}\n\n\
-            \tNumber of simple text files: {}\n\
-            \t\t{}\n\n\
-            \tNumber of binary files: {}\n\
-            \t\t{}\n\n\
-            \tSmallest text file: {}\n\
-            \t\tSize: {} bytes\n\
-            \t\tContents: {}\n\n\
-            \tSize of the largest text file: {} bytes\n\
-            \t\t{}\n\n\
-            \tSize of the smallest binary file: {} bytes\n\
-            \t\t{}\n\n\
-            \tSize of the largest binary file: {} bytes\n\
-            \t\t{}\n\n\
-            \tThe number of unique invalid references (error types): {}\n\n\
-            \tList of external servers:\n\
-            \t\t{}\n\n\
-            \tReferences that have issues/errors:\n\
-            \t\t{}\n\n\
-            END CRAWLER REPORT",
-            self.ndir,
-            sorted_dirs.join("\n\t\t"),
-            self.ntxt,
-            sorted_txt_files.join("\n\t\t"),
-            self.nbin,
-            sorted_bin_files.join("\n\t\t"),
-            format_server_selector(&self.smallest_txt_selector),
-            self.smallest_txt,
-            self.smallest_contents,
-            self.largest_txt,
-            format_server_selector(&self.largest_txt_selector),
-            self.smallest_bin,
-            format_server_selector(&self.smallest_bin_selector),
-            self.largest_bin,
-            format_server_selector(&self.largest_bin_selector),
-            self.nerr,
-            sorted_external_servers.join("\n\t\t"),
-            sorted_invalid_references.join("\n\t\t"),
-        );
+    /// Makes the crawl incremental. Every menu is still requested and compared
+    /// against the snapshot of an earlier crawl, and the files in a menu with the
+    /// same contents as before are taken from the snapshot instead of being 
+    /// requested again. Its sub-menus are requested, as they may have changed.
+    /// Files missing from the snapshot are always requested.
+    pub fn with_previous(mut self, previous: PreviousCrawl) -> Crawler {
+        self.previous = Some(previous);
+        self
+    }
+
+    /// Sets the number of smallest and largest items of each type listed in the
+    /// report, `DEFAULT_TOP_N` by default. Items with the same size as the last
+    /// one listed are listed as well.
+    pub fn with_top_n(mut self, top_n: usize) -> Crawler {
+        self.top_n = top_n;
+        self
+    }
+
+    /// Sets the number of menus matching the same pattern (e.g. `/maze/#`) which
+    /// are crawled before the rest are cut off as a suspected spider trap,
+    /// `DEFAULT_TRAP_THRESHOLD` by default. Selectors nested deeper than the 
+    /// threshold are cut off as well.
+    pub fn with_trap_threshold(mut self, trap_threshold: usize) -> Crawler {
+        self.trap_threshold = trap_threshold;
+        self
+    }
+
+    /// Sets which references to an item are treated as the same item, so they 
+    /// are only crawled once, see `CanonicalRules`. By default, only hostnames
+    /// are case-folded. References are mapped to the first equivalent reference
+    /// found, which is the one requested and reported.
+    pub fn with_canonicalization(mut self, rules: CanonicalRules) -> Crawler {
+        self.canonical.set_rules(rules);
+        self
+    }
+
+    /// Restores a crawl from a checkpoint. The transport and earlier crawl of an
+    /// incremental crawl are not part of the checkpoint, so they are the defaults
+    /// until replaced. The storage is the default until replaced as well, but 
+    /// the items it stored before the checkpoint are restored into it.
+    /// 
+    /// # Returns
+    /// A `Crawler` which continues from the checkpoint when started, if the 
+    /// checkpoint could be read. Otherwise, returns an IO error.
+    pub fn resume(path: &Path) -> io::Result<Crawler> {
+        let mut crawler = Crawler::default();
+        Checkpoint::read(path)?.restore(&mut crawler);
+        Ok(crawler)
+    }
+
+    /// Writes a checkpoint if one is due
+    fn checkpoint_if_due(&mut self) -> io::Result<()> {
+        match &self.checkpoint {
+            Some((_, interval, last)) if last.elapsed() >= *interval => self.write_checkpoint(),
+            _ => Ok(()),
+        }
+    }
+
+    fn write_checkpoint(&mut self) -> io::Result<()> {
+        if let Some((path, _, _)) = &self.checkpoint {
+            Checkpoint::capture(self).write(path)?;
+            debug_println!("CHECKPOINT WRITTEN TO {}", path.display());
+        }
+        if let Some((_, _, last)) = &mut self.checkpoint {
+            *last = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Returns the display string of a discovered item, if it has one
+    /// 
+    /// # Arguments
+    /// * `server_details`: hostname:port of the server providing the item
+    /// * `selector`: String used to request the item
+    pub fn title(&self, server_details: &str, selector: &str) -> Option<&str> {
+        self.items.get(server_details, selector)
+            .map(|item| item.display_string.as_str())
+            .filter(|display_string| !display_string.is_empty())
+    }
+
+    /// Returns every menu that was sucessfully crawled, in the order they completed
+    pub fn menus(&self) -> &[CrawledMenu] {
+        &self.menus
+    }
+
+    /// Returns every discovered directory, text file and binary file in the order
+    /// they were discovered, e.g. to query them or to export them with 
+    /// `export::write_items`
+    pub fn items(&self) -> &CrawledItems {
+        &self.items
+    }
+
+    /// Returns every group of menus matching the same pattern, including the 
+    /// suspected spider traps
+    pub fn traps(&self) -> &SpiderTraps {
+        &self.traps
+    }
+
+    /// Returns every group of downloaded files with identical contents, if the
+    /// storage keeps track of them
+    pub fn duplicates(&self) -> Option<Vec<DuplicateGroup>> {
+        self.storage.duplicates()
+    }
+
+    /// Reports the outcome of a server crawl
+    pub fn report(&self) {
+        println!("{}", self.render_report());
+    }
+
+    /// Renders the crawl report printed by `Crawler::report`
+    pub fn render_report(&self) -> String {
+        self.crawl_report().render()
     }
 
     /// Starts a Gopher server crawl on the root server name 
-    /// and root server port
+    /// and root server port. A crawl restored from a checkpoint continues from
+    /// its frontier instead.
     pub fn start_crawl(&mut self) -> std::io::Result<()> {
-        // Send an empty selector to start the call
-        let starting_selector = String::from("");
+        if self.items.is_empty() && self.frontier.is_empty() {
+            // The root menu is the first reference to the root server, so it keys
+            // the root server and the empty selector
+            self.canonical.load_caps(&mut *self.transport, &self.root_server_name, self.root_server_port);
+            let (server_name, starting_selector) = self.canonical.canonicalize(&mut *self.transport, &self.root_server_name, self.root_server_port, "");
+
+            self.items.insert(CrawledItem {
+                server_name: server_name.clone(),
+                server_port: self.root_server_port,
+                selector: starting_selector.clone(),
+                item_type: ItemType::Dir,
+                display_string: String::new(),
+                size: None,
+                outcome: ItemOutcome::Pending,
+                depth: 0,
+                referrers: Vec::new(),
+                latency: None,
+                hash: None,
+            });
+            self.frontier.push(Task::Menu(
+                Rc::new(starting_selector), 
+                Rc::new(server_name), 
+                self.root_server_port
+            ));
+        }
 
-        self.crawl(
-            Rc::new(starting_selector), 
-            Rc::clone(&self.root_server_name), 
-            self.root_server_port
-        )?;
+        self.crawl()
+    }
+
+    /// Works through the frontier until it is empty.
+    /// 
+    /// Tasks are taken from the top of the stack, and the lines of a menu are 
+    /// pushed so they are processed in order, with each sub-menu crawled before 
+    /// the lines after it. A checkpoint is written before a task whenever one
+    /// is due, and once the crawl completes.
+    /// 
+    /// # Returns
+    /// Nothing if sucessfull. An IO error is unsucessful.
+    fn crawl(&mut self) -> std::io::Result<()> {
+        loop {
+            self.checkpoint_if_due()?;
+            let Some(task) = self.frontier.pop() else { break };
+
+            match task {
+                Task::Menu(selector, server_name, server_port) => {
+                    self.crawl_menu(selector, server_name, server_port)?
+                },
+                Task::Line(server_details, selector, line_number, response_line) => {
+                    match response_line {
+                        // Process the response line
+                        Ok(response_line) => {
+                            self.process_response_line(response_line.as_menu_line(), &server_details, &selector, line_number)
+                                .map_err(|error| {
+                                    debug_eprintln!("Problem processing response line: {error}");
+                                    error
+                                })?;
+                        },
+                        // Invalid response line
+                        Err(error) => self.record_invalid_line(error, &server_details, &selector, line_number),
+                    }
+                },
+                Task::MenuDone => self.menus.extend(self.open_menus.pop()),
+            }
+        }
 
+        if self.checkpoint.is_some() {
+            self.write_checkpoint()?;
+        }
         Ok(())
     }
 
-    /// Crawls a given Gopher server with a selector
+    /// Requests a menu from a given Gopher server and queues its lines
     /// 
     /// # Arguments
-    /// * `selector`: Selector string being used to request an item
+    /// * `selector`: Selector string being used to request the menu
     /// * `server_name`: The name of the Gopher server to be crawled
     /// * `server_port`: The port number of the Gopher server to be crawled
     /// 
     /// # Returns
     /// Nothing if sucessfull. An IO error is unsucessful.
-    fn crawl(&mut self, selector: Rc<String>, server_name: Rc<String>, server_port: u16) -> std::io::Result<()> {
-        self.used.push((
-            Rc::clone(&server_name), 
-            server_port, 
-            Rc::clone(&selector)
-        ));
-
+    fn crawl_menu(&mut self, selector: Rc<String>, server_name: Rc<String>, server_port: u16) -> std::io::Result<()> {
         // Request to send to the server
         let request = Request::new(
             Rc::clone(&selector), 
@@ -280,7 +494,8 @@ impl Crawler {
             ItemType::Dir
         );
         
-        let response = gopher::send_and_recv(&request)
+        // Menus are always requested, as sub-menus of an unchanged menu may have changed
+        let response = self.fetch(&request, false)
             .map_err(|error| {
                 debug_eprintln!("Problem sending OR receving request: {error}");
                 error
@@ -288,83 +503,232 @@ impl Crawler {
 
         match response.response_outcome {
             ResponseOutcome::Complete => {
-                // Split the response into response lines
-                for response_line in response.to_response_lines() {
-                    match response_line {
-                        // Process the response line
-                        Ok(response_line) => {
-                            self.process_response_line(response_line).map_err(|error| {
-                                debug_eprintln!("Problem processing response line: {error}");
-                                error
-                            })?;
-                        },
-                        // Invalid response line
-                        Err(error) => {
-                            match error {
-                                ResponseLineError::Empty => (),
-                                ResponseLineError::InvalidParts(line) => {
-                                    self.invalid_references.push((line, ResponseOutcome::MalformedResponseLine));
-                                },
-                                ResponseLineError::EmptyDisplayString(line) => {
-                                    self.invalid_references.push((line, ResponseOutcome::MalformedResponseLine));
-                                },
-                                ResponseLineError::EmptyHost(server_name, server_port, selector) => {
-                                    self.invalid_references.push((
-                                        format!("{}:{} {}", server_name, server_port, selector),
-                                        ResponseOutcome::MalformedResponseLine
-                                    ));
-                                }, 
-                                ResponseLineError::NonIntPort(server_name, server_port, selector) => {
-                                    self.invalid_references.push((
-                                        format!("{}:{} {}", server_name, server_port, selector), 
-                                        ResponseOutcome::MalformedResponseLine
-                                    ));
-                                },
-                            }
-                        }
+                // Split the response into response lines borrowed from the response
+                let lines = (1..).zip(response.menu_lines()).collect::<Vec<_>>();
+
+                let valid_lines = lines.iter().filter_map(|(_, line)| line.as_ref().ok().copied()).collect::<Vec<_>>();
+                if self.is_unchanged(&request.server_details, &selector, &valid_lines) {
+                    self.unchanged.push((Rc::clone(&request.server_details), Rc::clone(&selector)));
+                }
+
+                // The links of menus similar to too many others are not followed
+                let is_root = *server_name == *self.root_server_name && server_port == self.root_server_port && selector.is_empty();
+                let follow = is_root || self.traps.check_contents(&request.server_details, &selector, &valid_lines, self.trap_threshold);
+
+                // Only links are copied out of the response, as they outlive it in the frontier
+                self.frontier.push(Task::MenuDone);
+                if follow {
+                    for (line_number, response_line) in lines.into_iter().rev() {
+                        let response_line = match response_line {
+                            Ok(line) if matches!(line.item_type, ItemType::Info | ItemType::Unknown(_)) => continue,
+                            response_line => response_line.map(MenuLine::into_owned),
+                        };
+                        self.frontier.push(Task::Line(
+                            Rc::clone(&request.server_details), 
+                            Rc::clone(&selector), 
+                            line_number, 
+                            response_line
+                        ));
                     }
+                } else {
+                    debug_println!("SUSPECTED TRAP, NOT FOLLOWING {} {}", request.server_details, selector);
                 }
-                self.dirs.push((request.server_details, selector));
-                self.ndir += 1;
+
+                // The menu is recorded once all of its lines were processed
+                self.open_menus.push(CrawledMenu { 
+                    server_details: Rc::clone(&request.server_details), 
+                    selector: Rc::clone(&selector), 
+                    contents: response.buffer,
+                });
             }
             // Response unsucessful
             _ => {
                 self.invalid_references.push((
                     format!("{} {}", request.server_details, selector),
-                    response.response_outcome
+                    response.response_outcome,
+                    ReferenceOrigin::Item(Rc::clone(&request.server_details), selector)
                 ));
             }
         }
         Ok(())
     }
 
-    fn process_response_line(&mut self, response_line: ResponseLine) -> std::io::Result<()> {    
+    /// Returns true if a menu has the same contents as in the earlier crawl of an
+    /// incremental crawl
+    /// 
+    /// # Arguments
+    /// * `server_details`: hostname:port of the server providing the menu
+    /// * `selector`: String used to request the menu
+    /// * `lines`: Every valid response line of the menu in order
+    fn is_unchanged(&self, server_details: &str, selector: &str, lines: &[MenuLine]) -> bool {
+        let Some(previous) = &self.previous else { return false };
+        let menu = Menu { items: lines.iter().map(|&line| MenuItem::from(line)).collect() };
+        match menu.serialize() {
+            Ok(serialized) => previous.menu_unchanged(server_details, selector, serialized.as_bytes()),
+            Err(_) => false,
+        }
+    }
+
+    /// Takes a file from the earlier crawl of an incremental crawl if `carry` and
+    /// the file was kept. Otherwise, requests the item with the transport.
+    fn fetch(&mut self, request: &Request, carry: bool) -> std::io::Result<Response> {
+        let previous = self.previous.as_ref()
+            .filter(|_| carry)
+            .and_then(|previous| previous.contents(request.item_type, &request.server_details, &request.selector));
+
+        let (response, outcome, latency) = match previous {
+            Some(contents) => {
+                let contents = contents?;
+                debug_println!("CARRIED FORWARD {} {}", request.server_details, request.selector);
+
+                self.carried.push((Rc::clone(&request.server_details), Rc::clone(&request.selector)));
+                (Response::new(contents, ResponseOutcome::Complete), ItemOutcome::Carried, None)
+            },
+            None => {
+                let start = Instant::now();
+                let response = self.transport.send_and_recv(request)?;
+                let outcome = ItemOutcome::Fetched(response.response_outcome);
+                (response, outcome, Some(start.elapsed()))
+            },
+        };
+
+        if let Some(item) = self.items.get_mut(&request.server_details, &request.selector) {
+            let complete = response.response_outcome == ResponseOutcome::Complete;
+            item.item_type = request.item_type;
+            item.size = complete.then_some(response.buffer.len() as u64);
+            item.hash = complete.then(|| content_hash(&response.buffer));
+            item.outcome = outcome;
+            item.latency = latency;
+        }
+        Ok(response)
+    }
+
+    /// Records an item the first time it is discovered, one menu deeper than the
+    /// menu it was discovered in. Items found again only gain a referrer.
+    fn record_item(&mut self, response_line: &MenuLine, menu_details: &Rc<String>, menu_selector: &Rc<String>, line_number: usize) {
+        let server_details = format!("{}:{}", response_line.server_name, response_line.server_port);
+        let referrer = Referrer {
+            server_details: menu_details.to_string(),
+            selector: menu_selector.to_string(),
+            line: line_number,
+            display_string: response_line.display_string.to_string(),
+        };
+
+        if let Some(item) = self.items.get_mut(&server_details, response_line.selector) {
+            // The root menu is not discovered in a menu, so it has no display string yet
+            if item.display_string.is_empty() {
+                item.display_string = response_line.display_string.to_string();
+            }
+            if !item.referrers.contains(&referrer) {
+                item.referrers.push(referrer);
+            }
+            return
+        }
+
+        let depth = self.items.get(menu_details, menu_selector).map_or(0, |menu| menu.depth) + 1;
+        self.items.insert(CrawledItem {
+            server_name: response_line.server_name.to_string(),
+            server_port: response_line.server_port,
+            selector: response_line.selector.to_string(),
+            item_type: response_line.item_type,
+            display_string: response_line.display_string.to_string(),
+            size: None,
+            outcome: ItemOutcome::Pending,
+            depth,
+            referrers: vec![referrer],
+            latency: None,
+            hash: None,
+        });
+    }
+
+    /// Records a response line which could not be parsed, and the menu line it
+    /// appeared on
+    fn record_invalid_line(&mut self, error: ResponseLineError, menu_details: &Rc<String>, menu_selector: &Rc<String>, line_number: usize) {
+        // Display string of the line, if the line has one before its first tab
+        let display_string = |line: &str| line.split(TAB).next().unwrap_or_default().chars().skip(1).collect();
+
+        let (details, display_string) = match error {
+            ResponseLineError::Empty => return,
+            ResponseLineError::InvalidParts(line) => {
+                let display_string = display_string(&line);
+                (line, display_string)
+            },
+            ResponseLineError::EmptyDisplayString(line) => (line, String::new()),
+            ResponseLineError::EmptyHost(server_name, server_port, selector) => {
+                (format!("{}:{} {}", server_name, server_port, selector), String::new())
+            }, 
+            ResponseLineError::NonIntPort(server_name, server_port, selector) => {
+                (format!("{}:{} {}", server_name, server_port, selector), String::new())
+            },
+            ResponseLineError::InvalidUtf8(line) => {
+                let display_string = display_string(&line);
+                (line, display_string)
+            },
+        };
+
+        let referrer = Referrer {
+            server_details: menu_details.to_string(),
+            selector: menu_selector.to_string(),
+            line: line_number,
+            display_string,
+        };
+        self.invalid_references.push((details, ResponseOutcome::MalformedResponseLine, ReferenceOrigin::Line(referrer)));
+    }
+
+    fn process_response_line(&mut self, response_line: MenuLine, menu_details: &Rc<String>, menu_selector: &Rc<String>, line_number: usize) -> std::io::Result<()> {    
+        // Lines are treated as referencing the first equivalent reference found
+        let (server_name, selector);
+        let mut response_line = response_line;
+        if matches!(response_line.item_type, ItemType::Txt | ItemType::Dir | ItemType::Bin) {
+            (server_name, selector) = self.canonical.canonicalize(
+                &mut *self.transport, response_line.server_name, response_line.server_port, response_line.selector
+            );
+            response_line = MenuLine { server_name: &server_name, selector: &selector, ..response_line };
+            self.record_item(&response_line, menu_details, menu_selector, line_number);
+        }
+
+        // Files in a menu which did not change since the earlier crawl are carried forward
+        let carry = self.unchanged.iter()
+            .any(|(server_details, selector)| server_details == menu_details && selector == menu_selector);
+
         match response_line.item_type {
-            ItemType::Txt => self.handle_file(response_line, ItemType::Txt)?,
-            ItemType::Dir => self.handle_dir(response_line)?,
-            ItemType::Err => self.nerr += 1,
-            ItemType::Bin => self.handle_file(response_line, ItemType::Bin)?,
-            ItemType::Unknown => (), 
+            ItemType::Txt => self.handle_file(response_line, ItemType::Txt, carry)?,
+            ItemType::Dir => self.handle_dir(response_line, menu_details, menu_selector)?,
+            ItemType::Err => self.record_error_item(&response_line, menu_details, menu_selector, line_number),
+            ItemType::Bin => self.handle_file(response_line, ItemType::Bin, carry)?,
+            ItemType::Info | ItemType::Unknown(_) => (), 
         }
         Ok(())
     }
 
-    fn handle_dir(&mut self, response_line: ResponseLine) -> std::io::Result<()> {
+    /// Records an error item and the menu line it appeared on. Error items with the 
+    /// same display string are grouped together.
+    fn record_error_item(&mut self, response_line: &MenuLine, menu_details: &Rc<String>, menu_selector: &Rc<String>, line_number: usize) {
+        let location = (Rc::clone(menu_details), Rc::clone(menu_selector), line_number);
+
+        match self.error_items.iter_mut().find(|(display_string, _)| display_string == response_line.display_string) {
+            Some((_, locations)) => locations.push(location),
+            None => self.error_items.push((response_line.display_string.to_string(), vec![location])),
+        }
+    }
+
+    fn handle_dir(&mut self, response_line: MenuLine, menu_details: &str, menu_selector: &str) -> std::io::Result<()> {
         // External server is anything with a different server name OR a different port 
-        if response_line.server_name != self.root_server_name || response_line.server_port != self.root_server_port {
+        if response_line.server_name != *self.root_server_name || response_line.server_port != self.root_server_port {
             // Get the current local time
             #[allow(unused_variables)]
             let local_time = Local::now();
 
             // Attempts to connect to the external server
-            match gopher::connect(&format!("{}:{}", response_line.server_name, response_line.server_port)) {
+            match self.transport.connect(&format!("{}:{}", response_line.server_name, response_line.server_port)) {
                 // Connected sucessfully
                 Ok(_) => {
                     debug_println!("[{:02}h:{:02}m:{:02}s]: CONNECTED TO EXTERNAL {} ON {}", 
                         local_time.time().hour(), local_time.time().minute(), local_time.time().second(),
                         response_line.server_name, response_line.server_port);
 
-                    self.external_servers.push((response_line.server_name, response_line.server_port, true));
+                    self.external_servers.push((Rc::new(response_line.server_name.to_string()), response_line.server_port, true));
+                    self.record_external(&response_line, true);
                     return Ok(())
                 },
                 // Failed to connect
@@ -373,37 +737,57 @@ impl Crawler {
                         local_time.time().hour(), local_time.time().minute(), local_time.time().second(),
                         response_line.server_name, response_line.server_port);
 
-                    self.external_servers.push((response_line.server_name, response_line.server_port, false));
+                    self.external_servers.push((Rc::new(response_line.server_name.to_string()), response_line.server_port, false));
+                    self.record_external(&response_line, false);
                     return Ok(())
                 },
             }
         }
 
         // Check if the directory has been crawled before
-        if self.has_crawled(&response_line.server_name, response_line.server_port, &response_line.selector) { 
+        if self.has_crawled(response_line.server_name, response_line.server_port, response_line.selector) { 
             return Ok(()) 
         }
+
+        // Menus which look like part of a spider trap are not requested
+        let server_details = format!("{}:{}", response_line.server_name, response_line.server_port);
+        let item = self.items.get_mut(&server_details, response_line.selector);
+        if item.as_ref().is_some_and(|item| item.outcome == ItemOutcome::Trapped) {
+            return Ok(())
+        }
+        if let Some(kind) = self.traps.check_selector(&server_details, response_line.selector, (menu_details, menu_selector), self.trap_threshold) {
+            debug_println!("SUSPECTED TRAP ({kind}), NOT REQUESTING {} {}", server_details, response_line.selector);
+            if let Some(item) = item {
+                item.outcome = ItemOutcome::Trapped;
+            }
+            return Ok(())
+        }
         
-        // Crawl the directory
-        self.crawl(response_line.selector, 
-            response_line.server_name, 
-            response_line.server_port
-        )?;
+        // Crawl the directory next, before the rest of the current menu
+        let selector = Rc::new(response_line.selector.to_string());
+        let server_name = Rc::new(response_line.server_name.to_string());
+        self.frontier.push(Task::Menu(selector, server_name, response_line.server_port));
         Ok(())
     }
 
-    fn handle_file(&mut self, response_line: ResponseLine, file_type: ItemType) -> std::io::Result<()> {
+    /// Records if the external server of a discovered directory accepted a connection
+    fn record_external(&mut self, response_line: &MenuLine, connected: bool) {
+        let server_details = format!("{}:{}", response_line.server_name, response_line.server_port);
+        if let Some(item) = self.items.get_mut(&server_details, response_line.selector) {
+            if item.outcome == ItemOutcome::Pending {
+                item.outcome = ItemOutcome::External(connected);
+            }
+        }
+    }
+
+    fn handle_file(&mut self, response_line: MenuLine, file_type: ItemType, carry: bool) -> std::io::Result<()> {
         // Check if the file has been crawled before
-        if self.has_crawled(&*response_line.server_name, response_line.server_port, &*response_line.selector) { 
+        if self.has_crawled(response_line.server_name, response_line.server_port, response_line.selector) { 
             return Ok(()) 
         }
         
-        self.used.push((
-            Rc::clone(&response_line.server_name), 
-            response_line.server_port,
-            Rc::clone(&response_line.selector))
-        );
-        
+        let response_line = response_line.into_owned();
+
         // Request to send to the server
         let request = Request::new(
             response_line.selector, 
@@ -412,114 +796,77 @@ impl Crawler {
             file_type,
         );
 
-        let response = gopher::send_and_recv(&request).map_err(|error| {
-            debug_eprintln!("Error sending or receving {} file: {}", request.item_type.to_string(), error);
+        let response = self.fetch(&request, carry).map_err(|error| {
+            debug_eprintln!("Error sending or receving {} file: {}", request.item_type, error);
             error
         })?;
 
         match response.response_outcome {
             // Sucessful transaction
             ResponseOutcome::Complete => {
-                let f = Crawler::download_file(&request.selector, &response.buffer).map_err(|error| {
-                    debug_eprintln!("Error downloading {} file: {}", request.item_type.to_string(), error);
-                    error
-                })?;
-                match f.metadata() {
-                    Ok(metadata) => self.update_file_stats(metadata, &request, &response.buffer),
-                    Err(error) => {
-                        debug_eprintln!("Error accessing {} file metadata: {}", request.item_type.to_string(), error);
-                        return Err(error)
-                    }
-                }
+                self.storage
+                    .store(request.item_type, &request.server_details, &request.selector, &response.buffer)
+                    .map_err(|error| {
+                        debug_eprintln!("Error downloading {} file: {}", request.item_type, error);
+                        error
+                    })?;
+                self.update_smallest_contents(&request, &response.buffer);
             }
             // Unsucessful transaction
             _ => {
                 self.invalid_references.push((
-                    format!("{} {}", request.server_details.clone(), request.selector.to_string()),
-                    response.response_outcome
+                    format!("{} {}", request.server_details, request.selector),
+                    response.response_outcome,
+                    ReferenceOrigin::Item(Rc::clone(&request.server_details), Rc::clone(&request.selector))
                 ));
             }
         }
         Ok(())
     }
 
-    fn update_file_stats(&mut self, file_metadata: Metadata, request: &Request, buffer: &Vec<u8>) {
-        let file_size = file_metadata.len();
-        match request.item_type {
-            ItemType::Txt => {
-                self.ntxt += 1;
-                self.txt_files.push((
-                    Rc::clone(&request.server_details), 
-                    Rc::clone(&request.selector))
-                ); 
-
-                if file_size > self.largest_txt {
-                    self.largest_txt = file_size;
-                    self.largest_txt_selector = (
-                        Rc::clone(&request.server_details), 
-                        Rc::clone(&request.selector)
-                    );
-                }
+    /// Keeps the contents of a downloaded text file if it is the smallest so far.
+    /// Of text files with the same size, the first one downloaded is kept.
+    fn update_smallest_contents(&mut self, request: &Request, buffer: &[u8]) {
+        if request.item_type != ItemType::Txt {
+            return
+        }
+        if self.smallest_contents.as_ref().is_none_or(|(size, _)| buffer.len() < *size) {
+            self.smallest_contents = Some((buffer.len(), String::from_utf8_lossy(buffer).into_owned()));
+        }
+    }
 
-                if file_size < self.smallest_txt {
-                    self.smallest_txt = file_size;
-                    self.smallest_txt_selector = (
-                        Rc::clone(&request.server_details), 
-                        Rc::clone(&request.selector)
-                    );
-                    self.smallest_contents = str::from_utf8(buffer).expect("Ivalid UTF-8 sequence").to_string();  
-                }
-            },
-            ItemType::Bin => {
-                self.nbin += 1;
-                self.bin_files.push((
-                    Rc::clone(&request.server_details), 
-                    Rc::clone(&request.selector)
-                )); 
-
-                if file_size > self.largest_bin {
-                    self.largest_bin = file_size;
-                    self.largest_bin_selector = (
-                        Rc::clone(&request.server_details), 
-                        Rc::clone(&request.selector)
-                    );
-                }
+    /// Returns true if the crawl was started and nothing is left to crawl
+    pub fn is_complete(&self) -> bool {
+        !self.items.is_empty() && self.frontier.is_empty()
+    }
 
-                if file_size < self.smallest_bin {
-                    self.smallest_bin = file_size;
-                    self.smallest_bin_selector = (
-                        Rc::clone(&request.server_details), 
-                        Rc::clone(&request.selector)
-                    );
-                }
-            },
-            _ => (),
-        }
+    /// Returns the size and hash of a downloaded file
+    fn digest(&self, server_details: &str, selector: &str) -> Option<(u64, &String)> {
+        let item = self.items.get(server_details, selector)?;
+        Some((item.size?, item.hash.as_ref()?))
     }
 
-    fn has_crawled(&self, server_details: &str, server_port: u16, selector: &str) -> bool {
-        self.used.iter()
-            .any(|(used_server_details, used_server_port, used_selector)| {
-                **used_server_details == server_details && 
-                *used_server_port == server_port &&
-                **used_selector == selector
-        })
+    /// Returns true if an item was already requested (or carried forward)
+    fn has_crawled(&self, server_name: &str, server_port: u16, selector: &str) -> bool {
+        self.items.get(&format!("{server_name}:{server_port}"), selector)
+            .is_some_and(CrawledItem::is_requested)
     }
 
-    fn download_file(selector: &str, buffer: &[u8]) -> std::io::Result<File> {
-        // Remove the / prefix from the selector. Truncate long selector names
-        let file_name = &selector[1..min(selector.len(), MAX_FILENAME_LEN + 1)];
+    /// Writes every crawled menu to the storage and finishes it, so a crawl stored
+    /// in a folder (e.g. the default mirror in `OUTPUT_FOLDER`) can be served again
+    /// with `server::serve`. The root menu is always the first line of the manifest.
+    pub fn save_snapshot(&mut self) -> std::io::Result<()> {
+        let root_details = format!("{}:{}", self.root_server_name, self.root_server_port);
 
-        // Replace forward slashes with dashes to create a valid file name
-        let file_name = file_name.replace("/", "-");
-        
-        let file_path = format!("{}/{}", OUTPUT_FOLDER, &file_name);
-        let mut f = File::create(file_path).map_err(|error| {
-            debug_eprintln!("Unable to create new file: {error}");
-            error
-        })?;
-        f.write_all(buffer)?;
-        
-        Ok(f)
+        // Root menu first, so the snapshot knows which server it mirrors
+        let mut menus = self.menus.iter().collect::<Vec<_>>();
+        menus.sort_by_key(|menu| !(*menu.server_details == root_details && menu.selector.is_empty()));
+
+        for menu in menus {
+            let contents = menu.to_menu().serialize()
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+            self.storage.store(ItemType::Dir, &menu.server_details, &menu.selector, contents.as_bytes())?;
+        }
+        self.storage.finish()
     }
 }
\ No newline at end of file
@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+
+use crate::gopher::response::ItemType;
+
+use super::{
+    graph::LinkGraph,
+    item::CrawledItems,
+    report::{sort_by_line, ReportItem}
+};
+
+/// Value items of the link graph are ranked by in the text report
+type NodeKey = fn(&GraphNode) -> usize;
+
+/// Represents the structure of the link graph of a crawl, for maintainers
+/// reorganising their servers.
+///
+/// The root menu is the first discovered item. Its depth is 0.
+///
+/// * `link_count`: Number of links from menus to items, see `LinkGraph`
+/// * `max_depth`: Largest depth of an item reached from the root menu
+/// * `nodes`: The depth and degrees of every discovered item, in the order
+///   they were discovered
+/// * `cycles`: Every group of menus which can all be reached from each other
+///   (strongly connected component), each sorted by line and listed by the line
+///   of its first menu
+/// * `single_points_of_failure`: Every menu which is the only way to reach
+///   other items from the root menu, the menus cutting off the most items first
+/// * `dead_end_menus`: Every received menu which does not link to a directory,
+///   text file or binary file
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphAnalysis {
+    pub link_count: usize,
+    pub max_depth: usize,
+    pub nodes: Vec<GraphNode>,
+    pub cycles: Vec<Vec<ReportItem>>,
+    pub single_points_of_failure: Vec<CutOff>,
+    pub dead_end_menus: Vec<ReportItem>,
+}
+
+/// Represents an item of the link graph.
+///
+/// * `item`: The item
+/// * `depth`: Fewest links to follow from the root menu to the item. Nothing if
+///   the item can not be reached from the root menu. Unlike the depth the item
+///   was discovered at, this does not depend on the order menus were crawled in.
+/// * `in_degree`: Number of menus linking to the item
+/// * `out_degree`: Number of items the item links to. Always 0 for files.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub item: ReportItem,
+    pub depth: Option<usize>,
+    pub in_degree: usize,
+    pub out_degree: usize,
+}
+
+/// Represents the items which can only be reached through a menu.
+///
+/// * `menu`: The menu
+/// * `items`: Every item which could not be reached from the root menu without
+///   the menu, sorted by line
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CutOff {
+    pub menu: ReportItem,
+    pub items: Vec<ReportItem>,
+}
+
+impl GraphAnalysis {
+    /// Analyses the link graph of the discovered items
+    pub fn of(items: &CrawledItems) -> GraphAnalysis {
+        const ROOT: usize = 0;
+
+        let graph = LinkGraph::of(items);
+        let depths = graph.depths(ROOT);
+        let in_degrees = graph.in_degrees();
+        let out_degrees = graph.out_degrees();
+        let report_items = |nodes: &[usize]| {
+            sort_by_line(nodes.iter().map(|&node| ReportItem::from(&items[node])).collect(), ReportItem::line)
+        };
+        let is_received_menu = |node: usize| items[node].item_type == ItemType::Dir && items[node].is_received();
+
+        let nodes = items.iter()
+            .enumerate()
+            .map(|(node, item)| GraphNode {
+                item: ReportItem::from(item),
+                depth: depths[node],
+                in_degree: in_degrees[node],
+                out_degree: out_degrees[node],
+            })
+            .collect();
+
+        let mut cycles = graph.cycles().iter().map(|nodes| report_items(nodes)).collect::<Vec<_>>();
+        cycles.sort_by_cached_key(|menus| menus[0].line().to_lowercase());
+
+        let mut single_points_of_failure = graph.cut_offs(ROOT).into_iter()
+            .enumerate()
+            .filter(|(_, cut_off)| !cut_off.is_empty())
+            .map(|(node, cut_off)| CutOff { menu: ReportItem::from(&items[node]), items: report_items(&cut_off) })
+            .collect::<Vec<_>>();
+        single_points_of_failure = sort_by_line(single_points_of_failure, |cut_off| cut_off.menu.line());
+        single_points_of_failure.sort_by_key(|cut_off| std::cmp::Reverse(cut_off.items.len()));
+
+        let dead_ends = (0..items.len())
+            .filter(|&node| is_received_menu(node) && out_degrees[node] == 0)
+            .collect::<Vec<_>>();
+
+        GraphAnalysis {
+            link_count: graph.links.len(),
+            max_depth: depths.iter().flatten().copied().max().unwrap_or(0),
+            nodes,
+            cycles,
+            single_points_of_failure,
+            dead_end_menus: report_items(&dead_ends),
+        }
+    }
+
+    /// Renders the analysis as a section of the text report
+    ///
+    /// # Arguments
+    /// * `top_n`: Number of the deepest and most linked items to list
+    pub(super) fn render(&self, top_n: usize) -> String {
+        let mut section = format!("\tLink graph: {} items, {} links, maximum depth {}\n",
+            self.nodes.len(), self.link_count, self.max_depth);
+
+        let top = |key: NodeKey| {
+            let mut nodes = self.nodes.iter().filter(|node| key(node) > 0).collect::<Vec<_>>();
+            nodes.sort_by_key(|node| std::cmp::Reverse(key(node)));
+            nodes.truncate(top_n);
+            nodes
+        };
+        let lists: [(&str, &str, NodeKey); 3] = [
+            ("Deepest items", "depth", |node| node.depth.unwrap_or(0)),
+            ("Most linked items", "linked from", |node| node.in_degree),
+            ("Menus with the most links", "links to", |node| node.out_degree),
+        ];
+        for (heading, label, key) in lists {
+            let nodes = top(key);
+            if nodes.is_empty() {
+                continue
+            }
+            section += &format!("\t\t{heading}:\n");
+            for node in nodes {
+                section += &format!("\t\t\t{label} {}: {}\n", key(node), node.item.line());
+            }
+        }
+        section += "\n";
+
+        section += &format!("\tMenu cycles: {}\n", self.cycles.len());
+        for menus in &self.cycles {
+            let lines = menus.iter().map(ReportItem::line).collect::<Vec<_>>();
+            section += &format!("\t\t{}\n", lines.join(", "));
+        }
+        section += "\n";
+
+        section += &format!("\tSingle points of failure (menus which are the only way to reach items): {}\n",
+            self.single_points_of_failure.len());
+        for cut_off in &self.single_points_of_failure {
+            section += &format!("\t\t{} items only reached through {}\n", cut_off.items.len(), cut_off.menu.line());
+        }
+        section += "\n";
+
+        section += &format!("\tDead-end menus: {}\n", self.dead_end_menus.len());
+        for menu in &self.dead_end_menus {
+            section += &format!("\t\t{}\n", menu.line());
+        }
+        section + "\n"
+    }
+}
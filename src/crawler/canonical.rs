@@ -0,0 +1,311 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    net::IpAddr,
+    rc::Rc,
+    str::FromStr
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::gopher::{
+    request::Request,
+    response::{ItemType, ResponseOutcome}
+};
+use crate::transport::Transport;
+
+/// Selector of the file servers describe their capabilities in
+pub const CAPS_SELECTOR: &str = "caps.txt";
+
+/// Represents which references to an item are treated as the same item.
+///
+/// * `fold_host_case`: Hostnames differing only in case are the same server
+/// * `trailing_slash`: Selectors differing only in trailing slashes are the same
+///   item, e.g. `/dir` and `/dir/`. `/` stays apart from the empty selector.
+/// * `resolve_hosts`: Hostnames resolving to the same address (and addresses) are
+///   the same server
+/// * `caps_paths`: Selectors are normalised with the path rules of the caps.txt
+///   of the root server, e.g. `/a/./b/../c` is `/a/c`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CanonicalRules {
+    pub fold_host_case: bool,
+    pub trailing_slash: bool,
+    pub resolve_hosts: bool,
+    pub caps_paths: bool,
+}
+
+/// Represents the path rules of a server, as described by its caps.txt.
+///
+/// * `delimiter`: Seperates the components of a path (`PathDelimeter`)
+/// * `identity`: Component referring to the current folder (`PathIdentity`)
+/// * `parent`: Component referring to the parent folder (`PathParent`)
+/// * `parent_double`: An empty component refers to the parent folder as well
+///   (`PathParentDouble`)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathRules {
+    pub delimiter: String,
+    pub identity: String,
+    pub parent: String,
+    pub parent_double: bool,
+}
+
+/// Represents a reference to an item which was merged into an equivalent
+/// reference found earlier.
+///
+/// * `server_details`: hostname:port of the server, as referenced
+/// * `selector`: Selector of the item, as referenced
+/// * `canonical_details`: hostname:port of the item it was merged into
+/// * `canonical_selector`: Selector of the item it was merged into
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergedVariant {
+    pub server_details: String,
+    pub selector: String,
+    pub canonical_details: String,
+    pub canonical_selector: String,
+}
+
+/// Maps every reference to an item to the first equivalent reference found, so
+/// equivalent references are crawled (and counted) once.
+///
+/// * `rules`: Which references are equivalent
+/// * `path_rules`: Path rules of the root server, used if `rules.caps_paths`
+/// * `caps_server`: Canonical server details of the root server, the only server
+///   whose selectors follow `path_rules`. Nothing until its caps.txt was requested.
+/// * `addresses`: Lowest address every resolved hostname resolved to, so the
+///   order of the addresses does not matter. Nothing for hostnames which could
+///   not be resolved.
+/// * `hosts`: Hostname first found for every canonical server, keyed by the
+///   canonical server details
+/// * `selectors`: Selector first found for every canonical selector, keyed by the
+///   canonical server details and then the canonical selector
+/// * `merged`: Every reference merged into an earlier one, in the order they were
+///   found
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Canonicalizer {
+    rules: CanonicalRules,
+    path_rules: PathRules,
+    caps_server: Option<String>,
+    addresses: HashMap<String, Option<IpAddr>>,
+    hosts: HashMap<String, String>,
+    selectors: HashMap<String, HashMap<String, String>>,
+    merged: Vec<MergedVariant>,
+}
+
+impl Default for CanonicalRules {
+    /// Only hostnames are case-folded, as DNS names are case-insensitive
+    fn default() -> CanonicalRules {
+        CanonicalRules {
+            fold_host_case: true,
+            trailing_slash: false,
+            resolve_hosts: false,
+            caps_paths: false,
+        }
+    }
+}
+
+impl FromStr for CanonicalRules {
+    type Err = String;
+
+    /// Parses `none`, or a comma seperated list of the rules to enable:
+    /// `host-case`, `trailing-slash`, `resolve-hosts` and `caps-paths`
+    fn from_str(names: &str) -> Result<CanonicalRules, String> {
+        let mut rules = CanonicalRules {
+            fold_host_case: false,
+            trailing_slash: false,
+            resolve_hosts: false,
+            caps_paths: false,
+        };
+        if names == "none" {
+            return Ok(rules)
+        }
+        for name in names.split(',') {
+            match name {
+                "host-case"       => rules.fold_host_case = true,
+                "trailing-slash"  => rules.trailing_slash = true,
+                "resolve-hosts"   => rules.resolve_hosts = true,
+                "caps-paths"      => rules.caps_paths = true,
+                _ => return Err(format!("Unknown canonicalization rule: {name}")),
+            }
+        }
+        Ok(rules)
+    }
+}
+
+impl Default for PathRules {
+    /// The path rules of a server without a caps.txt, as suggested by the
+    /// caps.txt specification
+    fn default() -> PathRules {
+        PathRules {
+            delimiter: String::from("/"),
+            identity: String::from("."),
+            parent: String::from(".."),
+            parent_double: false,
+        }
+    }
+}
+
+impl PathRules {
+    /// Parses the path rules of a caps.txt. Rules missing from it keep their
+    /// defaults, and other capabilities are ignored.
+    pub fn parse(caps: &str) -> PathRules {
+        let mut rules = PathRules::default();
+        for line in caps.lines() {
+            let Some((key, value)) = line.split_once('=') else { continue };
+            let value = value.trim();
+            match key.trim() {
+                "PathDelimeter" if !value.is_empty() => rules.delimiter = value.to_string(),
+                "PathIdentity"      => rules.identity = value.to_string(),
+                "PathParent"        => rules.parent = value.to_string(),
+                "PathParentDouble"  => rules.parent_double = value.eq_ignore_ascii_case("TRUE"),
+                _ => (),
+            }
+        }
+        rules
+    }
+
+    /// Resolves the identity and parent components of a path, and removes empty
+    /// components. Selectors which do not start with the delimiter are not paths,
+    /// and are returned as they are. A trailing delimiter is kept.
+    pub fn normalise(&self, selector: &str) -> String {
+        let Some(path) = selector.strip_prefix(self.delimiter.as_str()) else {
+            return selector.to_string()
+        };
+        let trailing = path.ends_with(self.delimiter.as_str());
+        let path = path.strip_suffix(self.delimiter.as_str()).unwrap_or(path);
+
+        let mut components = Vec::new();
+        for component in path.split(self.delimiter.as_str()) {
+            if component == self.parent || (component.is_empty() && self.parent_double) {
+                components.pop();
+            } else if !component.is_empty() && component != self.identity {
+                components.push(component);
+            }
+        }
+
+        let mut normalised = format!("{}{}", self.delimiter, components.join(&self.delimiter));
+        if trailing && !components.is_empty() {
+            normalised.push_str(&self.delimiter);
+        }
+        normalised
+    }
+}
+
+impl fmt::Display for MergedVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} merged into {}: {}", self.server_details, self.selector, self.canonical_details, self.canonical_selector)
+    }
+}
+
+impl Canonicalizer {
+    /// Constructs a canonicalizer with the given rules, which has not seen any
+    /// references yet
+    pub fn new(rules: CanonicalRules) -> Canonicalizer {
+        Canonicalizer { rules, ..Default::default() }
+    }
+
+    /// Returns the rules deciding which references are equivalent
+    pub fn rules(&self) -> CanonicalRules {
+        self.rules
+    }
+
+    /// Replaces the rules. References already seen keep the item they were
+    /// mapped to.
+    pub fn set_rules(&mut self, rules: CanonicalRules) {
+        self.rules = rules;
+    }
+
+    /// Returns every reference merged into an earlier one, in the order they were
+    /// found
+    pub fn merged(&self) -> &[MergedVariant] {
+        &self.merged
+    }
+
+    /// Requests the caps.txt of a server and takes its path rules for the
+    /// selectors of that server, if caps.txt path rules are enabled. Servers
+    /// without a caps.txt keep the default rules.
+    pub fn load_caps(&mut self, transport: &mut dyn Transport, server_name: &Rc<String>, server_port: u16) {
+        if !self.rules.caps_paths {
+            return
+        }
+        self.caps_server = Some(format!("{}:{}", self.canonical_host(transport, server_name, server_port), server_port));
+        let request = Request::new(Rc::new(String::from(CAPS_SELECTOR)), Rc::clone(server_name), server_port, ItemType::Txt);
+        if let Ok(response) = transport.send_and_recv(&request) {
+            if response.response_outcome == ResponseOutcome::Complete {
+                self.path_rules = PathRules::parse(&String::from_utf8_lossy(&response.buffer));
+            }
+        }
+    }
+
+    /// Maps a reference to the first equivalent reference found. References
+    /// which are not equivalent to an earlier one are mapped to themselves, and
+    /// references mapped to a different one are recorded as merged.
+    ///
+    /// # Arguments
+    /// * `transport`: Used to resolve hostnames, if hostnames are resolved
+    /// * `server_name`: Hostname of the server, as referenced
+    /// * `server_port`: Port number of the server
+    /// * `selector`: Selector of the item, as referenced
+    ///
+    /// # Returns
+    /// The hostname and selector of the first equivalent reference
+    pub fn canonicalize(&mut self, transport: &mut dyn Transport, server_name: &str, server_port: u16, selector: &str) -> (String, String) {
+        let canonical_details = format!("{}:{}", self.canonical_host(transport, server_name, server_port), server_port);
+        let host = self.hosts.entry(canonical_details.clone())
+            .or_insert_with(|| server_name.to_string())
+            .clone();
+
+        let canonical_selector = self.canonical_selector(&canonical_details, selector);
+        let first_selector = self.selectors.entry(canonical_details)
+            .or_default()
+            .entry(canonical_selector)
+            .or_insert_with(|| selector.to_string())
+            .clone();
+
+        if host != server_name || first_selector != selector {
+            let variant = MergedVariant {
+                server_details: format!("{server_name}:{server_port}"),
+                selector: selector.to_string(),
+                canonical_details: format!("{host}:{server_port}"),
+                canonical_selector: first_selector.clone(),
+            };
+            if !self.merged.contains(&variant) {
+                self.merged.push(variant);
+            }
+        }
+        (host, first_selector)
+    }
+
+    /// Returns the form of a hostname which is the same for every equivalent
+    /// hostname. Hostnames which can not be resolved are compared by name.
+    fn canonical_host(&mut self, transport: &mut dyn Transport, server_name: &str, server_port: u16) -> String {
+        if self.rules.resolve_hosts {
+            let address = *self.addresses.entry(server_name.to_string()).or_insert_with(|| {
+                transport.resolve(&format!("{server_name}:{server_port}"))
+                    .ok()
+                    .and_then(|addresses| addresses.into_iter().min())
+            });
+            if let Some(address) = address {
+                return address.to_string()
+            }
+        }
+        match self.rules.fold_host_case {
+            true => server_name.to_ascii_lowercase(),
+            false => server_name.to_string(),
+        }
+    }
+
+    /// Returns the form of a selector which is the same for every equivalent
+    /// selector on a server. Only the selectors of the server the caps.txt was
+    /// requested from follow its path rules.
+    fn canonical_selector(&self, canonical_details: &str, selector: &str) -> String {
+        let mut selector = match self.rules.caps_paths && self.caps_server.as_deref() == Some(canonical_details) {
+            true => self.path_rules.normalise(selector),
+            false => selector.to_string(),
+        };
+        // A selector made of slashes only keeps one of them
+        if self.rules.trailing_slash {
+            selector.truncate(selector.trim_end_matches('/').len().max(1));
+        }
+        selector
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+    rc::Rc
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::gopher::response::ResponseOutcome;
+use crate::storage::StorageState;
+
+use super::{canonical::Canonicalizer, item::CrawledItems, trap::SpiderTraps, CrawledMenu, Crawler, MenuLocation, ReferenceOrigin, Task};
+
+/// Version of the checkpoint format. Checkpoints of other versions are rejected.
+const CHECKPOINT_VERSION: u32 = 1;
+
+/// Represents the state of a crawl at a point in time: the frontier and the
+/// discovered items, from which the visited set and statistics are derived.
+///
+/// Checkpoints are stored as JSON. See `Crawler` for the meaning of each field.
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    version: u32,
+
+    root_server_name: Rc<String>,
+    root_server_port: u16,
+    frontier: Vec<Task>,
+
+    items: CrawledItems,
+    smallest_contents: Option<(usize, String)>,
+
+    error_items: Vec<(String, Vec<MenuLocation>)>,
+    external_servers: Vec<(Rc<String>, u16, bool)>,
+    invalid_references: Vec<(String, ResponseOutcome, ReferenceOrigin)>,
+
+    menus: Vec<CrawledMenu>,
+    open_menus: Vec<CrawledMenu>,
+
+    unchanged: Vec<(Rc<String>, Rc<String>)>,
+    carried: Vec<(Rc<String>, Rc<String>)>,
+
+    top_n: usize,
+    trap_threshold: usize,
+    traps: SpiderTraps,
+    canonical: Canonicalizer,
+
+    storage: Option<StorageState>,
+}
+
+impl Checkpoint {
+    /// Captures the current state of a crawl
+    pub(super) fn capture(crawler: &Crawler) -> Checkpoint {
+        Checkpoint {
+            version: CHECKPOINT_VERSION,
+
+            root_server_name: Rc::clone(&crawler.root_server_name),
+            root_server_port: crawler.root_server_port,
+            frontier: crawler.frontier.clone(),
+
+            items: crawler.items.clone(),
+            smallest_contents: crawler.smallest_contents.clone(),
+
+            error_items: crawler.error_items.clone(),
+            external_servers: crawler.external_servers.clone(),
+            invalid_references: crawler.invalid_references.clone(),
+
+            menus: crawler.menus.clone(),
+            open_menus: crawler.open_menus.clone(),
+
+            unchanged: crawler.unchanged.clone(),
+            carried: crawler.carried.clone(),
+
+            top_n: crawler.top_n,
+            trap_threshold: crawler.trap_threshold,
+            traps: crawler.traps.clone(),
+            canonical: crawler.canonical.clone(),
+
+            storage: crawler.storage.state(),
+        }
+    }
+
+    /// Puts the state back into a crawler, replacing its own
+    pub(super) fn restore(self, crawler: &mut Crawler) {
+        crawler.root_server_name = self.root_server_name;
+        crawler.root_server_port = self.root_server_port;
+        crawler.frontier = self.frontier;
+
+        crawler.items = self.items;
+        crawler.smallest_contents = self.smallest_contents;
+
+        crawler.error_items = self.error_items;
+        crawler.external_servers = self.external_servers;
+        crawler.invalid_references = self.invalid_references;
+
+        crawler.menus = self.menus;
+        crawler.open_menus = self.open_menus;
+
+        crawler.unchanged = self.unchanged;
+        crawler.carried = self.carried;
+
+        crawler.top_n = self.top_n;
+        crawler.trap_threshold = self.trap_threshold;
+        crawler.traps = self.traps;
+        crawler.canonical = self.canonical;
+
+        if let Some(state) = &self.storage {
+            crawler.storage.restore(state.clone());
+        }
+        crawler.storage_state = self.storage;
+    }
+
+    /// Writes the checkpoint to `path`. The previous checkpoint is only replaced
+    /// once the new one was written completely, so a crawl killed while writing
+    /// keeps its last checkpoint.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+
+        let mut file = BufWriter::new(File::create(&temp_path)?);
+        serde_json::to_writer(&mut file, self)?;
+        file.flush()?;
+        drop(file);
+
+        fs::rename(temp_path, path)
+    }
+
+    /// Reads a checkpoint written by `Checkpoint::write`
+    ///
+    /// # Returns
+    /// The checkpoint if it is valid and of the current version. Otherwise, returns
+    /// an IO error.
+    pub fn read(path: &Path) -> io::Result<Checkpoint> {
+        let checkpoint: Checkpoint = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        if checkpoint.version != CHECKPOINT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: unsupported checkpoint version {}", path.display(), checkpoint.version)
+            ));
+        }
+        Ok(checkpoint)
+    }
+}
@@ -0,0 +1,269 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    rc::Rc
+};
+
+use serde::Serialize;
+
+use crate::gopher::response::{ItemType, ResponseOutcome};
+
+use super::Crawler;
+
+/// Represents what changed between two crawls of the same servers.
+///
+/// Every list is sorted by server details and then selector (or, for invalid
+/// references, by the details of the request).
+///
+/// * `added_dirs`, `removed_dirs`: Directories only found by the new (old) crawl
+/// * `added_txt_files`, `removed_txt_files`: Text files only found by the new
+///   (old) crawl
+/// * `added_bin_files`, `removed_bin_files`: Binary files only found by the new
+///   (old) crawl
+/// * `changed_files`: Files downloaded by both crawls whose size or contents changed
+/// * `new_invalid_references`: Invalid references only found by the new crawl
+/// * `fixed_invalid_references`: Invalid references only found by the old crawl
+/// * `external_servers`: External servers which appeared, disappeared or changed
+///   reachability
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct CrawlDiff {
+    pub added_dirs: Vec<DiffItem>,
+    pub removed_dirs: Vec<DiffItem>,
+    pub added_txt_files: Vec<DiffItem>,
+    pub removed_txt_files: Vec<DiffItem>,
+    pub added_bin_files: Vec<DiffItem>,
+    pub removed_bin_files: Vec<DiffItem>,
+    pub changed_files: Vec<ChangedFile>,
+    pub new_invalid_references: Vec<DiffReference>,
+    pub fixed_invalid_references: Vec<DiffReference>,
+    pub external_servers: Vec<ExternalServerChange>,
+}
+
+/// Represents an item found by only one of the crawls.
+///
+/// * `server_details`: hostname:port of the server providing the item
+/// * `selector`: String used to request the item
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct DiffItem {
+    pub server_details: String,
+    pub selector: String,
+}
+
+/// Represents a file whose size or contents differ between the crawls.
+///
+/// * `item_type`: The type of the file
+/// * `server_details`: hostname:port of the server providing the file
+/// * `selector`: String used to request the file
+/// * `old_size`, `new_size`: Size of the file (bytes) in the old (new) crawl
+/// * `old_hash`, `new_hash`: Hex encoded SHA-256 hash of the file in the old
+///   (new) crawl
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ChangedFile {
+    pub item_type: ItemType,
+    pub server_details: String,
+    pub selector: String,
+    pub old_size: u64,
+    pub new_size: u64,
+    pub old_hash: String,
+    pub new_hash: String,
+}
+
+/// Represents an invalid reference found by only one of the crawls.
+///
+/// * `details`: Details of the request
+/// * `outcome`: Outcome of the response
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DiffReference {
+    pub details: String,
+    pub outcome: ResponseOutcome,
+}
+
+/// Represents an external server whose reachability differs between the crawls.
+///
+/// * `server_name`: Hostname of the external server
+/// * `server_port`: Port number of the external server
+/// * `before`: If the server accepted a connection in the old crawl. Nothing if
+///   the old crawl did not find the server.
+/// * `after`: If the server accepted a connection in the new crawl. Nothing if
+///   the new crawl did not find the server.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ExternalServerChange {
+    pub server_name: String,
+    pub server_port: u16,
+    pub before: Option<bool>,
+    pub after: Option<bool>,
+}
+
+impl CrawlDiff {
+    /// Compares two crawls of the same servers, e.g. two crawls restored with
+    /// `Crawler::resume` from the last checkpoint of a completed crawl.
+    ///
+    /// Files are only compared if both crawls know their size and hash, which
+    /// checkpoints written before diffs were supported do not.
+    ///
+    /// # Arguments
+    /// * `old`: The earlier crawl
+    /// * `new`: The later crawl
+    pub fn between(old: &Crawler, new: &Crawler) -> CrawlDiff {
+        let (added_dirs, removed_dirs) = added_and_removed(old, new, ItemType::Dir);
+        let (added_txt_files, removed_txt_files) = added_and_removed(old, new, ItemType::Txt);
+        let (added_bin_files, removed_bin_files) = added_and_removed(old, new, ItemType::Bin);
+
+        // Files found by both crawls
+        let mut changed_files = Vec::new();
+        for item_type in [ItemType::Txt, ItemType::Bin] {
+            let old_files = items(old, item_type);
+            for item in items(new, item_type).intersection(&old_files) {
+                let (Some((old_size, old_hash)), Some((new_size, new_hash))) = (old.digest(&item.server_details, &item.selector), new.digest(&item.server_details, &item.selector)) else {
+                    continue
+                };
+                if old_size != new_size || old_hash != new_hash {
+                    changed_files.push(ChangedFile {
+                        item_type,
+                        server_details: item.server_details.clone(),
+                        selector: item.selector.clone(),
+                        old_size,
+                        new_size,
+                        old_hash: old_hash.clone(),
+                        new_hash: new_hash.clone(),
+                    });
+                }
+            }
+        }
+
+        let old_servers = external_servers(&old.external_servers);
+        let new_servers = external_servers(&new.external_servers);
+        let external_servers = old_servers.keys().chain(new_servers.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter_map(|server| {
+                let before = old_servers.get(server).copied();
+                let after = new_servers.get(server).copied();
+                (before != after).then(|| ExternalServerChange {
+                    server_name: server.0.clone(),
+                    server_port: server.1,
+                    before,
+                    after,
+                })
+            })
+            .collect();
+
+        CrawlDiff {
+            added_dirs,
+            removed_dirs,
+            added_txt_files,
+            removed_txt_files,
+            added_bin_files,
+            removed_bin_files,
+            changed_files,
+            new_invalid_references: only_in(&new.invalid_references, &old.invalid_references),
+            fixed_invalid_references: only_in(&old.invalid_references, &new.invalid_references),
+            external_servers,
+        }
+    }
+
+    /// Returns true if nothing changed between the crawls
+    pub fn is_empty(&self) -> bool {
+        *self == CrawlDiff::default()
+    }
+
+    /// Renders the differences as text, in the style of the crawl report
+    pub fn render(&self) -> String {
+        let format_items = |heading: &str, items: &[DiffItem]| {
+            let lines = items.iter()
+                .map(|item| format!("\n\t\t{}: {}", item.server_details, item.selector))
+                .collect::<String>();
+            format!("\t{}: {}{}\n\n", heading, items.len(), lines)
+        };
+
+        let changed_files = self.changed_files.iter()
+            .map(|file| {
+                let contents = if file.old_hash != file.new_hash { ", contents changed" } else { "" };
+                format!("\n\t\t{}: {} ({} -> {} bytes{})",
+                    file.server_details, file.selector, file.old_size, file.new_size, contents)
+            })
+            .collect::<String>();
+
+        let format_references = |heading: &str, references: &[DiffReference]| {
+            let lines = references.iter()
+                .map(|reference| format!("\n\t\t{} {}", reference.outcome, reference.details))
+                .collect::<String>();
+            format!("\t{}: {}{}\n\n", heading, references.len(), lines)
+        };
+
+        let external_servers = self.external_servers.iter()
+            .map(|server| {
+                let change = match (server.before, server.after) {
+                    (None, Some(true))  => "appeared, connected successfully",
+                    (None, _)           => "appeared, did not connect",
+                    (Some(_), None)     => "disappeared",
+                    (_, Some(true))     => "now connects",
+                    (_, _)              => "no longer connects",
+                };
+                format!("\n\t\t{}:{} {}", server.server_name, server.server_port, change)
+            })
+            .collect::<String>();
+
+        format!(
+            "\nSTART CRAWL DIFF\n\n\
+            {}{}{}{}{}{}\
+            \tChanged files: {}{}\n\n\
+            {}{}\
+            \tChanged external servers: {}{}\n\n\
+            END CRAWL DIFF",
+            format_items("Added Gopher directories", &self.added_dirs),
+            format_items("Removed Gopher directories", &self.removed_dirs),
+            format_items("Added simple text files", &self.added_txt_files),
+            format_items("Removed simple text files", &self.removed_txt_files),
+            format_items("Added binary files", &self.added_bin_files),
+            format_items("Removed binary files", &self.removed_bin_files),
+            self.changed_files.len(),
+            changed_files,
+            format_references("New invalid references", &self.new_invalid_references),
+            format_references("Fixed invalid references", &self.fixed_invalid_references),
+            self.external_servers.len(),
+            external_servers,
+        )
+    }
+}
+
+/// Returns every received item of a type as a sorted set
+fn items(crawler: &Crawler, item_type: ItemType) -> BTreeSet<DiffItem> {
+    crawler.items.received(item_type)
+        .map(|item| DiffItem {
+            server_details: item.server_details(),
+            selector: item.selector.clone()
+        })
+        .collect()
+}
+
+/// Returns the received items of a type only in `new` and the ones only in `old`
+fn added_and_removed(old: &Crawler, new: &Crawler, item_type: ItemType) -> (Vec<DiffItem>, Vec<DiffItem>) {
+    let (old, new) = (items(old, item_type), items(new, item_type));
+    (
+        new.difference(&old).cloned().collect(),
+        old.difference(&new).cloned().collect(),
+    )
+}
+
+/// Returns the invalid references in `references` but not in `other`, sorted by 
+/// the details of the request
+fn only_in<T>(references: &[(String, ResponseOutcome, T)], other: &[(String, ResponseOutcome, T)]) -> Vec<DiffReference> {
+    let other = other.iter()
+        .map(|(details, outcome, _)| (details, *outcome))
+        .collect::<HashSet<_>>();
+    let mut only = references.iter()
+        .filter(|(details, outcome, _)| !other.contains(&(details, *outcome)))
+        .map(|(details, outcome, _)| DiffReference { details: details.clone(), outcome: *outcome })
+        .collect::<Vec<_>>();
+    only.sort_by(|a, b| a.details.cmp(&b.details));
+    only.dedup();
+    only
+}
+
+/// Returns if every external server accepted a connection, keyed by (server
+/// name, server port). Servers found more than once keep their last attempt.
+fn external_servers(servers: &[(Rc<String>, u16, bool)]) -> BTreeMap<(String, u16), bool> {
+    servers.iter()
+        .map(|(server_name, server_port, connected)| ((server_name.to_string(), *server_port), *connected))
+        .collect()
+}
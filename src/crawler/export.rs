@@ -0,0 +1,86 @@
+use std::io::{self, Write};
+
+use super::item::CrawledItem;
+
+/// Columns of an export, in order
+const COLUMNS: [&str; 12] = [
+    "host", "port", "selector", "item_type", "display_string", "size",
+    "outcome", "depth", "referrer_server", "referrer_selector", "latency_ms", "hash"
+];
+
+/// Represents the formats items can be exported in.
+///
+/// * `Csv`: Comma seperated values as specified by RFC 4180
+/// * `Tsv`: Tab seperated values, with tabs, line breaks and backslashes in
+///   fields escaped as `\t`, `\n`, `\r` and `\\`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Tsv,
+}
+
+impl CrawledItem {
+    /// Returns the fields of the row, in the order of `COLUMNS`
+    fn fields(&self) -> [String; 12] {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        [
+            self.server_name.clone(),
+            self.server_port.to_string(),
+            self.selector.clone(),
+            self.item_type.to_char().to_string(),
+            self.display_string.clone(),
+            optional(self.size.map(|size| size.to_string())),
+            self.outcome.to_string(),
+            self.depth.to_string(),
+            optional(self.referrer().map(|referrer| referrer.server_details.clone())),
+            optional(self.referrer().map(|referrer| referrer.selector.clone())),
+            optional(self.latency.map(|latency| format!("{:.3}", latency.as_secs_f64() * 1000.0))),
+            optional(self.hash.clone()),
+        ]
+    }
+}
+
+/// Writes items as a table with a header row and one row per item.
+///
+/// The item type is written as its Gopher type character, the referrer as the
+/// server details and selector of the menu the item was discovered in (in
+/// seperate columns), and the latency in milliseconds. Unknown values are left
+/// empty.
+///
+/// # Arguments
+/// * `writer`: Where the table is written to
+/// * `items`: Items to export, one row each
+/// * `format`: Format of the table
+///
+/// # Returns
+/// Nothing if sucessfull. Otherwise, returns an IO error.
+pub fn write_items(writer: &mut impl Write, items: &[CrawledItem], format: ExportFormat) -> io::Result<()> {
+    let (separator, escape): (&str, fn(&str) -> String) = match format {
+        ExportFormat::Csv => (",", escape_csv),
+        ExportFormat::Tsv => ("\t", escape_tsv),
+    };
+
+    writeln!(writer, "{}", COLUMNS.join(separator))?;
+    for item in items {
+        let fields = item.fields().iter().map(|field| escape(field)).collect::<Vec<_>>();
+        writeln!(writer, "{}", fields.join(separator))?;
+    }
+    writer.flush()
+}
+
+/// Quotes a CSV field if it contains a comma, quote or line break
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Escapes the characters of a TSV field which would break the table
+fn escape_tsv(field: &str) -> String {
+    field.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
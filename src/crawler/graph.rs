@@ -0,0 +1,439 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Write}
+};
+
+use crate::gopher::response::{ItemType, ResponseOutcome};
+
+use super::{
+    html::title,
+    item::{CrawledItem, CrawledItems, ItemOutcome},
+    Crawler
+};
+
+/// Attributes of the nodes of a GraphML graph (name, type) pairs
+const GRAPHML_KEYS: [(&str, &str); 9] = [
+    ("label", "string"), ("server", "string"), ("selector", "string"),
+    ("item_type", "string"), ("outcome", "string"), ("size", "long"),
+    ("depth", "int"), ("fill_color", "string"), ("outline_color", "string"),
+];
+
+/// Represents the formats a link graph can be exported in.
+///
+/// * `Dot`: Graphviz DOT, with a cluster per server
+/// * `GraphMl`: GraphML, with a nested graph per server
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    GraphMl,
+}
+
+/// Represents the links from menus to the items they list. Nodes are the
+/// discovered items, identified by their position in `Crawler::items`.
+///
+/// * `node_count`: Number of nodes, i.e. discovered items
+/// * `links`: Every (menu, item) pair of a menu linking to an item, sorted by
+///   menu. The links of a menu are in the order the items were discovered. A
+///   menu listing an item more than once links to it once.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LinkGraph {
+    pub node_count: usize,
+    pub links: Vec<(usize, usize)>,
+}
+
+impl LinkGraph {
+    /// Builds the link graph from the menus linking to each item
+    pub fn of(items: &CrawledItems) -> LinkGraph {
+        let mut links = items.iter()
+            .enumerate()
+            .flat_map(|(index, item)| item.referrers.iter()
+                .filter_map(|referrer| items.position(&referrer.server_details, &referrer.selector))
+                .map(move |menu| (menu, index)))
+            .collect::<Vec<_>>();
+        // Lines of a menu listing the same item are next to each other
+        links.sort_by_key(|(menu, _)| *menu);
+        links.dedup();
+
+        LinkGraph { node_count: items.len(), links }
+    }
+
+    /// Returns the nodes each node links to, in the order of `links`
+    pub fn successors(&self) -> Vec<Vec<usize>> {
+        let mut successors = vec![Vec::new(); self.node_count];
+        for &(menu, item) in &self.links {
+            successors[menu].push(item);
+        }
+        successors
+    }
+
+    /// Returns the number of links to each node
+    pub fn in_degrees(&self) -> Vec<usize> {
+        let mut degrees = vec![0; self.node_count];
+        for &(_, item) in &self.links {
+            degrees[item] += 1;
+        }
+        degrees
+    }
+
+    /// Returns the number of links from each node
+    pub fn out_degrees(&self) -> Vec<usize> {
+        self.successors().iter().map(Vec::len).collect()
+    }
+
+    /// Returns the fewest links to follow from `root` to each node. Nothing for
+    /// nodes which can not be reached from `root`.
+    pub fn depths(&self, root: usize) -> Vec<Option<usize>> {
+        self.reachable(root)
+    }
+
+    /// Returns the nodes which can only be reached from `root` through `node`,
+    /// i.e. which would no longer be reached if `node` did not link to anything
+    pub fn reached_only_through(&self, root: usize, node: usize) -> Vec<usize> {
+        self.cut_offs(root).into_iter().nth(node).unwrap_or_default()
+    }
+
+    /// Returns, for each node, the nodes which can only be reached from `root`
+    /// through it, sorted. These are the nodes it dominates, other than itself,
+    /// so they are all derived from a single dominator tree.
+    pub fn cut_offs(&self, root: usize) -> Vec<Vec<usize>> {
+        let dominators = self.dominators(root);
+        let mut cut_offs = vec![Vec::new(); self.node_count];
+        for node in 0..self.node_count {
+            let mut dominator = dominators[node];
+            while let Some(ancestor) = dominator.filter(|&ancestor| ancestor != root) {
+                cut_offs[ancestor].push(node);
+                dominator = dominators[ancestor];
+            }
+        }
+        cut_offs
+    }
+
+    /// Returns the immediate dominator of each node, i.e. the last node other
+    /// than itself which is on every path from `root` to it, with the algorithm
+    /// of Cooper, Harvey and Kennedy. `root` is its own immediate dominator.
+    /// Nothing for nodes which can not be reached from `root`.
+    pub fn dominators(&self, root: usize) -> Vec<Option<usize>> {
+        let mut dominators = vec![None; self.node_count];
+        if root >= self.node_count {
+            return dominators
+        }
+
+        // Number the nodes reached from the root by when their depth-first search finishes
+        let successors = self.successors();
+        let mut finished = Vec::new();
+        let mut order = vec![usize::MAX; self.node_count];
+        let mut visited = vec![false; self.node_count];
+        visited[root] = true;
+        let mut stack = vec![(root, 0)];
+        while let Some((node, next)) = stack.pop() {
+            match successors[node].get(next) {
+                Some(&successor) => {
+                    stack.push((node, next + 1));
+                    if !visited[successor] {
+                        visited[successor] = true;
+                        stack.push((successor, 0));
+                    }
+                },
+                None => {
+                    order[node] = finished.len();
+                    finished.push(node);
+                },
+            }
+        }
+
+        let mut predecessors = vec![Vec::new(); self.node_count];
+        for &(menu, item) in &self.links {
+            if visited[menu] {
+                predecessors[item].push(menu);
+            }
+        }
+
+        // Walks up the dominators of two nodes until they meet
+        let intersect = |dominators: &[Option<usize>], mut first: usize, mut second: usize| {
+            while first != second {
+                while order[first] < order[second] {
+                    first = dominators[first].expect("processed nodes have a dominator");
+                }
+                while order[second] < order[first] {
+                    second = dominators[second].expect("processed nodes have a dominator");
+                }
+            }
+            first
+        };
+
+        dominators[root] = Some(root);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &node in finished.iter().rev().filter(|&&node| node != root) {
+                let mut dominator = None;
+                for &predecessor in predecessors[node].iter().filter(|&&predecessor| dominators[predecessor].is_some()) {
+                    dominator = Some(match dominator {
+                        Some(other) => intersect(&dominators, predecessor, other),
+                        None => predecessor,
+                    });
+                }
+                if dominator != dominators[node] {
+                    dominators[node] = dominator;
+                    changed = true;
+                }
+            }
+        }
+        dominators
+    }
+
+    /// Breadth-first search from `root`
+    fn reachable(&self, root: usize) -> Vec<Option<usize>> {
+        let successors = self.successors();
+        let mut depths = vec![None; self.node_count];
+        let mut queue = VecDeque::new();
+        if root < self.node_count {
+            depths[root] = Some(0);
+            queue.push_back(root);
+        }
+
+        while let Some(node) = queue.pop_front() {
+            let depth = depths[node].map(|depth| depth + 1);
+            for &next in &successors[node] {
+                if depths[next].is_none() {
+                    depths[next] = depth;
+                    queue.push_back(next);
+                }
+            }
+        }
+        depths
+    }
+
+    /// Returns the strongly connected components of the graph, i.e. the groups
+    /// of nodes which can all be reached from each other, with Kosaraju's
+    /// algorithm. Only components which form a cycle (more than one node, or a
+    /// node linking to itself) are returned, each sorted, in the order of their
+    /// first node.
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        let successors = self.successors();
+        let mut predecessors = vec![Vec::new(); self.node_count];
+        for &(menu, item) in &self.links {
+            predecessors[item].push(menu);
+        }
+
+        // Order the nodes by when their depth-first search finishes
+        let mut visited = vec![false; self.node_count];
+        let mut finished = Vec::with_capacity(self.node_count);
+        for start in 0..self.node_count {
+            if visited[start] {
+                continue
+            }
+            visited[start] = true;
+            let mut stack = vec![(start, 0)];
+            while let Some((node, next)) = stack.pop() {
+                match successors[node].get(next) {
+                    Some(&successor) => {
+                        stack.push((node, next + 1));
+                        if !visited[successor] {
+                            visited[successor] = true;
+                            stack.push((successor, 0));
+                        }
+                    },
+                    None => finished.push(node),
+                }
+            }
+        }
+
+        // Nodes reached backwards from the last to finish form a component
+        let mut component_of = vec![None; self.node_count];
+        let mut components: Vec<Vec<usize>> = Vec::new();
+        for &start in finished.iter().rev() {
+            if component_of[start].is_some() {
+                continue
+            }
+            let component = components.len();
+            component_of[start] = Some(component);
+            let mut nodes = Vec::new();
+            let mut stack = vec![start];
+            while let Some(node) = stack.pop() {
+                nodes.push(node);
+                for &predecessor in &predecessors[node] {
+                    if component_of[predecessor].is_none() {
+                        component_of[predecessor] = Some(component);
+                        stack.push(predecessor);
+                    }
+                }
+            }
+            nodes.sort_unstable();
+            components.push(nodes);
+        }
+
+        let mut cycles = components.into_iter()
+            .filter(|nodes| nodes.len() > 1 || successors[nodes[0]].contains(&nodes[0]))
+            .collect::<Vec<_>>();
+        cycles.sort_by_key(|nodes| nodes[0]);
+        cycles
+    }
+}
+
+impl Crawler {
+    /// Returns the links from the crawled menus to the items they list
+    pub fn link_graph(&self) -> LinkGraph {
+        LinkGraph::of(&self.items)
+    }
+}
+
+/// Writes the link graph of a crawl.
+///
+/// Nodes are filled with the colour of their item type and outlined with the
+/// colour of their outcome, and grouped by the server providing them in the
+/// order servers were discovered.
+///
+/// # Arguments
+/// * `writer`: Where the graph is written to
+/// * `items`: Every item discovered by the crawl
+/// * `format`: Format of the graph
+///
+/// # Returns
+/// Nothing if sucessfull. Otherwise, returns an IO error.
+pub fn write_graph(writer: &mut impl Write, items: &CrawledItems, format: GraphFormat) -> io::Result<()> {
+    let graph = LinkGraph::of(items);
+    match format {
+        GraphFormat::Dot => write_dot(writer, items, &graph)?,
+        GraphFormat::GraphMl => write_graphml(writer, items, &graph)?,
+    }
+    writer.flush()
+}
+
+fn write_dot(writer: &mut impl Write, items: &CrawledItems, graph: &LinkGraph) -> io::Result<()> {
+    writeln!(writer, "digraph links {{")?;
+    writeln!(writer, "\tnode [penwidth=2];")?;
+    for (cluster, (server_details, nodes)) in servers(items).into_iter().enumerate() {
+        writeln!(writer, "\tsubgraph \"cluster_{cluster}\" {{")?;
+        writeln!(writer, "\t\tlabel=\"{}\";", escape_dot(&server_details))?;
+        for index in nodes {
+            let item = &items[index];
+            let style = if matches!(item.outcome, ItemOutcome::Pending | ItemOutcome::Trapped) { "filled,dashed" } else { "filled,solid" };
+            writeln!(writer, "\t\tn{index} [label=\"{}\", tooltip=\"{}\", shape={}, fillcolor=\"{}\", color=\"{}\", style=\"{style}\"];",
+                escape_dot(&title(item)), escape_dot(&tooltip(item)),
+                shape(item.item_type), type_color(item.item_type), outcome_color(item.outcome))?;
+        }
+        writeln!(writer, "\t}}")?;
+    }
+    for (menu, item) in &graph.links {
+        writeln!(writer, "\tn{menu} -> n{item};")?;
+    }
+    writeln!(writer, "}}")
+}
+
+fn write_graphml(writer: &mut impl Write, items: &CrawledItems, graph: &LinkGraph) -> io::Result<()> {
+    writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(writer, "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\" \
+        xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+        xsi:schemaLocation=\"http://graphml.graphdrawing.org/xmlns http://graphml.graphdrawing.org/xmlns/1.0/graphml.xsd\">")?;
+    for (key, attr_type) in GRAPHML_KEYS {
+        writeln!(writer, "\t<key id=\"{key}\" for=\"node\" attr.name=\"{key}\" attr.type=\"{attr_type}\"/>")?;
+    }
+    writeln!(writer, "\t<graph id=\"links\" edgedefault=\"directed\">")?;
+    for (cluster, (server_details, nodes)) in servers(items).into_iter().enumerate() {
+        writeln!(writer, "\t\t<node id=\"server-{cluster}\">")?;
+        writeln!(writer, "\t\t\t<data key=\"label\">{}</data>", escape_xml(&server_details))?;
+        writeln!(writer, "\t\t\t<graph id=\"server-{cluster}:\" edgedefault=\"directed\">")?;
+        for index in nodes {
+            let item = &items[index];
+            writeln!(writer, "\t\t\t\t<node id=\"n{index}\">")?;
+            let size = item.size.map(|size| size.to_string());
+            let values = [
+                ("label", Some(title(item))),
+                ("server", Some(item.server_details())),
+                ("selector", Some(item.selector.clone())),
+                ("item_type", Some(item.item_type.to_char().to_string())),
+                ("outcome", Some(item.outcome.to_string())),
+                ("size", size),
+                ("depth", Some(item.depth.to_string())),
+                ("fill_color", Some(type_color(item.item_type).to_string())),
+                ("outline_color", Some(outcome_color(item.outcome).to_string())),
+            ];
+            for (key, value) in values {
+                if let Some(value) = value {
+                    writeln!(writer, "\t\t\t\t\t<data key=\"{key}\">{}</data>", escape_xml(&value))?;
+                }
+            }
+            writeln!(writer, "\t\t\t\t</node>")?;
+        }
+        writeln!(writer, "\t\t\t</graph>")?;
+        writeln!(writer, "\t\t</node>")?;
+    }
+    for (menu, item) in &graph.links {
+        writeln!(writer, "\t\t<edge source=\"n{menu}\" target=\"n{item}\"/>")?;
+    }
+    writeln!(writer, "\t</graph>")?;
+    writeln!(writer, "</graphml>")
+}
+
+/// Groups the items by the server providing them (server details, indices of
+/// the items) pairs, in the order the servers were discovered
+fn servers(items: &[CrawledItem]) -> Vec<(String, Vec<usize>)> {
+    let mut servers: Vec<(String, Vec<usize>)> = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let server_details = item.server_details();
+        match servers.iter_mut().find(|(details, _)| *details == server_details) {
+            Some((_, nodes)) => nodes.push(index),
+            None => servers.push((server_details, vec![index])),
+        }
+    }
+    servers
+}
+
+/// Returns the text shown when hovering over a node
+fn tooltip(item: &CrawledItem) -> String {
+    format!("{} {} ({})", item.server_details(), item.selector, item.outcome)
+}
+
+/// Returns the shape of the nodes of an item type
+fn shape(item_type: ItemType) -> &'static str {
+    match item_type {
+        ItemType::Dir => "folder",
+        ItemType::Txt => "note",
+        _ => "box3d",
+    }
+}
+
+/// Returns the colour nodes of an item type are filled with
+fn type_color(item_type: ItemType) -> &'static str {
+    match item_type {
+        ItemType::Dir => "#cfe2f3",
+        ItemType::Txt => "#fff2cc",
+        _ => "#d9d2e9",
+    }
+}
+
+/// Returns the colour nodes with an outcome are outlined with
+fn outcome_color(outcome: ItemOutcome) -> &'static str {
+    match outcome {
+        ItemOutcome::Fetched(ResponseOutcome::Complete) | ItemOutcome::Carried => "#274e13",
+        ItemOutcome::External(true) => "#1155cc",
+        ItemOutcome::Pending | ItemOutcome::Trapped => "#999999",
+        _ => "#cc0000",
+    }
+}
+
+/// Escapes the characters of a DOT string which would end it
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "")
+}
+
+/// Escapes the characters of a string which have a meaning in XML, and replaces
+/// control characters XML does not allow
+fn escape_xml(text: &str) -> String {
+    text.chars()
+        .map(|character| match character {
+            '&' => String::from("&amp;"),
+            '<' => String::from("&lt;"),
+            '>' => String::from("&gt;"),
+            '"' => String::from("&quot;"),
+            '\'' => String::from("&apos;"),
+            '\t' | '\n' | '\r' => character.to_string(),
+            character if (character as u32) < 0x20 => String::from("\u{fffd}"),
+            character => character.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+
+use chrono::Local;
+
+use crate::gopher::response::{ItemType, ResponseOutcome};
+use crate::session::outcome_name;
+
+use super::{
+    item::{CrawledItem, ItemOutcome, Referrer},
+    report::{CrawlReport, ReportFile},
+    stats::{type_heading, SizeStats},
+    Crawler
+};
+
+/// Styles of the HTML report, inlined so the report is a single file
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; color: #222; }
+h1, h2 { font-weight: normal; }
+code { background: #f2f2f2; padding: 0 .2em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: .2em .6em; text-align: left; }
+th.sortable { cursor: pointer; background: #eee; }
+td.number { text-align: right; }
+ul.tree, ul.tree ul { list-style: none; padding-left: 1.2em; }
+ul.tree summary { cursor: pointer; }
+.type { font-family: monospace; color: #555; }
+.ok { color: #1a7f37; }
+.error { color: #c62828; }
+.pending { color: #8a6d00; }
+.bar { background: #4a78b5; height: 1em; }
+:target { background: #fff3b0; }
+";
+
+/// Script sorting a table by the clicked column header. Cells with a `data-value`
+/// attribute sort numerically by it, other cells alphabetically by their text.
+const SCRIPT: &str = "\
+function sortTable(header) {
+    const table = header.closest('table');
+    const column = header.cellIndex;
+    const ascending = header.dataset.order !== 'asc';
+    header.dataset.order = ascending ? 'asc' : 'desc';
+    const key = row => {
+        const cell = row.cells[column];
+        return cell.dataset.value !== undefined ? Number(cell.dataset.value) : cell.textContent.toLowerCase();
+    };
+    const rows = Array.from(table.tBodies[0].rows);
+    rows.sort((a, b) => {
+        const [x, y] = [key(a), key(b)];
+        return (x < y ? -1 : x > y ? 1 : 0) * (ascending ? 1 : -1);
+    });
+    rows.forEach(row => table.tBodies[0].appendChild(row));
+}
+";
+
+impl Crawler {
+    /// Renders the outcome of the crawl as a self-contained HTML page: a summary,
+    /// a collapsible tree of the crawled menus, sortable tables of every item,
+    /// size histograms, the external servers and the invalid references, each
+    /// linked to the menu they were found in.
+    pub fn render_html(&self) -> String {
+        render_html(&self.crawl_report(), self.items())
+    }
+}
+
+/// Renders a crawl report and the items discovered by the crawl as a
+/// self-contained HTML page, which does not load any external assets.
+///
+/// # Arguments
+/// * `report`: Outcome of the crawl
+/// * `items`: Every item discovered by the crawl, in the order they were discovered
+///
+/// # Returns
+/// The HTML page.
+pub fn render_html(report: &CrawlReport, items: &[CrawledItem]) -> String {
+    let menus = MenuIndex::new(items);
+
+    format!(
+        "<!DOCTYPE html>\n\
+        <html lang=\"en\">\n\
+        <head>\n\
+        <meta charset=\"utf-8\">\n\
+        <title>Crawl report for {root}</title>\n\
+        <style>\n{STYLE}</style>\n\
+        <script>\n{SCRIPT}</script>\n\
+        </head>\n\
+        <body>\n\
+        <h1>Crawl report for <code>{root}</code></h1>\n\
+        <p>Generated {generated}</p>\n\
+        {summary}\
+        {tree}\
+        {items}\
+        {histograms}\
+        {external_servers}\
+        {invalid_references}\
+        </body>\n\
+        </html>\n",
+        root = escape(&report.root_server),
+        generated = Local::now().format("%Y-%m-%d %H:%M:%S"),
+        summary = render_summary(report),
+        tree = render_tree(items, &menus),
+        items = render_items(items),
+        histograms = render_size_stats(report),
+        external_servers = render_external_servers(report, items, &menus),
+        invalid_references = render_invalid_references(report, items, &menus),
+    )
+}
+
+/// Looks up the items of menus by the server details and selector of the menu.
+///
+/// * `children`: Indices of the items discovered in each menu, in the order they
+///   were discovered
+/// * `menus`: Index of the item of each menu
+struct MenuIndex {
+    children: HashMap<(String, String), Vec<usize>>,
+    menus: HashMap<(String, String), usize>,
+}
+
+impl MenuIndex {
+    fn new(items: &[CrawledItem]) -> MenuIndex {
+        let mut children: HashMap<(String, String), Vec<usize>> = HashMap::new();
+        let mut menus = HashMap::new();
+
+        for (index, item) in items.iter().enumerate() {
+            if let Some(referrer) = item.referrer() {
+                children.entry((referrer.server_details.clone(), referrer.selector.clone())).or_default().push(index);
+            }
+            if item.item_type == ItemType::Dir {
+                menus.entry((item.server_details(), item.selector.clone())).or_insert(index);
+            }
+        }
+        MenuIndex { children, menus }
+    }
+
+    /// Returns a link to the menu of a referrer, followed by the line number
+    fn referrer_link(&self, items: &[CrawledItem], referrer: &Referrer) -> String {
+        let menu = match self.menus.get(&(referrer.server_details.clone(), referrer.selector.clone())) {
+            Some(&index) => item_link(index, &items[index]),
+            None => format!("<code>{}{}</code>", escape(&referrer.server_details), escape(&referrer.selector)),
+        };
+        format!("{menu} (line {})", referrer.line)
+    }
+}
+
+/// Renders the counts and the smallest and largest files
+fn render_summary(report: &CrawlReport) -> String {
+    let file = |file: &Option<ReportFile>| match file {
+        Some(file) => format!("<code>{}{}</code> ({} bytes)",
+            escape(&file.item.server_details), escape(&file.item.selector), file.size),
+        None => String::from("-"),
+    };
+    let mut rows = vec![
+        (String::from("Gopher directories"), report.dir_count.to_string()),
+        (String::from("Simple text files"), report.txt_file_count.to_string()),
+        (String::from("Binary files"), report.bin_file_count.to_string()),
+        (String::from("Smallest text file"), file(&report.smallest_txt)),
+        (String::from("Largest text file"), file(&report.largest_txt)),
+        (String::from("Smallest binary file"), file(&report.smallest_bin)),
+        (String::from("Largest binary file"), file(&report.largest_bin)),
+        (String::from("Unique error items"), report.error_items.len().to_string()),
+        (String::from("External servers"), report.external_servers.len().to_string()),
+        (String::from("Invalid references"), report.invalid_references.len().to_string()),
+    ];
+    if let Some(incremental) = &report.incremental {
+        rows.push((String::from("Unchanged menus"), incremental.unchanged_menus.to_string()));
+        rows.push((String::from("Items carried forward"), incremental.carried_forward.to_string()));
+    }
+
+    let rows = rows.iter()
+        .map(|(name, value)| format!("<tr><th>{name}</th><td>{value}</td></tr>\n"))
+        .collect::<String>();
+    format!("<h2>Summary</h2>\n<table>\n{rows}</table>\n")
+}
+
+/// Renders the menus as a tree of collapsible lists, starting at the root menu
+fn render_tree(items: &[CrawledItem], menus: &MenuIndex) -> String {
+    let roots = items.iter().enumerate()
+        .filter(|(_, item)| item.referrer().is_none())
+        .map(|(index, _)| render_node(index, items, menus))
+        .collect::<String>();
+    format!("<h2>Menu tree</h2>\n<ul class=\"tree\">\n{roots}</ul>\n")
+}
+
+/// Renders an item of the tree and, if it is a menu, everything discovered in it
+fn render_node(index: usize, items: &[CrawledItem], menus: &MenuIndex) -> String {
+    let item = &items[index];
+    let label = format!("<span class=\"type\">[{}]</span> {} <code>{}{}</code> <span class=\"{}\">{}</span>",
+        type_name(item.item_type), escape(&title(item)), escape(&item.server_details()),
+        escape(&item.selector), outcome_class(item.outcome), item.outcome);
+
+    let children = match (item.item_type, menus.children.get(&(item.server_details(), item.selector.clone()))) {
+        (ItemType::Dir, Some(children)) => children,
+        _ => return format!("<li id=\"item-{index}\">{label}</li>\n"),
+    };
+    let children = children.iter()
+        .map(|&child| render_node(child, items, menus))
+        .collect::<String>();
+    format!("<li id=\"item-{index}\"><details open><summary>{label}</summary>\n<ul>\n{children}</ul>\n</details></li>\n")
+}
+
+/// Renders a sortable table of every item
+fn render_items(items: &[CrawledItem]) -> String {
+    let rows = items.iter().enumerate()
+        .map(|(index, item)| format!(
+            "<tr><td>{}</td><td><a href=\"#item-{}\">{}</a></td><td>{}</td><td>{}</td>{}<td class=\"{}\">{}</td>{}{}</tr>\n",
+            type_name(item.item_type),
+            index,
+            escape(&title(item)),
+            escape(&item.server_details()),
+            escape(&item.selector),
+            number_cell(item.size.map(|size| (size as f64, size.to_string()))),
+            outcome_class(item.outcome),
+            item.outcome,
+            number_cell(Some((item.depth as f64, item.depth.to_string()))),
+            number_cell(item.latency.map(|latency| {
+                let millis = latency.as_secs_f64() * 1000.0;
+                (millis, format!("{millis:.3}"))
+            })),
+        ))
+        .collect::<String>();
+
+    format!("<h2>Items</h2>\n\
+        <p>Click a column header to sort by it.</p>\n\
+        <table>\n\
+        <thead><tr>{}</tr></thead>\n\
+        <tbody>\n{rows}</tbody>\n\
+        </table>\n",
+        sortable_headers(&["Type", "Display string", "Server", "Selector", "Size (bytes)", "Outcome", "Depth", "Latency (ms)"]))
+}
+
+/// Renders the size statistics of every item type, the bytes received from each
+/// server and a histogram of the sizes of the received text and binary files, in
+/// buckets of powers of ten
+fn render_size_stats(report: &CrawlReport) -> String {
+    let size = |size: Option<u64>| size.map(|size| (size as f64, size.to_string()));
+    let stats = report.size_stats.iter()
+        .map(|stats| format!("<tr><td>{}</td>{}{}{}{}{}{}{}</tr>\n",
+            type_heading(stats.item_type),
+            number_cell(Some((stats.count as f64, stats.count.to_string()))),
+            number_cell(Some((stats.total_bytes as f64, stats.total_bytes.to_string()))),
+            number_cell(size(stats.min)),
+            number_cell(size(stats.median)),
+            number_cell(stats.mean.map(|mean| (mean, format!("{mean:.1}")))),
+            number_cell(size(stats.p95)),
+            number_cell(size(stats.max)),
+        ))
+        .collect::<String>();
+
+    let hosts = report.host_totals.iter()
+        .map(|host| format!("<tr><td>{}</td>{}{}{}{}</tr>\n",
+            escape(&host.server_details),
+            number_cell(Some((host.dir_bytes as f64, host.dir_bytes.to_string()))),
+            number_cell(Some((host.txt_bytes as f64, host.txt_bytes.to_string()))),
+            number_cell(Some((host.bin_bytes as f64, host.bin_bytes.to_string()))),
+            number_cell(Some((host.total_bytes as f64, host.total_bytes.to_string()))),
+        ))
+        .collect::<String>();
+
+    let histograms = report.size_stats.iter()
+        .filter(|stats| matches!(stats.item_type, ItemType::Txt | ItemType::Bin))
+        .map(render_histogram)
+        .collect::<String>();
+
+    format!("<h2>File sizes</h2>\n\
+        <table>\n\
+        <thead><tr>{}</tr></thead>\n\
+        <tbody>\n{stats}</tbody>\n\
+        </table>\n\
+        <h3>Bytes received per server</h3>\n\
+        <table>\n\
+        <thead><tr>{}</tr></thead>\n\
+        <tbody>\n{hosts}</tbody>\n\
+        </table>\n\
+        {histograms}",
+        sortable_headers(&["Type", "Received", "Total (bytes)", "Min", "Median", "Mean", "95th percentile", "Max"]),
+        sortable_headers(&["Server", "Directories (bytes)", "Text files (bytes)", "Binary files (bytes)", "Total (bytes)"]))
+}
+
+/// Renders a histogram of the sizes of the received items of a type, with a bar
+/// per bucket
+fn render_histogram(stats: &SizeStats) -> String {
+    let heading = format!("Histogram of {}", type_heading(stats.item_type));
+    if stats.histogram.is_empty() {
+        return format!("<h3>{heading}</h3>\n<p>No files received.</p>\n")
+    }
+
+    let most = stats.histogram.iter().map(|bucket| bucket.count).max().unwrap_or(1);
+    let rows = stats.histogram.iter()
+        .map(|bucket| format!("<tr><td>{} - {} bytes</td><td class=\"number\">{}</td>\
+            <td style=\"width: 20em\"><div class=\"bar\" style=\"width: {}%\"></div></td></tr>\n",
+            bucket.min, bucket.max, bucket.count, bucket.count * 100 / most))
+        .collect::<String>();
+    format!("<h3>{heading}</h3>\n<table>\n<tr><th>Size</th><th>Files</th><th></th></tr>\n{rows}</table>\n")
+}
+
+/// Renders a sortable table of the external servers and the menus linking to them
+fn render_external_servers(report: &CrawlReport, items: &[CrawledItem], menus: &MenuIndex) -> String {
+    let rows = report.external_servers.iter()
+        .map(|server| {
+            let linked_from = server.linked_from.iter()
+                .map(|referrer| menus.referrer_link(items, referrer))
+                .collect::<Vec<_>>();
+            let (class, status) = if server.connected {
+                ("ok", "connected successfully")
+            } else {
+                ("error", "did not connect")
+            };
+            format!("<tr><td>{}</td>{}<td class=\"{class}\">{status}</td><td>{}</td></tr>\n",
+                escape(&server.server_name),
+                number_cell(Some((server.server_port as f64, server.server_port.to_string()))),
+                linked_from.join(", "))
+        })
+        .collect::<String>();
+
+    format!("<h2>External servers</h2>\n\
+        <table>\n\
+        <thead><tr>{}</tr></thead>\n\
+        <tbody>\n{rows}</tbody>\n\
+        </table>\n",
+        sortable_headers(&["Server", "Port", "Status", "Linked from"]))
+}
+
+/// Renders a sortable table of the invalid references and the menus linking to them
+fn render_invalid_references(report: &CrawlReport, items: &[CrawledItem], menus: &MenuIndex) -> String {
+    let rows = report.invalid_references.iter()
+        .map(|reference| {
+            let linked_from = reference.linked_from.iter()
+                .map(|referrer| menus.referrer_link(items, referrer))
+                .collect::<Vec<_>>();
+            format!("<tr><td><code>{}</code></td><td class=\"{}\">{}</td><td>{}</td></tr>\n",
+                escape(&reference.details),
+                outcome_class(ItemOutcome::Fetched(reference.outcome)),
+                outcome_name(reference.outcome),
+                linked_from.join(", "))
+        })
+        .collect::<String>();
+
+    format!("<h2>Invalid references</h2>\n\
+        <table>\n\
+        <thead><tr>{}</tr></thead>\n\
+        <tbody>\n{rows}</tbody>\n\
+        </table>\n",
+        sortable_headers(&["Reference", "Outcome", "Linked from"]))
+}
+
+/// Returns header cells which sort their table when clicked
+fn sortable_headers(headings: &[&str]) -> String {
+    headings.iter()
+        .map(|heading| format!("<th class=\"sortable\" onclick=\"sortTable(this)\">{heading}</th>"))
+        .collect()
+}
+
+/// Returns a right aligned cell sorting by a number. Empty if there is no value.
+fn number_cell(value: Option<(f64, String)>) -> String {
+    match value {
+        Some((value, text)) => format!("<td class=\"number\" data-value=\"{value}\">{text}</td>"),
+        None => String::from("<td class=\"number\" data-value=\"-1\"></td>"),
+    }
+}
+
+/// Returns a link to an item of the tree
+fn item_link(index: usize, item: &CrawledItem) -> String {
+    format!("<a href=\"#item-{index}\">{}</a>", escape(&title(item)))
+}
+
+/// Returns the display string of an item, or its selector if it has none
+pub(super) fn title(item: &CrawledItem) -> String {
+    match item.display_string.as_str() {
+        "" if item.selector.is_empty() => String::from("/"),
+        "" => item.selector.clone(),
+        display_string => display_string.to_string(),
+    }
+}
+
+/// Returns the name an item type is shown with
+fn type_name(item_type: ItemType) -> &'static str {
+    match item_type {
+        ItemType::Dir => "dir",
+        ItemType::Txt => "txt",
+        ItemType::Bin => "bin",
+        _ => "other",
+    }
+}
+
+/// Returns the CSS class an outcome is shown with
+fn outcome_class(outcome: ItemOutcome) -> &'static str {
+    match outcome {
+        ItemOutcome::Pending | ItemOutcome::Trapped => "pending",
+        ItemOutcome::Fetched(ResponseOutcome::Complete) | ItemOutcome::Carried | ItemOutcome::External(true) => "ok",
+        _ => "error",
+    }
+}
+
+/// Escapes the characters of a string which have a meaning in HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
@@ -0,0 +1,83 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io,
+    path::{Path, PathBuf}
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::gopher::response::ItemType;
+use crate::storage::read_manifest;
+
+/// Represents the snapshot of an earlier crawl that an incremental crawl is
+/// compared against.
+///
+/// Any snapshot with a manifest can be used, i.e. a crawl saved with
+/// `Crawler::save_snapshot` into a `Mirror` or a `ContentStorage`.
+///
+/// * `folder`: Folder containing the snapshot
+/// * `items`: Path within `folder` of every stored item, keyed by (is menu,
+///   server details, selector) triples
+pub struct PreviousCrawl {
+    folder: PathBuf,
+    items: HashMap<(bool, String, String), String>,
+}
+
+impl PreviousCrawl {
+    /// Loads the snapshot of an earlier crawl from its manifest
+    ///
+    /// # Returns
+    /// A new `PreviousCrawl` if the manifest could be read. Otherwise, returns an
+    /// IO error.
+    pub fn load(folder: &Path) -> io::Result<PreviousCrawl> {
+        let items = read_manifest(folder)?.into_iter()
+            .map(|(item_type, server_details, selector, path)| {
+                ((item_type == ItemType::Dir, server_details, selector), path)
+            })
+            .collect();
+
+        Ok(PreviousCrawl {
+            folder: folder.to_path_buf(),
+            items,
+        })
+    }
+
+    /// Returns the number of items in the snapshot
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns true if the snapshot does not hold any item
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the stored contents of an item, if it was part of the earlier crawl.
+    /// Menus are returned as they were stored, i.e. terminated by the last line
+    /// `.`CRLF.
+    ///
+    /// # Arguments
+    /// * `item_type`: The type of the item
+    /// * `server_details`: hostname:port of the server providing the item
+    /// * `selector`: String used to request the item
+    pub fn contents(&self, item_type: ItemType, server_details: &str, selector: &str) -> Option<io::Result<Vec<u8>>> {
+        let key = (item_type == ItemType::Dir, server_details.to_string(), selector.to_string());
+        self.items.get(&key).map(|path| fs::read(self.folder.join(path)))
+    }
+
+    /// Returns true if a menu has the same contents as in the earlier crawl.
+    /// Menus which were not part of the earlier crawl, or cannot be read, have
+    /// changed.
+    ///
+    /// # Arguments
+    /// * `server_details`: hostname:port of the server providing the menu
+    /// * `selector`: String used to request the menu
+    /// * `serialized`: The menu serialized as it is stored in a snapshot
+    pub fn menu_unchanged(&self, server_details: &str, selector: &str, serialized: &[u8]) -> bool {
+        match self.contents(ItemType::Dir, server_details, selector) {
+            Some(Ok(previous)) => Sha256::digest(previous) == Sha256::digest(serialized),
+            _ => false,
+        }
+    }
+}
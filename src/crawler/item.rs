@@ -0,0 +1,224 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::Deref,
+    time::Duration
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::gopher::response::{ItemType, ResponseOutcome};
+use crate::session::outcome_name;
+
+/// Represents an item (directory, text file or binary file) discovered by a crawl.
+///
+/// * `server_name`: Hostname of the server providing the item
+/// * `server_port`: Port number of the server providing the item
+/// * `selector`: String used to request the item
+/// * `item_type`: The type the item was requested as, or discovered with if it
+///   was not requested
+/// * `display_string`: Display string the item was first discovered with. Empty
+///   for the root menu until a menu links to it.
+/// * `size`: Size of the item (bytes), if it was received
+/// * `outcome`: What happened to the item
+/// * `depth`: Number of menus between the root menu and the item. The root menu
+///   has depth 0.
+/// * `referrers`: Every menu line linking to the item, in the order they were
+///   found. The first is the line the item was discovered on, except for the
+///   root menu.
+/// * `latency`: Time taken to request the item, if it was requested
+/// * `hash`: Hex encoded SHA-256 hash of the item, if it was received
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrawledItem {
+    pub server_name: String,
+    pub server_port: u16,
+    pub selector: String,
+    pub item_type: ItemType,
+    pub display_string: String,
+    pub size: Option<u64>,
+    pub outcome: ItemOutcome,
+    pub depth: usize,
+    pub referrers: Vec<Referrer>,
+    pub latency: Option<Duration>,
+    pub hash: Option<String>,
+}
+
+/// Represents a menu line linking to an item.
+///
+/// * `server_details`: hostname:port of the server providing the menu
+/// * `selector`: String used to request the menu
+/// * `line`: Line number within the menu, starting at 1
+/// * `display_string`: Display string of the line
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Referrer {
+    pub server_details: String,
+    pub selector: String,
+    pub line: usize,
+    pub display_string: String,
+}
+
+/// Represents what happened to a discovered item.
+///
+/// * `Pending`: The item was not requested yet
+/// * `Fetched(outcome)`: The item was requested with the given outcome
+/// * `Carried`: The item was taken from the earlier crawl of an incremental crawl
+/// * `External(connected)`: The item is on an external server, which was only
+///   connected to
+/// * `Trapped`: The item is a menu which was not requested, as it looked like part
+///   of a spider trap
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemOutcome {
+    Pending,
+    Fetched(ResponseOutcome),
+    Carried,
+    External(bool),
+    Trapped,
+}
+
+/// Every item discovered by a crawl, in the order they were discovered, and
+/// queries over them. Statistics such as the number of files of a type or the
+/// largest files are derived from the items instead of being kept separately.
+///
+/// Derefs to a slice of the items, and serializes as one.
+///
+/// * `items`: Every discovered item, in the order they were discovered
+/// * `index`: Position of every item in `items`, keyed by server details and
+///   then selector
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<CrawledItem>", into = "Vec<CrawledItem>")]
+pub struct CrawledItems {
+    items: Vec<CrawledItem>,
+    index: HashMap<String, HashMap<String, usize>>,
+}
+
+impl fmt::Display for ItemOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ItemOutcome::Pending           => write!(f, "pending"),
+            ItemOutcome::Fetched(outcome)  => write!(f, "{}", outcome_name(*outcome)),
+            ItemOutcome::Carried           => write!(f, "carried-forward"),
+            ItemOutcome::External(true)    => write!(f, "external-connected"),
+            ItemOutcome::External(false)   => write!(f, "external-not-connected"),
+            ItemOutcome::Trapped           => write!(f, "suspected-trap"),
+        }
+    }
+}
+
+impl CrawledItem {
+    /// Returns hostname:port of the server providing the item
+    pub fn server_details(&self) -> String {
+        format!("{}:{}", self.server_name, self.server_port)
+    }
+
+    /// Returns the menu line the item was discovered on. Nothing for the root menu.
+    pub fn referrer(&self) -> Option<&Referrer> {
+        self.referrers.first().filter(|_| self.depth > 0)
+    }
+
+    /// Returns true if the item was received completely, i.e. downloaded or
+    /// carried forward
+    pub fn is_received(&self) -> bool {
+        self.size.is_some()
+    }
+
+    /// Returns true if the item was requested (or carried forward), whatever the
+    /// outcome
+    pub fn is_requested(&self) -> bool {
+        matches!(self.outcome, ItemOutcome::Fetched(_) | ItemOutcome::Carried)
+    }
+}
+
+impl Referrer {
+    /// Returns the line the referrer is printed as
+    pub(super) fn line(&self) -> String {
+        format!("{}: {} (line {}: {})", self.server_details, self.selector, self.line, self.display_string)
+    }
+}
+
+impl CrawledItems {
+    /// Constructs an empty collection
+    pub fn new() -> CrawledItems {
+        CrawledItems::default()
+    }
+
+    /// Returns an item by the server providing it and its selector
+    ///
+    /// # Arguments
+    /// * `server_details`: hostname:port of the server providing the item
+    /// * `selector`: String used to request the item
+    pub fn get(&self, server_details: &str, selector: &str) -> Option<&CrawledItem> {
+        self.position(server_details, selector).map(|index| &self.items[index])
+    }
+
+    /// Returns an item by the server providing it and its selector, to update it
+    pub fn get_mut(&mut self, server_details: &str, selector: &str) -> Option<&mut CrawledItem> {
+        self.position(server_details, selector).map(|index| &mut self.items[index])
+    }
+
+    /// Returns the position of an item in the order items were discovered
+    pub fn position(&self, server_details: &str, selector: &str) -> Option<usize> {
+        self.index.get(server_details)?.get(selector).copied()
+    }
+
+    /// Adds an item, unless an item with the same server details and selector
+    /// was already discovered.
+    ///
+    /// # Returns
+    /// True if the item was added. False otherwise.
+    pub fn insert(&mut self, item: CrawledItem) -> bool {
+        let selectors = self.index.entry(item.server_details()).or_default();
+        if selectors.contains_key(&item.selector) {
+            return false
+        }
+        selectors.insert(item.selector.clone(), self.items.len());
+        self.items.push(item);
+        true
+    }
+
+    /// Returns every received item of a type, in the order they were discovered
+    pub fn received(&self, item_type: ItemType) -> impl Iterator<Item = &CrawledItem> {
+        self.items.iter().filter(move |item| item.item_type == item_type && item.is_received())
+    }
+
+    /// Returns up to `n` of the smallest received items of a type, smallest first.
+    /// Items of the same size are in the order they were discovered.
+    pub fn smallest(&self, item_type: ItemType, n: usize) -> Vec<&CrawledItem> {
+        let mut items = self.received(item_type).collect::<Vec<_>>();
+        items.sort_by_key(|item| item.size);
+        items.truncate(n);
+        items
+    }
+
+    /// Returns up to `n` of the largest received items of a type, largest first.
+    /// Items of the same size are in the order they were discovered.
+    pub fn largest(&self, item_type: ItemType, n: usize) -> Vec<&CrawledItem> {
+        let mut items = self.received(item_type).collect::<Vec<_>>();
+        items.sort_by_key(|item| std::cmp::Reverse(item.size));
+        items.truncate(n);
+        items
+    }
+}
+
+impl Deref for CrawledItems {
+    type Target = [CrawledItem];
+
+    fn deref(&self) -> &[CrawledItem] {
+        &self.items
+    }
+}
+
+impl From<Vec<CrawledItem>> for CrawledItems {
+    fn from(items: Vec<CrawledItem>) -> CrawledItems {
+        let mut collection = CrawledItems::new();
+        for item in items {
+            collection.insert(item);
+        }
+        collection
+    }
+}
+
+impl From<CrawledItems> for Vec<CrawledItem> {
+    fn from(items: CrawledItems) -> Vec<CrawledItem> {
+        items.items
+    }
+}
@@ -0,0 +1,542 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    rc::Rc
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::gopher::response::{ItemType, ResponseOutcome};
+use crate::storage::DuplicateGroup;
+
+use super::{
+    analysis::GraphAnalysis,
+    canonical::MergedVariant,
+    item::{CrawledItem, CrawledItems, ItemOutcome, Referrer},
+    stats::{HostTotal, SizeStats, SIZE_STATS_TYPES},
+    trap::{SpiderTrap, TrapKind},
+    Crawler, MenuLocation, ReferenceOrigin
+};
+
+/// Version of the JSON schema of `CrawlReport`. It is increased whenever a field
+/// is renamed, removed or changes meaning; new optional fields may be added
+/// without a new version.
+pub const REPORT_SCHEMA_VERSION: u32 = 1;
+
+/// Represents the outcome of a crawl, i.e. everything printed by `Crawler::report`.
+///
+/// Every list is in the order of the text report: sorted alphabetically (ignoring
+/// case) by the line it is printed as. Serializes to JSON with the field names
+/// below, see the README for the schema.
+///
+/// * `schema_version`: Always `REPORT_SCHEMA_VERSION`
+/// * `root_server`: hostname:port of the root (start) server
+/// * `dir_count`: Number of directories
+/// * `dirs`: Every directory
+/// * `txt_file_count`: Number of simple text files
+/// * `txt_files`: Every simple text file
+/// * `bin_file_count`: Number of binary files
+/// * `bin_files`: Every binary file
+/// * `smallest_txt`: The smallest text file. Nothing if no text file was downloaded.
+/// * `smallest_txt_contents`: Contents of the smallest text file
+/// * `largest_txt`: The largest text file. Nothing if no text file was downloaded.
+/// * `smallest_bin`: The smallest binary file. Nothing if no binary file was downloaded.
+/// * `largest_bin`: The largest binary file. Nothing if no binary file was downloaded.
+/// * `error_items`: Every unique error (type 3) item and where it appeared
+/// * `external_servers`: Every external server and if it accepted a connection
+/// * `invalid_references`: Every reference that has issues/errors
+/// * `incremental`: What an incremental crawl took from the earlier crawl.
+///   Nothing for other crawls.
+/// * `duplicates`: Every group of downloaded files with identical contents.
+///   Nothing if the storage does not keep track of them.
+/// * `top_n`: Number of items in each list of the smallest, largest, deepest or
+///   most linked items
+/// * `size_stats`: Distribution of the sizes of directories, simple text files
+///   and binary files, in that order
+/// * `host_totals`: Bytes received from each server
+/// * `graph`: Structure of the links from menus to items. Nothing in reports
+///   written before it was analysed.
+/// * `traps`: Every suspected spider trap, in the order they were found
+/// * `merged_variants`: Every reference to an item which was merged into an
+///   equivalent reference, e.g. with a hostname differing only in case
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CrawlReport {
+    pub schema_version: u32,
+    pub root_server: String,
+
+    pub dir_count: u32,
+    pub dirs: Vec<ReportItem>,
+    pub txt_file_count: u32,
+    pub txt_files: Vec<ReportItem>,
+    pub bin_file_count: u32,
+    pub bin_files: Vec<ReportItem>,
+
+    pub smallest_txt: Option<ReportFile>,
+    pub smallest_txt_contents: String,
+    pub largest_txt: Option<ReportFile>,
+    pub smallest_bin: Option<ReportFile>,
+    pub largest_bin: Option<ReportFile>,
+
+    pub error_items: Vec<ReportErrorItem>,
+    pub external_servers: Vec<ReportExternalServer>,
+    pub invalid_references: Vec<ReportReference>,
+
+    pub incremental: Option<ReportIncremental>,
+    pub duplicates: Option<Vec<DuplicateGroup>>,
+
+    // Missing from reports written before size statistics
+    #[serde(default)]
+    pub top_n: usize,
+    #[serde(default)]
+    pub size_stats: Vec<SizeStats>,
+    #[serde(default)]
+    pub host_totals: Vec<HostTotal>,
+
+    #[serde(default)]
+    pub graph: Option<GraphAnalysis>,
+    #[serde(default)]
+    pub traps: Vec<ReportTrap>,
+    #[serde(default)]
+    pub merged_variants: Vec<MergedVariant>,
+}
+
+/// Represents an item found by the crawl.
+///
+/// * `server_details`: hostname:port of the server providing the item
+/// * `selector`: String used to request the item
+/// * `title`: Display string the item was first discovered with, if it had one
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportItem {
+    pub server_details: String,
+    pub selector: String,
+    pub title: Option<String>,
+}
+
+/// Represents a downloaded file and its size.
+///
+/// * `item`: The file
+/// * `size`: Size of the file (bytes)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportFile {
+    pub item: ReportItem,
+    pub size: u64,
+}
+
+/// Represents a unique error (type 3) item.
+///
+/// * `display_string`: Display string of the error item
+/// * `locations`: Every menu line the error item appeared on
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportErrorItem {
+    pub display_string: String,
+    pub locations: Vec<ReportLocation>,
+}
+
+/// Represents a line within a menu.
+///
+/// * `server_details`: hostname:port of the server providing the menu
+/// * `selector`: String used to request the menu
+/// * `line`: Line number within the menu, starting at 1
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportLocation {
+    pub server_details: String,
+    pub selector: String,
+    pub line: usize,
+}
+
+/// Represents an external server.
+///
+/// * `server_name`: Hostname of the external server
+/// * `server_port`: Port number of the external server
+/// * `connected`: If the server accepted a connection
+/// * `linked_from`: Every menu line linking to a directory on the server
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportExternalServer {
+    pub server_name: String,
+    pub server_port: u16,
+    pub connected: bool,
+    #[serde(default)]
+    pub linked_from: Vec<Referrer>,
+}
+
+/// Represents a reference that has issues/errors.
+///
+/// * `details`: Details of the request
+/// * `outcome`: Outcome of the response
+/// * `linked_from`: Every menu line linking to the reference. For malformed
+///   response lines, the line itself.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportReference {
+    pub details: String,
+    pub outcome: ResponseOutcome,
+    #[serde(default)]
+    pub linked_from: Vec<Referrer>,
+}
+
+/// Represents a suspected spider trap: menus matching the same pattern, of which
+/// some were cut off.
+///
+/// * `kind`: The heuristic which found the trap
+/// * `server_details`: hostname:port of the server providing the menus
+/// * `pattern`: What the menus have in common
+/// * `crawled`: Number of menus matching the pattern which were followed
+/// * `cut_off`: Every menu which was cut off, in the order they were found
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportTrap {
+    pub kind: TrapKind,
+    pub server_details: String,
+    pub pattern: String,
+    pub crawled: usize,
+    pub cut_off: Vec<ReportItem>,
+}
+
+/// Represents what an incremental crawl took from the earlier crawl.
+///
+/// * `unchanged_menus`: Number of menus with the same contents as before
+/// * `carried_forward`: Number of items taken from the earlier crawl
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportIncremental {
+    pub unchanged_menus: usize,
+    pub carried_forward: usize,
+}
+
+impl Crawler {
+    /// Returns the outcome of the crawl so far, as printed by `Crawler::report`
+    pub fn crawl_report(&self) -> CrawlReport {
+        let to_items = |item_type: ItemType| {
+            sort_by_line(self.items.received(item_type).map(ReportItem::from).collect(), ReportItem::line)
+        };
+        let count = |item_type: ItemType| self.items.received(item_type).count() as u32;
+
+        // Nothing until a file of that type was downloaded
+        let to_file = |files: Vec<&CrawledItem>| files.first().map(|&file| ReportFile::from(file));
+
+        let error_items = self.error_items.iter()
+            .map(|(display_string, locations)| ReportErrorItem {
+                display_string: display_string.clone(),
+                locations: locations.iter()
+                    .map(|(server_details, selector, line): &MenuLocation| ReportLocation {
+                        server_details: server_details.to_string(),
+                        selector: selector.to_string(),
+                        line: *line,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        // A server linked from several menus is listed once, as connected if any attempt was
+        let mut servers: Vec<(&Rc<String>, u16, bool)> = Vec::new();
+        let mut positions: HashMap<_, usize> = HashMap::new();
+        for (server_name, server_port, connected) in &self.external_servers {
+            match positions.entry((server_name, *server_port)) {
+                Entry::Occupied(position) => servers[*position.get()].2 |= connected,
+                Entry::Vacant(position) => {
+                    position.insert(servers.len());
+                    servers.push((server_name, *server_port, *connected));
+                },
+            }
+        }
+        let external_servers = servers.into_iter()
+            .map(|(server_name, server_port, connected)| ReportExternalServer {
+                server_name: server_name.to_string(),
+                server_port,
+                connected,
+                linked_from: self.items.iter()
+                    .filter(|item| matches!(item.outcome, ItemOutcome::External(_)))
+                    .filter(|item| item.server_name == **server_name && item.server_port == server_port)
+                    .flat_map(|item| item.referrers.iter().cloned())
+                    .collect(),
+            })
+            .collect();
+
+        let invalid_references = self.invalid_references.iter()
+            .map(|(details, outcome, origin)| ReportReference {
+                details: details.clone(),
+                outcome: *outcome,
+                linked_from: match origin {
+                    ReferenceOrigin::Item(server_details, selector) => self.items.get(server_details, selector)
+                        .map_or_else(Vec::new, |item| item.referrers.clone()),
+                    ReferenceOrigin::Line(referrer) => vec![referrer.clone()],
+                },
+            })
+            .collect();
+
+        CrawlReport {
+            schema_version: REPORT_SCHEMA_VERSION,
+            root_server: format!("{}:{}", self.root_server_name, self.root_server_port),
+
+            dir_count: count(ItemType::Dir),
+            dirs: to_items(ItemType::Dir),
+            txt_file_count: count(ItemType::Txt),
+            txt_files: to_items(ItemType::Txt),
+            bin_file_count: count(ItemType::Bin),
+            bin_files: to_items(ItemType::Bin),
+
+            smallest_txt: to_file(self.items.smallest(ItemType::Txt, 1)),
+            smallest_txt_contents: self.smallest_contents.as_ref().map(|(_, contents)| contents.clone()).unwrap_or_default(),
+            largest_txt: to_file(self.items.largest(ItemType::Txt, 1)),
+            smallest_bin: to_file(self.items.smallest(ItemType::Bin, 1)),
+            largest_bin: to_file(self.items.largest(ItemType::Bin, 1)),
+
+            error_items: sort_by_line(error_items, ReportErrorItem::line),
+            external_servers: sort_by_line(external_servers, ReportExternalServer::line),
+            invalid_references: sort_by_line(invalid_references, ReportReference::line),
+
+            incremental: self.previous.as_ref().map(|_| ReportIncremental {
+                unchanged_menus: self.unchanged.len(),
+                carried_forward: self.carried.len(),
+            }),
+            duplicates: self.duplicates(),
+
+            top_n: self.top_n,
+            size_stats: SIZE_STATS_TYPES.iter()
+                .map(|&item_type| SizeStats::of(&self.items, item_type, self.top_n))
+                .collect(),
+            host_totals: HostTotal::of(&self.items),
+
+            graph: Some(GraphAnalysis::of(&self.items)),
+            traps: self.traps.suspected()
+                .map(|trap| ReportTrap::of(trap, &self.items))
+                .collect(),
+            merged_variants: sort_by_line(self.canonical.merged().to_vec(), MergedVariant::to_string),
+        }
+    }
+}
+
+impl CrawlReport {
+    /// Renders the report as text, as printed by `Crawler::report`
+    pub fn render(&self) -> String {
+        let join = |lines: Vec<String>| lines.join("\n\t\t");
+        let file_line = |file: &Option<ReportFile>| {
+            file.as_ref().map_or_else(|| String::from(": "), |file| file.item.line())
+        };
+        // Crawls without a file of a type print the sizes they start with
+        let file_size = |file: &Option<ReportFile>, none: u64| {
+            file.as_ref().map_or(none, |file| file.size)
+        };
+
+        // Only incremental crawls take items from an earlier crawl
+        let incremental = match &self.incremental {
+            Some(incremental) => format!(
+                "\tIncremental crawl:\n\
+                \t\tUnchanged menus: {}\n\
+                \t\tItems carried forward: {}\n\n",
+                incremental.unchanged_menus,
+                incremental.carried_forward,
+            ),
+            None => String::new(),
+        };
+
+        // Duplicates are only known to storages which keep track of them
+        let duplicates = match &self.duplicates {
+            Some(groups) => format!(
+                "\tDuplicate file groups: {}\n\
+                \t\tBytes saved: {}\n\
+                \t\t{}\n\n",
+                groups.len(),
+                groups.iter().map(DuplicateGroup::bytes_saved).sum::<u64>(),
+                join(groups.iter().map(format_duplicate_group).collect()),
+            ),
+            None => String::new(),
+        };
+
+        let size_stats = self.size_stats.iter()
+            .map(|stats| stats.render(self.top_n))
+            .collect::<String>();
+        let host_totals = match self.host_totals.is_empty() {
+            true => String::new(),
+            false => format!(
+                "\tBytes received per server:\n\
+                \t\t{}\n\n",
+                join(self.host_totals.iter().map(HostTotal::line).collect()),
+            ),
+        };
+
+        let graph = self.graph.as_ref()
+            .map_or_else(String::new, |graph| graph.render(self.top_n));
+
+        let traps = match self.traps.is_empty() {
+            true => String::new(),
+            false => format!(
+                "\tSuspected spider traps: {}\n\
+                \t\t{}\n\n",
+                self.traps.len(),
+                join(self.traps.iter().map(ReportTrap::lines).collect()),
+            ),
+        };
+
+        let merged_variants = match self.merged_variants.is_empty() {
+            true => String::new(),
+            false => format!(
+                "\tMerged variants: {}\n\
+                \t\t{}\n\n",
+                self.merged_variants.len(),
+                join(self.merged_variants.iter().map(MergedVariant::to_string).collect()),
+            ),
+        };
+
+        format!(
+            "\nSTART CRAWLER REPORT\n\n\
+            \tNumber of Gopher directories: {}\n\
+            \t\t{}\n\n\
+            \tNumber of simple text files: {}\n\
+            \t\t{}\n\n\
+            \tNumber of binary files: {}\n\
+            \t\t{}\n\n\
+            \tSmallest text file: {}\n\
+            \t\tSize: {} bytes\n\
+            \t\tContents: {}\n\n\
+            \tSize of the largest text file: {} bytes\n\
+            \t\t{}\n\n\
+            \tSize of the smallest binary file: {} bytes\n\
+            \t\t{}\n\n\
+            \tSize of the largest binary file: {} bytes\n\
+            \t\t{}\n\n\
+            {}\
+            {}\
+            \tThe number of unique invalid references (error types): {}\n\
+            \t\t{}\n\n\
+            \tList of external servers:\n\
+            \t\t{}\n\n\
+            \tReferences that have issues/errors:\n\
+            \t\t{}\n\n\
+            {}\
+            {}\
+            {}\
+            {}\
+            {}\
+            END CRAWLER REPORT",
+            self.dir_count,
+            join(self.dirs.iter().map(ReportItem::line).collect()),
+            self.txt_file_count,
+            join(self.txt_files.iter().map(ReportItem::line).collect()),
+            self.bin_file_count,
+            join(self.bin_files.iter().map(ReportItem::line).collect()),
+            file_line(&self.smallest_txt),
+            file_size(&self.smallest_txt, u64::MAX),
+            self.smallest_txt_contents,
+            file_size(&self.largest_txt, 0),
+            file_line(&self.largest_txt),
+            file_size(&self.smallest_bin, u64::MAX),
+            file_line(&self.smallest_bin),
+            file_size(&self.largest_bin, 0),
+            file_line(&self.largest_bin),
+            size_stats,
+            host_totals,
+            self.error_items.len(),
+            join(self.error_items.iter().map(ReportErrorItem::line).collect()),
+            join(self.external_servers.iter().map(ReportExternalServer::line).collect()),
+            join(self.invalid_references.iter().map(ReportReference::line).collect()),
+            traps,
+            merged_variants,
+            incremental,
+            duplicates,
+            graph,
+        )
+    }
+}
+
+impl From<&CrawledItem> for ReportItem {
+    fn from(item: &CrawledItem) -> ReportItem {
+        ReportItem {
+            server_details: item.server_details(),
+            selector: item.selector.clone(),
+            title: Some(item.display_string.clone()).filter(|title| !title.is_empty()),
+        }
+    }
+}
+
+impl From<&CrawledItem> for ReportFile {
+    fn from(item: &CrawledItem) -> ReportFile {
+        ReportFile { item: ReportItem::from(item), size: item.size.unwrap_or(0) }
+    }
+}
+
+impl ReportItem {
+    /// Returns the line the item is printed as
+    pub(super) fn line(&self) -> String {
+        match &self.title {
+            Some(title) if !title.is_empty() => format!("{}: {} ({})", self.server_details, self.selector, title),
+            _ => format!("{}: {}", self.server_details, self.selector),
+        }
+    }
+}
+
+impl ReportErrorItem {
+    /// Returns the line the error item is printed as
+    fn line(&self) -> String {
+        let locations = self.locations.iter()
+            .map(|location| format!("{}: {} (line {})", location.server_details, location.selector, location.line))
+            .collect::<Vec<_>>();
+        format!("\"{}\" in {}", self.display_string, locations.join(", "))
+    }
+}
+
+impl ReportExternalServer {
+    /// Returns the line the external server is printed as
+    fn line(&self) -> String {
+        let status = if self.connected {
+            "connected successfully"
+        } else {
+            "did not connect"
+        };
+        format!("{}:{} {}{}", self.server_name, self.server_port, status, linked_from(&self.linked_from))
+    }
+}
+
+impl ReportReference {
+    /// Returns the line the reference is printed as
+    fn line(&self) -> String {
+        format!("{} {}{}", self.outcome, self.details, linked_from(&self.linked_from))
+    }
+}
+
+impl ReportTrap {
+    /// Returns the trap as reported, with the menus cut off by it
+    fn of(trap: &SpiderTrap, items: &CrawledItems) -> ReportTrap {
+        ReportTrap {
+            kind: trap.kind,
+            server_details: trap.server_details.clone(),
+            pattern: trap.pattern.clone(),
+            crawled: trap.crawled,
+            cut_off: trap.cut_off.iter()
+                .map(|selector| items.get(&trap.server_details, selector).map_or_else(
+                    || ReportItem { server_details: trap.server_details.clone(), selector: selector.clone(), title: None },
+                    ReportItem::from
+                ))
+                .collect(),
+        }
+    }
+
+    /// Returns the lines the trap is printed as: a summary, then every menu cut off
+    fn lines(&self) -> String {
+        let cut_off = self.cut_off.iter()
+            .map(|item| format!("\n\t\t\t{}", item.line()))
+            .collect::<String>();
+        format!("{} {} {}: {} menus crawled, {} cut off{}",
+            self.kind, self.server_details, self.pattern, self.crawled, self.cut_off.len(), cut_off)
+    }
+}
+
+/// Returns the menu lines linking to an external server or invalid reference, as
+/// printed after it
+fn linked_from(referrers: &[Referrer]) -> String {
+    if referrers.is_empty() {
+        return String::new()
+    }
+    let lines = referrers.iter().map(Referrer::line).collect::<Vec<_>>();
+    format!(", linked from {}", lines.join(", "))
+}
+
+/// Returns the line a group of duplicates is printed as
+fn format_duplicate_group(group: &DuplicateGroup) -> String {
+    let items = group.items.iter()
+        .map(|(server_details, selector)| format!("{}: {}", server_details, selector))
+        .collect::<Vec<_>>();
+    format!("{} bytes, {} copies: {}", group.size, group.items.len(), items.join(", "))
+}
+
+/// Sorts entries alphabetically (ignoring case) by the line they are printed as
+pub(super) fn sort_by_line<T>(mut entries: Vec<T>, line: impl Fn(&T) -> String) -> Vec<T> {
+    entries.sort_by_cached_key(|entry| line(entry).to_lowercase());
+    entries
+}
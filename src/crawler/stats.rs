@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::gopher::response::ItemType;
+
+use super::{
+    item::{CrawledItem, CrawledItems},
+    report::ReportFile
+};
+
+/// Number of smallest and largest items listed per item type, unless configured
+/// with `Crawler::with_top_n`
+pub const DEFAULT_TOP_N: usize = 5;
+
+/// Item types with size statistics, in the order they are reported
+pub const SIZE_STATS_TYPES: [ItemType; 3] = [ItemType::Dir, ItemType::Txt, ItemType::Bin];
+
+/// Longest bar of a histogram in the text report (characters)
+const HISTOGRAM_WIDTH: usize = 30;
+
+/// Represents the distribution of the sizes of the received items of a type.
+///
+/// Percentiles use the nearest-rank method, so they are always the size of a
+/// received item. The median is the 50th percentile.
+///
+/// * `item_type`: The type of the items
+/// * `count`: Number of received items
+/// * `total_bytes`: Sum of the sizes of the received items
+/// * `min`, `median`, `p95`, `max`: Smallest size, median size, 95th percentile
+///   and largest size (bytes). Nothing if no item was received.
+/// * `mean`: Mean size (bytes). Nothing if no item was received.
+/// * `smallest`: The smallest items, smallest first. Holds the configured number
+///   of items plus any items with the same size as the last of them.
+/// * `largest`: The largest items, largest first, with ties as in `smallest`
+/// * `histogram`: Number of items per power of ten of sizes (0 - 9 bytes,
+///   10 - 99 bytes, ...), from the smallest to the largest non-empty bucket
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SizeStats {
+    pub item_type: ItemType,
+    pub count: usize,
+    pub total_bytes: u64,
+    pub min: Option<u64>,
+    pub median: Option<u64>,
+    pub mean: Option<f64>,
+    pub p95: Option<u64>,
+    pub max: Option<u64>,
+    pub smallest: Vec<ReportFile>,
+    pub largest: Vec<ReportFile>,
+    pub histogram: Vec<SizeBucket>,
+}
+
+/// Represents a bucket of a size histogram.
+///
+/// * `min`: Smallest size in the bucket (bytes)
+/// * `max`: Largest size in the bucket (bytes)
+/// * `count`: Number of items in the bucket
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeBucket {
+    pub min: u64,
+    pub max: u64,
+    pub count: usize,
+}
+
+/// Represents the bytes received from a server.
+///
+/// * `server_details`: hostname:port of the server
+/// * `dir_bytes`: Bytes of menus received from the server
+/// * `txt_bytes`: Bytes of simple text files received from the server
+/// * `bin_bytes`: Bytes of binary files received from the server
+/// * `total_bytes`: Sum of the above
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostTotal {
+    pub server_details: String,
+    pub dir_bytes: u64,
+    pub txt_bytes: u64,
+    pub bin_bytes: u64,
+    pub total_bytes: u64,
+}
+
+impl SizeStats {
+    /// Computes the size statistics of the received items of a type
+    ///
+    /// # Arguments
+    /// * `items`: Every item discovered by the crawl
+    /// * `item_type`: The type of the items
+    /// * `top_n`: Number of smallest and largest items to list
+    pub fn of(items: &CrawledItems, item_type: ItemType, top_n: usize) -> SizeStats {
+        let smallest = items.smallest(item_type, usize::MAX);
+        let sizes = smallest.iter().filter_map(|item| item.size).collect::<Vec<_>>();
+        let total_bytes = sizes.iter().sum::<u64>();
+        let percentile = |percent: usize| {
+            // Nearest-rank: the smallest size with at least `percent` of the sizes at or below it
+            let rank = (percent * sizes.len()).div_ceil(100).max(1);
+            sizes.get(rank - 1).copied()
+        };
+
+        SizeStats {
+            item_type,
+            count: sizes.len(),
+            total_bytes,
+            min: sizes.first().copied(),
+            median: percentile(50),
+            mean: (!sizes.is_empty()).then(|| total_bytes as f64 / sizes.len() as f64),
+            p95: percentile(95),
+            max: sizes.last().copied(),
+            smallest: with_ties(smallest, top_n),
+            largest: with_ties(items.largest(item_type, usize::MAX), top_n),
+            histogram: histogram(&sizes),
+        }
+    }
+
+    /// Renders the statistics as a section of the text report
+    pub(super) fn render(&self, top_n: usize) -> String {
+        let mut section = format!("\tSize statistics of {}: {} received, {} bytes in total\n",
+            type_heading(self.item_type), self.count, self.total_bytes);
+        let (Some(min), Some(median), Some(mean), Some(p95), Some(max)) = (self.min, self.median, self.mean, self.p95, self.max) else {
+            return section + "\n"
+        };
+
+        section += &format!("\t\tMin: {min} bytes, median: {median} bytes, mean: {mean:.1} bytes, 95th percentile: {p95} bytes, max: {max} bytes\n");
+        for (heading, files) in [("Smallest", &self.smallest), ("Largest", &self.largest)] {
+            if files.is_empty() {
+                continue
+            }
+            let ties = if files.len() > top_n { " (including ties)" } else { "" };
+            section += &format!("\t\t{heading} {top_n}{ties}:\n");
+            for file in files {
+                section += &format!("\t\t\t{} bytes: {}\n", file.size, file.item.line());
+            }
+        }
+
+        let most = self.histogram.iter().map(|bucket| bucket.count).max().unwrap_or(1);
+        section += "\t\tHistogram:\n";
+        for bucket in &self.histogram {
+            let bar = "#".repeat((bucket.count * HISTOGRAM_WIDTH).div_ceil(most));
+            section += &format!("\t\t\t{} - {} bytes: {} {}\n", bucket.min, bucket.max, bucket.count, bar);
+        }
+        section + "\n"
+    }
+}
+
+impl HostTotal {
+    /// Sums the bytes received from each server, sorted alphabetically (ignoring
+    /// case) by server details. Servers nothing was received from are left out.
+    pub fn of(items: &CrawledItems) -> Vec<HostTotal> {
+        let mut totals: BTreeMap<(String, String), HostTotal> = BTreeMap::new();
+        for item in items.iter() {
+            let Some(size) = item.size else { continue };
+            let server_details = item.server_details();
+            let key = (server_details.to_lowercase(), server_details.clone());
+            let total = totals.entry(key).or_insert_with(|| HostTotal {
+                server_details,
+                dir_bytes: 0,
+                txt_bytes: 0,
+                bin_bytes: 0,
+                total_bytes: 0,
+            });
+            match item.item_type {
+                ItemType::Dir => total.dir_bytes += size,
+                ItemType::Txt => total.txt_bytes += size,
+                ItemType::Bin => total.bin_bytes += size,
+                _ => continue,
+            }
+            total.total_bytes += size;
+        }
+        totals.into_values().collect()
+    }
+
+    /// Returns the line the server is printed as
+    pub(super) fn line(&self) -> String {
+        format!("{}: {} bytes ({}: {}, {}: {}, {}: {})",
+            self.server_details, self.total_bytes,
+            type_heading(ItemType::Dir), self.dir_bytes,
+            type_heading(ItemType::Txt), self.txt_bytes,
+            type_heading(ItemType::Bin), self.bin_bytes)
+    }
+}
+
+/// Returns the name items of a type are reported as
+pub fn type_heading(item_type: ItemType) -> &'static str {
+    match item_type {
+        ItemType::Dir => "Gopher directories",
+        ItemType::Txt => "simple text files",
+        ItemType::Bin => "binary files",
+        _ => "other items",
+    }
+}
+
+/// Takes the first `n` of items sorted by size, and any items after them with
+/// the same size as the last one taken
+fn with_ties(sorted: Vec<&CrawledItem>, n: usize) -> Vec<ReportFile> {
+    let last = n.checked_sub(1)
+        .and_then(|index| sorted.get(index))
+        .map(|item| item.size);
+
+    sorted.into_iter()
+        .enumerate()
+        .take_while(|(index, item)| *index < n || Some(item.size) == last)
+        .map(|(_, item)| ReportFile::from(item))
+        .collect()
+}
+
+/// Counts sorted sizes per power of ten
+fn histogram(sizes: &[u64]) -> Vec<SizeBucket> {
+    let bucket = |size: u64| size.checked_ilog10().unwrap_or(0);
+    let (Some(&smallest), Some(&largest)) = (sizes.first(), sizes.last()) else {
+        return Vec::new()
+    };
+
+    (bucket(smallest)..=bucket(largest))
+        .map(|power| SizeBucket {
+            min: if power == 0 { 0 } else { 10u64.pow(power) },
+            max: 10u64.checked_pow(power + 1).map_or(u64::MAX, |next| next - 1),
+            count: sizes.iter().filter(|&&size| bucket(size) == power).count(),
+        })
+        .collect()
+}
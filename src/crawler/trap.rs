@@ -0,0 +1,264 @@
+use std::{
+    collections::HashMap,
+    fmt
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::gopher::response::MenuLine;
+use crate::storage::content::content_hash;
+
+/// Number of menus matching the same pattern which are crawled before further
+/// menus matching it are cut off, unless configured with
+/// `Crawler::with_trap_threshold`. Also the deepest a selector may be nested.
+pub const DEFAULT_TRAP_THRESHOLD: usize = 20;
+
+/// Represents the heuristics suggesting that a menu is part of a spider trap,
+/// e.g. a generated maze with no end.
+///
+/// * `NumberedSelectors`: Selectors of menus which only differ in their numbers,
+///   linked from a menu matching the same pattern or nesting numbers deeper, e.g.
+///   `/maze/18` linked from `/maze/17`
+/// * `GrowingFolders`: Selectors of menus in the same folder without numbers in
+///   their names, linked from another menu of that folder, e.g. `/maze/east`
+///   linked from `/maze/north`
+/// * `RepeatedComponents`: Selectors of menus repeating a path component, e.g.
+///   `/a/b/a/b`
+/// * `SimilarMenus`: Menus with the same lines apart from their numbers
+/// * `DeepNesting`: Selectors of menus with more path components than the
+///   threshold
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TrapKind {
+    NumberedSelectors,
+    GrowingFolders,
+    RepeatedComponents,
+    SimilarMenus,
+    DeepNesting,
+}
+
+/// Represents a group of menus matching the same pattern, and therefore possibly
+/// a spider trap.
+///
+/// * `kind`: The heuristic which grouped the menus
+/// * `server_details`: hostname:port of the server providing the menus
+/// * `key`: What the menus have in common. The pattern for every heuristic but
+///   `SimilarMenus`, whose key is a hash of the menu lines.
+/// * `pattern`: What the menus have in common, as shown in the report. Numbers
+///   are replaced by `#`, and similar menus are shown as the first of them.
+/// * `crawled`: Number of menus matching the pattern which were followed
+/// * `cut_off`: Selectors of the menus which were cut off. For `SimilarMenus`,
+///   the menus which were received but whose links were not followed. Otherwise,
+///   the menus which were not requested.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpiderTrap {
+    pub kind: TrapKind,
+    pub server_details: String,
+    pub key: String,
+    pub pattern: String,
+    pub crawled: usize,
+    pub cut_off: Vec<String>,
+}
+
+/// Every group of menus matching the same pattern, in the order they were
+/// found, and the heuristics deciding whether a menu is cut off.
+///
+/// Serializes as the list of groups.
+///
+/// * `traps`: Every group of menus, suspected of being a trap or not
+/// * `index`: Position of every group in `traps`, keyed by (kind, server
+///   details, key) triples
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<SpiderTrap>", into = "Vec<SpiderTrap>")]
+pub struct SpiderTraps {
+    traps: Vec<SpiderTrap>,
+    index: HashMap<(TrapKind, String, String), usize>,
+}
+
+impl fmt::Display for TrapKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrapKind::NumberedSelectors   => write!(f, "Numbered selectors"),
+            TrapKind::GrowingFolders      => write!(f, "Growing folders"),
+            TrapKind::RepeatedComponents  => write!(f, "Repeated path components"),
+            TrapKind::SimilarMenus        => write!(f, "Similar menus"),
+            TrapKind::DeepNesting         => write!(f, "Deep nesting"),
+        }
+    }
+}
+
+impl SpiderTraps {
+    /// Constructs an empty collection
+    pub fn new() -> SpiderTraps {
+        SpiderTraps::default()
+    }
+
+    /// Returns every group of menus which had menus cut off, in the order they
+    /// were found
+    pub fn suspected(&self) -> impl Iterator<Item = &SpiderTrap> {
+        self.traps.iter().filter(|trap| !trap.cut_off.is_empty())
+    }
+
+    /// Decides whether a menu is requested, before it is requested. A menu is cut
+    /// off if `threshold` menus with the same pattern were already crawled, or if
+    /// its selector has more than `threshold` path components. Otherwise, it counts
+    /// towards the patterns of its selector.
+    ///
+    /// Numbered selectors and folders only form a pattern when the menu is linked
+    /// from a menu of the same pattern (or, for numbers, a menu nesting them one
+    /// level up), as a maze leading from room to room would. Menus listed side by
+    /// side in a single menu, e.g. `/issues/1` to `/issues/50`, are not cut off.
+    ///
+    /// # Arguments
+    /// * `server_details`: hostname:port of the server providing the menu
+    /// * `selector`: String used to request the menu
+    /// * `referrer`: (server details, selector) of the menu linking to the menu
+    /// * `threshold`: Number of menus matching a pattern which are crawled
+    ///
+    /// # Returns
+    /// The heuristic the menu is cut off by. Nothing if it should be requested.
+    pub fn check_selector(&mut self, server_details: &str, selector: &str, referrer: (&str, &str), threshold: usize) -> Option<TrapKind> {
+        let components = selector.split('/').filter(|component| !component.is_empty()).collect::<Vec<_>>();
+
+        if components.len() > threshold {
+            let pattern = format!("/{}/…", components[..threshold].join("/"));
+            let trap = self.entry(TrapKind::DeepNesting, server_details, &pattern, &pattern);
+            trap.cut_off.push(selector.to_string());
+            return Some(TrapKind::DeepNesting)
+        }
+
+        let mut patterns = Vec::new();
+        let (referrer_details, referrer_selector) = referrer;
+        if referrer_details == server_details {
+            let has_numbers = |text: &str| text.contains(|character: char| character.is_ascii_digit());
+            let (masked, masked_referrer) = (mask_numbers(selector), mask_numbers(referrer_selector));
+            let nested = masked.strip_prefix(masked_referrer.as_str()).is_some_and(|rest| rest.starts_with('/'));
+            if has_numbers(selector) && has_numbers(referrer_selector) && (masked == masked_referrer || nested) {
+                patterns.push((TrapKind::NumberedSelectors, collapse_nesting(&masked)));
+            }
+
+            let name = selector.rsplit('/').next().unwrap_or_default();
+            if let Some(parent) = folder(selector).filter(|&parent| !has_numbers(name) && folder(referrer_selector) == Some(parent)) {
+                patterns.push((TrapKind::GrowingFolders, format!("{parent}/*")));
+            }
+        }
+        if let Some(repeated) = (1..components.len()).find(|&end| components[..end].contains(&components[end])) {
+            patterns.push((TrapKind::RepeatedComponents, format!("/{}", components[..=repeated].join("/"))));
+        }
+
+        for (kind, pattern) in &patterns {
+            let trap = self.entry(*kind, server_details, pattern, pattern);
+            if trap.crawled >= threshold {
+                trap.cut_off.push(selector.to_string());
+                return Some(*kind)
+            }
+        }
+        for (kind, pattern) in &patterns {
+            self.entry(*kind, server_details, pattern, pattern).crawled += 1;
+        }
+        None
+    }
+
+    /// Decides whether the links of a received menu are followed. They are not
+    /// once `threshold` menus with the same lines (apart from their numbers) were
+    /// followed.
+    ///
+    /// # Arguments
+    /// * `server_details`: hostname:port of the server providing the menu
+    /// * `selector`: String used to request the menu
+    /// * `lines`: Every valid response line of the menu
+    /// * `threshold`: Number of similar menus which are followed
+    ///
+    /// # Returns
+    /// True if the links of the menu should be followed. False otherwise.
+    pub fn check_contents(&mut self, server_details: &str, selector: &str, lines: &[MenuLine], threshold: usize) -> bool {
+        // Menus without links can not lead into a trap
+        if lines.iter().all(|line| line.selector.is_empty()) {
+            return true
+        }
+        let contents = lines.iter()
+            .map(|line| format!("{}{}\t{}\t{}:{}", line.item_type.to_char(), line.display_string, line.selector, line.server_name, line.server_port))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let key = content_hash(mask_numbers(&contents).as_bytes());
+
+        let trap = self.entry(TrapKind::SimilarMenus, server_details, &key, selector);
+        if trap.crawled >= threshold {
+            trap.cut_off.push(selector.to_string());
+            return false
+        }
+        trap.crawled += 1;
+        true
+    }
+
+    /// Returns the group of menus with a kind, server and key, adding it if it
+    /// was not found yet
+    fn entry(&mut self, kind: TrapKind, server_details: &str, key: &str, pattern: &str) -> &mut SpiderTrap {
+        let index_key = (kind, server_details.to_string(), key.to_string());
+        let position = match self.index.get(&index_key) {
+            Some(&position) => position,
+            None => {
+                self.traps.push(SpiderTrap {
+                    kind,
+                    server_details: server_details.to_string(),
+                    key: key.to_string(),
+                    pattern: pattern.to_string(),
+                    crawled: 0,
+                    cut_off: Vec::new(),
+                });
+                self.index.insert(index_key, self.traps.len() - 1);
+                self.traps.len() - 1
+            },
+        };
+        &mut self.traps[position]
+    }
+}
+
+impl From<Vec<SpiderTrap>> for SpiderTraps {
+    fn from(traps: Vec<SpiderTrap>) -> SpiderTraps {
+        let index = traps.iter()
+            .enumerate()
+            .map(|(position, trap)| ((trap.kind, trap.server_details.clone(), trap.key.clone()), position))
+            .collect();
+        SpiderTraps { traps, index }
+    }
+}
+
+impl From<SpiderTraps> for Vec<SpiderTrap> {
+    fn from(traps: SpiderTraps) -> Vec<SpiderTrap> {
+        traps.traps
+    }
+}
+
+/// Returns the folder of a selector, i.e. everything before its last `/`
+fn folder(selector: &str) -> Option<&str> {
+    selector.rsplit_once('/').map(|(folder, _)| folder)
+}
+
+/// Replaces path components which repeat the numbered component before them
+/// with a single `…`, so numbers nested to any depth share a pattern
+fn collapse_nesting(masked: &str) -> String {
+    let mut components = Vec::new();
+    let mut previous = None;
+    for component in masked.split('/') {
+        if previous != Some(component) || !component.contains('#') {
+            components.push(component);
+        } else if components.last() != Some(&"…") {
+            components.push("…");
+        }
+        previous = Some(component);
+    }
+    components.join("/")
+}
+
+/// Replaces every run of digits with `#`
+fn mask_numbers(text: &str) -> String {
+    let mut masked = String::with_capacity(text.len());
+    for character in text.chars() {
+        if !character.is_ascii_digit() {
+            masked.push(character);
+        } else if !masked.ends_with('#') {
+            masked.push('#');
+        }
+    }
+    masked
+}
@@ -1,3 +1,4 @@
+pub mod menu;
 pub mod request; 
 pub mod response;
 
@@ -61,11 +62,19 @@ pub fn send_and_recv(request: &Request) -> std::io::Result<Response> {
 
     // Send the request to the Gopher server
     let selector = format!("{}{}", request.selector, CRLF);
-    stream.write_all(selector.as_bytes())?; 
+    if let Err(error) = stream.write_all(selector.as_bytes()) {
+        match error.kind() {
+            ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted | ErrorKind::BrokenPipe => {
+                debug_eprintln!("Connection reset while sending request: {error}");
+                return Ok(Response::new(Vec::new(), ResponseOutcome::ConnectionReset))
+            },
+            _ => return Err(error),
+        }
+    }
 
     // Receive the request from the Gopher server
     let response = recv(&stream, &request.item_type)?; 
-    return Ok(response)
+    Ok(response)
 }
 
 /// Attempts to connect to the provided Gopher server.
@@ -125,7 +134,7 @@ fn recv(mut stream: &TcpStream, item_type: &ItemType) -> std::io::Result<Respons
                 buffer.extend_from_slice(&chunk[..n]);
                 
                 // Overall timeout
-                if start.elapsed().as_secs() == 5 {
+                if start.elapsed().as_secs() >= 5 {
                     debug_eprintln!("File too long");
                     return Ok(Response::new(buffer, ResponseOutcome::FileTooLong));
                 }
@@ -138,6 +147,11 @@ fn recv(mut stream: &TcpStream, item_type: &ItemType) -> std::io::Result<Respons
                         debug_eprintln!("Read timed out");
                         return Ok(Response::new(buffer, ResponseOutcome::Timeout));
                     }, 
+                    // Server dropped the connection part way through the response
+                    ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted => {
+                        debug_eprintln!("Connection reset");
+                        return Ok(Response::new(buffer, ResponseOutcome::ConnectionReset));
+                    },
                     _ => return Err(error),
                 }
             }
@@ -147,7 +161,7 @@ fn recv(mut stream: &TcpStream, item_type: &ItemType) -> std::io::Result<Respons
     if matches!(*item_type, ItemType::Txt) || matches!(*item_type, ItemType::Dir) {
         if buffer.len() < 3 {
             Ok(Response::new(buffer, ResponseOutcome::MissingEndLine))
-        } else if buffer.iter().rev().take(3).eq(&[b'\n', b'\r', b'.']) {
+        } else if buffer.ends_with(b".\r\n") {
             buffer.truncate(buffer.len() - 3);
             Ok(Response::new(buffer, ResponseOutcome::Complete))
         } else {
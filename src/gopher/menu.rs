@@ -0,0 +1,272 @@
+use std::fmt;
+
+use super::response::{ItemType, MenuLine, Response, ResponseLineError, ResponseOutcome};
+
+use crate::{CRLF, TAB};
+
+/// Host name used for the placeholder fields of info and error lines
+const NULL_HOST: &str = "null.host";
+
+/// Represents a single line of a Gopher menu.
+///
+/// * `item_type`: The type of the item
+/// * `display_string`: Human-readable display string, without the item type
+/// * `selector`: String being used to request the item
+/// * `server_name`: Host name or IP address of the server providing the item
+/// * `server_port`: The port number of the server providing the item
+/// * `gopher_plus`: Optional fields after the port number (e.g. `+` for Gopher+ items)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MenuItem {
+    pub item_type:      ItemType,
+    pub display_string: String,
+    pub selector:       String,
+    pub server_name:    String,
+    pub server_port:    u16,
+    pub gopher_plus:    Option<String>,
+}
+
+/// Represents a Gopher menu (directory listing).
+///
+/// * `items`: The lines of the menu in order
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Menu {
+    pub items: Vec<MenuItem>,
+}
+
+/// Builds a `Menu` whose links point at a single server by default.
+///
+/// * `server_name`: Host name used for local links
+/// * `server_port`: Port number used for local links
+/// * `items`: The lines added so far
+pub struct MenuBuilder {
+    server_name: String,
+    server_port: u16,
+    items: Vec<MenuItem>,
+}
+
+impl MenuItem {
+    /// Constructs a new `MenuItem` instance without Gopher+ fields
+    ///
+    /// # Arguments
+    /// * `item_type`: The type of the item
+    /// * `display_string`: Human-readable display string
+    /// * `selector`: String being used to request the item
+    /// * `server_name`: Host name of the server providing the item
+    /// * `server_port`: Port number of the server providing the item
+    pub fn new(item_type: ItemType, display_string: &str, selector: &str, server_name: &str, server_port: u16) -> MenuItem {
+        MenuItem {
+            item_type,
+            display_string: display_string.to_string(),
+            selector: selector.to_string(),
+            server_name: server_name.to_string(),
+            server_port,
+            gopher_plus: None,
+        }
+    }
+
+    /// Constructs an informational line. Placeholder values are used for the
+    /// selector, host and port.
+    pub fn info(text: &str) -> MenuItem {
+        MenuItem::new(ItemType::Info, text, "", NULL_HOST, 1)
+    }
+
+    /// Constructs an error line. Placeholder values are used for the selector,
+    /// host and port.
+    pub fn error(text: &str) -> MenuItem {
+        MenuItem::new(ItemType::Err, text, "", NULL_HOST, 1)
+    }
+
+    /// Marks the item with trailing Gopher+ fields (usually `+` or `?`)
+    pub fn with_gopher_plus(mut self, fields: &str) -> MenuItem {
+        self.gopher_plus = Some(fields.to_string());
+        self
+    }
+
+    /// Writes the item as a response line, including the CRLF.
+    ///
+    /// Tabs and line breaks in the display string are replaced with spaces since
+    /// RFC 1436 has no way of escaping them. Any other field containing them
+    /// cannot be represented and is rejected.
+    ///
+    /// # Returns
+    /// Nothing if sucessfull. Otherwise, returns the appropriate `MenuError`.
+    fn write_to(&self, out: &mut String) -> Result<(), MenuError> {
+        let type_char = self.item_type.to_char();
+        // A line starting with '.' could be mistaken for the last line
+        if type_char == '.' || is_reserved(type_char) {
+            return Err(MenuError::InvalidItemType(type_char));
+        }
+        if self.selector.contains(is_reserved) {
+            return Err(MenuError::InvalidSelector(self.selector.clone()));
+        }
+        // Only info lines may leave the host empty
+        if self.server_name.contains(is_reserved) || (self.server_name.is_empty() && self.item_type != ItemType::Info) {
+            return Err(MenuError::InvalidHost(self.server_name.clone()));
+        }
+        if let Some(fields) = &self.gopher_plus {
+            // Gopher+ fields may be tab seperated, but must not break the line
+            if fields.contains(['\r', '\n']) {
+                return Err(MenuError::InvalidGopherPlus(fields.clone()));
+            }
+        }
+
+        out.push(type_char);
+        out.extend(self.display_string.chars().map(|c| if is_reserved(c) { ' ' } else { c }));
+        for field in [self.selector.as_str(), self.server_name.as_str()] {
+            out.push_str(TAB);
+            out.push_str(field);
+        }
+        out.push_str(TAB);
+        out.push_str(&self.server_port.to_string());
+        if let Some(fields) = &self.gopher_plus {
+            out.push_str(TAB);
+            out.push_str(fields);
+        }
+        out.push_str(CRLF);
+        Ok(())
+    }
+}
+
+impl From<MenuLine<'_>> for MenuItem {
+    fn from(line: MenuLine<'_>) -> MenuItem {
+        MenuItem {
+            item_type: line.item_type,
+            display_string: line.display_string.to_string(),
+            selector: line.selector.to_string(),
+            server_name: line.server_name.to_string(),
+            server_port: line.server_port,
+            gopher_plus: line.gopher_plus.map(String::from),
+        }
+    }
+}
+
+impl Menu {
+    /// Starts building a menu whose links point at `server_name`:`server_port`
+    pub fn builder(server_name: &str, server_port: u16) -> MenuBuilder {
+        MenuBuilder {
+            server_name: server_name.to_string(),
+            server_port,
+            items: Vec::new(),
+        }
+    }
+
+    /// Parses a menu from the raw bytes of a directory response. The last line
+    /// `.`CRLF is optional. Empty lines are skipped.
+    ///
+    /// # Returns
+    /// A new `Menu` if every line is valid. Otherwise, returns the first
+    /// `ResponseLineError`.
+    pub fn parse(buffer: &[u8]) -> Result<Menu, ResponseLineError> {
+        let buffer = buffer.strip_suffix(b".\r\n").unwrap_or(buffer);
+        let response = Response::new(buffer.to_vec(), ResponseOutcome::Complete);
+
+        let mut items = Vec::new();
+        for line in response.menu_lines() {
+            match line {
+                Ok(line) => items.push(MenuItem::from(line)),
+                Err(ResponseLineError::Empty) => (),
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(Menu { items })
+    }
+
+    /// Serializes the menu as specified by RFC 1436, terminated by the last
+    /// line `.`CRLF.
+    ///
+    /// # Returns
+    /// The serialized menu if every item can be represented. Otherwise, returns
+    /// the appropriate `MenuError`.
+    pub fn serialize(&self) -> Result<String, MenuError> {
+        let mut out = String::new();
+        for item in &self.items {
+            item.write_to(&mut out)?;
+        }
+        out.push('.');
+        out.push_str(CRLF);
+        Ok(out)
+    }
+}
+
+impl MenuBuilder {
+    /// Adds an informational line
+    pub fn info(mut self, text: &str) -> MenuBuilder {
+        // Each line of a multi-line text gets its own info line
+        self.items.extend(
+            text.split('\n').map(|line| MenuItem::info(line.strip_suffix('\r').unwrap_or(line)))
+        );
+        self
+    }
+
+    /// Adds an error line
+    pub fn error(mut self, text: &str) -> MenuBuilder {
+        self.items.push(MenuItem::error(text));
+        self
+    }
+
+    /// Adds a local link of any item type
+    pub fn link(mut self, item_type: ItemType, display_string: &str, selector: &str) -> MenuBuilder {
+        let item = MenuItem::new(item_type, display_string, selector, &self.server_name, self.server_port);
+        self.items.push(item);
+        self
+    }
+
+    /// Adds a local link to a text file
+    pub fn text(self, display_string: &str, selector: &str) -> MenuBuilder {
+        self.link(ItemType::Txt, display_string, selector)
+    }
+
+    /// Adds a local link to a directory
+    pub fn dir(self, display_string: &str, selector: &str) -> MenuBuilder {
+        self.link(ItemType::Dir, display_string, selector)
+    }
+
+    /// Adds a local link to a binary file
+    pub fn binary(self, display_string: &str, selector: &str) -> MenuBuilder {
+        self.link(ItemType::Bin, display_string, selector)
+    }
+
+    /// Adds an arbitrary item, e.g. a link to another server
+    pub fn item(mut self, item: MenuItem) -> MenuBuilder {
+        self.items.push(item);
+        self
+    }
+
+    /// Finishes building the menu
+    pub fn build(self) -> Menu {
+        Menu { items: self.items }
+    }
+}
+
+/// Returns true if `c` would break the structure of a response line
+fn is_reserved(c: char) -> bool {
+    matches!(c, '\t' | '\r' | '\n')
+}
+
+/// Represents the reasons a `Menu` cannot be serialized.
+///
+/// * `InvalidItemType(c)`: The item type cannot start a response line
+/// * `InvalidSelector(selector)`: The selector contains a tab or line break
+/// * `InvalidHost(server_name)`: The host name is empty or contains a tab or 
+///   line break
+/// * `InvalidGopherPlus(fields)`: The Gopher+ fields contain a line break
+#[derive(Debug)]
+pub enum MenuError {
+    InvalidItemType(char),
+    InvalidSelector(String),
+    InvalidHost(String),
+    InvalidGopherPlus(String),
+}
+
+impl std::error::Error for MenuError {}
+
+impl fmt::Display for MenuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MenuError::InvalidItemType(c) => write!(f, "Invalid item type: {c:?}"),
+            MenuError::InvalidSelector(selector) => write!(f, "Invalid selector: {selector:?}"),
+            MenuError::InvalidHost(server_name) => write!(f, "Invalid host name: {server_name:?}"),
+            MenuError::InvalidGopherPlus(fields) => write!(f, "Invalid Gopher+ fields: {fields:?}"),
+        }
+    }
+}
@@ -26,7 +26,7 @@ impl Request {
     /// A new `Request` instance with `server_details`: `server_name`:`server_port`
     pub fn new(selector: Rc<String>, server_name: Rc<String>, server_port: u16, item_type: ItemType) -> Request {
         let server_details = Rc::new(
-            format!("{}:{}", server_name, server_port.to_string())
+            format!("{}:{}", server_name, server_port)
         );
         
         Request {
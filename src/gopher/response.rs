@@ -1,8 +1,11 @@
 use std::{
+    fmt,
     str,
     rc::Rc
 };
 
+use serde::{Deserialize, Serialize};
+
 use crate::{CRLF, TAB};
 
 /// Represents an item type offered by a Gopher server.
@@ -11,13 +14,16 @@ use crate::{CRLF, TAB};
 /// * `Dir`: 1  Item is a directory
 /// * `Err`: 3  Item is a error
 /// * `Bin`: 9  Item is a binary file
-/// * `Unknown`: Item type invalid or unsupported
+/// * `Info`: i  Item is an informational line of text
+/// * `Unknown(c)`: Item type `c` is invalid or unsupported
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ItemType {
     Txt,
     Dir,
     Err,
     Bin,
-    Unknown,
+    Info,
+    Unknown(char),
 }
 
 /// Represents the outcome of a response from a Gopher server.
@@ -26,16 +32,19 @@ pub enum ItemType {
 /// * `Timeout`: The transaction failed because the read timed out
 /// * `FileTooLong`: The transaction failed becasue the file was too long
 /// * `ConnectionFailed`: The transaction failed because the connection failed
+/// * `ConnectionReset`: The transaction failed because the server reset the connection
 /// * `MissingEndLine`: The transaction failed because the response was missing 
-/// the last line. This is only triggered for text and directory item types.
+///   the last line. This is only triggered for text and directory item types.
 /// * `MalformedResponseLine`: The transaction failed because a response line was
-/// malformed.
+///   malformed.
 /// 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ResponseOutcome {
     Complete,
     Timeout,
     FileTooLong,
     ConnectionFailed,
+    ConnectionReset,
     MissingEndLine,
     MalformedResponseLine,
 }
@@ -44,6 +53,7 @@ pub enum ResponseOutcome {
 /// 
 /// * `buffer`: Raw bytes received from the server, with the last line .\r\n removed
 /// * `response_outcome`: Specifies the result of the transaction
+#[derive(Clone, Debug)]
 pub struct Response {
     pub buffer: Vec<u8>,
     pub response_outcome: ResponseOutcome,
@@ -52,39 +62,70 @@ pub struct Response {
 /// Represents a response line from a Gopher server.
 /// 
 /// * `item_type`: First character of the human-readable display string.
+/// * `display_string`: Human-readable display string, without the item type
 /// * `selector`: String being used to request the item
 /// * `server_name`: Host name or IP address of the server providing the item
 /// * `server_port`: The port number of the server providing the item
-/// 
-/// The display string of the response line is ignored.
+/// * `gopher_plus`: Any fields after the port number (e.g. `+` for Gopher+ items)
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ResponseLine{
-    pub item_type:   ItemType,
-    pub selector:    Rc<String>, 
-    pub server_name: Rc<String>,
-    pub server_port: u16,
+    pub item_type:      ItemType,
+    pub display_string: Rc<String>,
+    pub selector:       Rc<String>, 
+    pub server_name:    Rc<String>,
+    pub server_port:    u16,
+    pub gopher_plus:    Option<Rc<String>>,
+}
+
+impl fmt::Display for ItemType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ItemType::Txt        => write!(f, "TXT"),
+            ItemType::Dir        => write!(f, "DIR"),
+            ItemType::Err        => write!(f, "ERR"),
+            ItemType::Bin        => write!(f, "BIN"),
+            ItemType::Info       => write!(f, "INFO"),
+            ItemType::Unknown(_) => write!(f, "UNKNOWN"),
+        }
+    }
 }
 
-impl ToString for ItemType {
-    fn to_string(&self) -> String {
+impl ItemType {
+    /// Returns the `ItemType` identified by the first character of a display string
+    pub fn from_char(c: char) -> ItemType {
+        match c {
+            '0' => ItemType::Txt,
+            '1' => ItemType::Dir,
+            '3' => ItemType::Err,
+            '9' => ItemType::Bin,
+            'i' => ItemType::Info,
+            _   => ItemType::Unknown(c),
+        }
+    }
+
+    /// Returns the character used to identify the `ItemType` in a response line
+    pub fn to_char(self) -> char {
         match self {
-            ItemType::Txt     => String::from("TXT"),
-            ItemType::Dir     => String::from("DIR"),
-            ItemType::Err     => String::from("ERR"),
-            ItemType::Bin     => String::from("BIN"),
-            ItemType::Unknown => String::from("UNKNOWN"),
+            ItemType::Txt        => '0',
+            ItemType::Dir        => '1',
+            ItemType::Err        => '3',
+            ItemType::Bin        => '9',
+            ItemType::Info       => 'i',
+            ItemType::Unknown(c) => c,
         }
     }
 }
 
-impl ToString for ResponseOutcome {
-    fn to_string(&self) -> String {
+impl fmt::Display for ResponseOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ResponseOutcome::Complete              => String::from("Completed sucessfully"),
-            ResponseOutcome::Timeout               => String::from("Connection timed out"),
-            ResponseOutcome::FileTooLong           => String::from("File too long"),
-            ResponseOutcome::ConnectionFailed      => String::from("Failed to connect"),
-            ResponseOutcome::MissingEndLine        => String::from("Missing end-line"),
-            ResponseOutcome::MalformedResponseLine => String::from("Malformed response line"),
+            ResponseOutcome::Complete              => write!(f, "Completed sucessfully"),
+            ResponseOutcome::Timeout               => write!(f, "Connection timed out"),
+            ResponseOutcome::FileTooLong           => write!(f, "File too long"),
+            ResponseOutcome::ConnectionFailed      => write!(f, "Failed to connect"),
+            ResponseOutcome::ConnectionReset       => write!(f, "Connection reset"),
+            ResponseOutcome::MissingEndLine        => write!(f, "Missing end-line"),
+            ResponseOutcome::MalformedResponseLine => write!(f, "Malformed response line"),
         }
     }
 }
@@ -109,70 +150,209 @@ impl Response {
 
     /// Splits the Gopher response into multiple response lines. Gopher response lines 
     /// are seperated by CLRF.
-    pub fn to_response_lines<'a>(&'a self) -> Vec<Result<ResponseLine, ResponseLineError>> {
+    /// 
+    /// Every line and every field is copied into its own `String`. Prefer 
+    /// `Response::menu_lines` unless owned response lines are required.
+    pub fn to_response_lines(&self) -> Vec<Result<ResponseLine, ResponseLineError>> {
         // Convert byte stream into a string (i.e. UTF-8 sequence)
         let buffer = str::from_utf8(&self.buffer).expect("Ivalid UTF-8 sequence"); 
         buffer.split(CRLF).map(|line| ResponseLine::new(line.to_string())).collect()
     }
+
+    /// Splits the Gopher response into borrowed response lines. Gopher response 
+    /// lines are seperated by CRLF.
+    /// 
+    /// No allocations are made for valid lines: each `MenuLine` is a view into 
+    /// `buffer`. Lines which are not valid UTF-8 are reported as 
+    /// `ResponseLineError::InvalidUtf8` instead of aborting the whole response.
+    pub fn menu_lines(&self) -> MenuLines<'_> {
+        MenuLines::new(&self.buffer)
+    }
 }
 
-impl<'a> ResponseLine {
-    /// Constructs a new `ResponseLine` instance from a response line.
+impl<'a> MenuLines<'a> {
+    /// Constructs a new `MenuLines` iterator over the lines of a received menu
+    pub fn new(buffer: &'a [u8]) -> MenuLines<'a> {
+        MenuLines { 
+            remaining: Some(buffer) 
+        }
+    }
+}
+
+/// Iterator over the borrowed response lines of a `Response`. 
+/// 
+/// Created by `Response::menu_lines` or `MenuLines::new`.
+pub struct MenuLines<'a> {
+    remaining: Option<&'a [u8]>,
+}
+
+impl<'a> Iterator for MenuLines<'a> {
+    type Item = Result<MenuLine<'a>, ResponseLineError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining = self.remaining?;
+        
+        // Split on the next CRLF. The final piece is yielded even if it is empty,
+        // matching the behaviour of `str::split`.
+        let crlf = remaining.iter()
+            .enumerate()
+            .position(|(i, &b)| b == b'\n' && i > 0 && remaining[i - 1] == b'\r');
+        let line = match crlf {
+            Some(i) => {
+                self.remaining = Some(&remaining[i + 1..]);
+                &remaining[..i - 1]
+            },
+            None => {
+                self.remaining = None;
+                remaining
+            }
+        };
+
+        match str::from_utf8(line) {
+            Ok(line) => Some(MenuLine::parse(line)),
+            Err(_) => Some(Err(ResponseLineError::InvalidUtf8(
+                String::from_utf8_lossy(line).into_owned()
+            ))),
+        }
+    }
+}
+
+/// Borrowed view of a response line from a Gopher server.
+/// 
+/// * `item_type`: First character of the human-readable display string.
+/// * `display_string`: Human-readable display string, without the item type
+/// * `selector`: String being used to request the item
+/// * `server_name`: Host name or IP address of the server providing the item
+/// * `server_port`: The port number of the server providing the item
+/// * `gopher_plus`: Any fields after the port number (e.g. `+` for Gopher+ items)
+/// 
+/// Use `MenuLine::into_owned` to keep the line beyond the lifetime of the 
+/// `Response` it was parsed from.
+#[derive(Clone, Copy, Debug)]
+pub struct MenuLine<'a> {
+    pub item_type:      ItemType,
+    pub display_string: &'a str,
+    pub selector:       &'a str,
+    pub server_name:    &'a str,
+    pub server_port:    u16,
+    pub gopher_plus:    Option<&'a str>,
+}
+
+impl<'a> MenuLine<'a> {
+    /// Parses a response line without copying any of its fields.
     /// 
     /// # Arguments
     /// 
-    /// * `line`: Response line received from a Gopher server
+    /// * `line`: Response line received from a Gopher server, without the CRLF
     /// 
     /// # Returns
     /// 
-    /// A `ResponseLine` if the response line is valid. Otherwise, returns the 
-    /// appropriate `ResponseLineError`.
-    pub fn new(line: String) -> Result<ResponseLine, ResponseLineError> {
+    /// A `MenuLine` borrowing from `line` if the response line is valid. Otherwise, 
+    /// returns the appropriate `ResponseLineError`.
+    pub fn parse(line: &'a str) -> Result<MenuLine<'a>, ResponseLineError> {
         if line.is_empty() {
             return Err(ResponseLineError::Empty);
         }
 
-        let mut parts = line.splitn(4, TAB).map(String::from).collect::<Vec<_>>();
+        let mut parts = line.splitn(4, TAB);
+        let user_display_string = parts.next().unwrap_or_default();
 
-        if parts.len() != 4 {
-            return Err(ResponseLineError::InvalidParts(line));
-        }
+        let item_type = match user_display_string.chars().next() {
+            Some(i) => ItemType::from_char(i),
+            None => return Err(ResponseLineError::EmptyDisplayString(line.to_string()))
+        };
+        let display_string = &user_display_string[item_type.to_char().len_utf8()..];
 
-        let user_display_string = parts.remove(0);
-        let selector = parts.remove(0);
-        let server_name = parts.remove(0);
-        let server_port_str = parts.remove(0);
+        // Info lines are not links, so their remaining fields are often missing 
+        // or filled with placeholders. Keep whatever is there.
+        if item_type == ItemType::Info {
+            let selector = parts.next().unwrap_or_default();
+            let server_name = parts.next().unwrap_or_default();
+            let (server_port_str, gopher_plus) = split_gopher_plus(parts.next().unwrap_or_default());
+            let server_port = server_port_str.parse().unwrap_or(0);
+            return Ok(MenuLine { item_type, display_string, selector, server_name, server_port, gopher_plus })
+        }
 
-        let item_type = match user_display_string.chars().next() {
-            Some(i) => match i {
-                '0' => ItemType::Txt,
-                '1' => ItemType::Dir,
-                '3' => ItemType::Err,
-                '9' => ItemType::Bin,
-                _   => ItemType::Unknown
-            },
-            None => return Err(ResponseLineError::EmptyDisplayString(line))
+        let (selector, server_name, server_port_str) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(selector), Some(name), Some(port)) => (selector, name, port),
+            _ => return Err(ResponseLineError::InvalidParts(line.to_string())),
         };
+        let (server_port_str, gopher_plus) = split_gopher_plus(server_port_str);
+
         // Server name cannot be empty        
         if server_name.is_empty() {
-            return Err(ResponseLineError::EmptyHost(server_name, server_port_str, selector))
+            return Err(ResponseLineError::EmptyHost(
+                server_name.to_string(), server_port_str.to_string(), selector.to_string()
+            ))
         }
         // Server port must be an integer        
-        let server_port = server_port_str.parse::<u16>();
-        let server_port = match server_port {
+        let server_port = match server_port_str.parse::<u16>() {
             Ok(port) => port,
-            Err(_) => return Err(ResponseLineError::NonIntPort(server_name, server_port_str, selector)), 
+            Err(_) => return Err(ResponseLineError::NonIntPort(
+                server_name.to_string(), server_port_str.to_string(), selector.to_string()
+            )), 
         };
 
         Ok(
-            ResponseLine {
+            MenuLine {
                 item_type,
-                selector: Rc::new(selector),
-                server_name: Rc::new(server_name),
+                display_string,
+                selector,
+                server_name,
                 server_port,
+                gopher_plus,
             }
         )
     }
+
+    /// Copies the borrowed fields into a new `ResponseLine`.
+    pub fn into_owned(self) -> ResponseLine {
+        ResponseLine {
+            item_type: self.item_type,
+            display_string: Rc::new(self.display_string.to_string()),
+            selector: Rc::new(self.selector.to_string()),
+            server_name: Rc::new(self.server_name.to_string()),
+            server_port: self.server_port,
+            gopher_plus: self.gopher_plus.map(|fields| Rc::new(fields.to_string())),
+        }
+    }
+}
+
+/// Splits the last field of a response line into the port number and any 
+/// trailing Gopher+ fields.
+fn split_gopher_plus(field: &str) -> (&str, Option<&str>) {
+    match field.split_once(TAB) {
+        Some((server_port, gopher_plus)) => (server_port, Some(gopher_plus)),
+        None => (field, None),
+    }
+}
+
+impl ResponseLine {
+    /// Constructs a new `ResponseLine` instance from a response line.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `line`: Response line received from a Gopher server
+    /// 
+    /// # Returns
+    /// 
+    /// A `ResponseLine` if the response line is valid. Otherwise, returns the 
+    /// appropriate `ResponseLineError`.
+    pub fn new(line: String) -> Result<ResponseLine, ResponseLineError> {
+        MenuLine::parse(&line).map(MenuLine::into_owned)
+    }
+
+    /// Borrows the fields of the line as a `MenuLine`
+    pub fn as_menu_line(&self) -> MenuLine<'_> {
+        MenuLine {
+            item_type: self.item_type,
+            display_string: &self.display_string,
+            selector: &self.selector,
+            server_name: &self.server_name,
+            server_port: self.server_port,
+            gopher_plus: self.gopher_plus.as_ref().map(|fields| fields.as_str()),
+        }
+    }
 }
 
 /// Represents issues of a Gopher response line.
@@ -182,17 +362,20 @@ impl<'a> ResponseLine {
 /// * `EmptyDisplayString(line)`: The display string is empty
 /// * `EmptyHost(server_name, server_port, selector)`: The hostname is empty
 /// * `NonIntPort(server_name, server_port, selector)`: The port number is
-/// not an integer
-#[derive(Debug)]
+///   not an integer
+/// * `InvalidUtf8(line)`: The response line is not valid UTF-8. The line is
+///   stored with invalid sequences replaced.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ResponseLineError {
     Empty,
     InvalidParts(String),
     EmptyDisplayString(String),
     EmptyHost(String, String, String), 
-    NonIntPort(String, String, String)
+    NonIntPort(String, String, String),
+    InvalidUtf8(String),
 }
 
-impl<'a> std::error::Error for ResponseLineError {}
+impl std::error::Error for ResponseLineError {}
 
 impl std::fmt::Display for ResponseLineError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -202,6 +385,7 @@ impl std::fmt::Display for ResponseLineError {
             ResponseLineError::EmptyDisplayString(line) => write!(f, "Empty display string: {line}"),
             ResponseLineError::EmptyHost(_, _, _) => write!(f, "Missing host name"),
             ResponseLineError::NonIntPort(_, _, _) => write!(f, "Invalid port number"),
+            ResponseLineError::InvalidUtf8(line) => write!(f, "Invalid UTF-8 sequence: {line}"),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,15 @@
+pub mod archive;
+pub mod crawler;
+pub mod gopher;
+pub mod mirror;
+pub mod server;
+pub mod session;
+pub mod storage;
+pub mod transport;
+
+pub const CRLF: &str              = "\r\n";
+pub const TAB: &str               = "\t";
+pub const OUTPUT_FOLDER: &str     = "out";
+pub const MANIFEST: &str          = "manifest";
+pub const MAX_CHUNK_SIZE: usize   = 4096; 
+pub const MAX_FILENAME_LEN: usize = 255;  
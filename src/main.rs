@@ -1,27 +1,192 @@
-mod crawler;
-mod gopher;
-
 use std::{
     env, 
-    fs::{self, remove_dir_all}, 
-    io::ErrorKind, 
-    path::Path
+    fs::{self, remove_dir_all, File}, 
+    io::{self, BufWriter, ErrorKind, Write}, 
+    path::Path,
+    time::Duration
+};
+
+use gopher::{
+    archive::{self, ArchivingTransport},
+    crawler::{
+        canonical::CanonicalRules,
+        diff::CrawlDiff,
+        export::{self, ExportFormat},
+        graph::{self, GraphFormat},
+        incremental::PreviousCrawl,
+        Crawler
+    }, 
+    server::{self, scenario::Scenario}, 
+    session::{self, RecordingTransport, ReplayTransport},
+    storage::StorageKind,
+    transport::{TcpTransport, Transport},
+    OUTPUT_FOLDER
 };
 
-use crawler::Crawler;
+/// How often a crawl run with `--checkpoint` or `--resume` saves its state
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
 
-const CRLF: &str              = "\r\n";
-const TAB: &str               = "\t";
-const OUTPUT_FOLDER: &str     = "out";
-const MAX_CHUNK_SIZE: usize   = 4096; 
-const MAX_FILENAME_LEN: usize = 255;  
+const USAGE: &str = "Usage: gopher [-n <server_name>] [-p <server_port>] [-d] [-s <storage>] [-o <output_path>] [-r <session_file>] [-w <archive_file>] [--checkpoint <state_file> | --resume <state_file>] [--incremental <previous_folder>] [--json <report_file>] [--csv <items_file> | --tsv <items_file>] [--html <report_file>] [--dot <graph_file> | --graphml <graph_file>] [--top <n>] [--trap-threshold <n>] [--canonicalize <rules>]\n       \
+                     gopher archive list <archive_file>\n       \
+                     gopher archive extract <archive_file> <gopher_uri> [<output_file>]\n       \
+                     gopher diff <old_state_file> <new_state_file> [--json]\n       \
+                     gopher replay <session_file> [-d] [-s <storage>] [-o <output_path>] [--json <report_file>] [--csv <items_file> | --tsv <items_file>] [--html <report_file>] [--dot <graph_file> | --graphml <graph_file>] [--top <n>] [--trap-threshold <n>] [--canonicalize <rules>]\n       \
+                     gopher serve [<folder>] [-n <server_name>] [-p <server_port>]\n       \
+                     gopher test-server <scenario_file> [-n <server_name>] [-p <server_port>]\n\n\
+                     gopher diff compares the state files of completed crawls written with --checkpoint, not reports written with --json.";
+
+/// Where the results of a crawl (or replay) are written to
+/// 
+/// * `storage_kind`: Kind of storage downloaded items are kept in
+/// * `output_path`: Where the storage writes to. Defaults to `OUTPUT_FOLDER`, or 
+///   `OUTPUT_FOLDER.tar` for tar archives.
+/// * `remove_dirs`: Remove the output folder after the crawl, if the crawl created
+///   it. Only applies to storages which write into a folder.
+/// * `report_file`: Also write the report as JSON to this file
+/// * `items_file`: Also export every discovered item to this file, in the given format
+/// * `html_file`: Also write the report as HTML to this file
+/// * `graph_file`: Also export the link graph to this file, in the given format
+/// * `top_n`: Number of smallest and largest items of each type in the report.
+///   The crawler's default if nothing.
+/// * `trap_threshold`: Number of menus matching a pattern which are crawled before
+///   the rest are cut off as a suspected spider trap. The crawler's default if nothing.
+/// * `canonical_rules`: Which references to an item are treated as the same item.
+///   The crawler's default if nothing.
+struct Outputs {
+    storage_kind: StorageKind,
+    output_path: Option<String>,
+    remove_dirs: bool,
+    report_file: Option<String>,
+    items_file: Option<(String, ExportFormat)>,
+    html_file: Option<String>,
+    graph_file: Option<(String, GraphFormat)>,
+    top_n: Option<usize>,
+    trap_threshold: Option<usize>,
+    canonical_rules: Option<CanonicalRules>,
+}
+
+impl Default for Outputs {
+    fn default() -> Outputs {
+        Outputs {
+            storage_kind: StorageKind::Directory,
+            output_path: None,
+            remove_dirs: true,
+            report_file: None,
+            items_file: None,
+            html_file: None,
+            graph_file: None,
+            top_n: None,
+            trap_threshold: None,
+            canonical_rules: None,
+        }
+    }
+}
+
+impl Outputs {
+    /// Parses an argument shared by crawls and replays
+    /// 
+    /// # Returns
+    /// True if `arg` (and its value) was an output argument. False otherwise.
+    fn parse_arg(&mut self, arg: &str, args_iter: &mut impl Iterator<Item = String>) -> Result<bool, Box<dyn std::error::Error>> {
+        match arg {
+            // Directory delete argument
+            "-d" => {
+                self.remove_dirs = false;
+            }
+            // Storage argument
+            "-s" => {
+                let kind_str = args_iter.next().ok_or("Missing storage after -s")?;
+                self.storage_kind = kind_str.parse()?;
+            }
+            // Output path argument
+            "-o" => {
+                self.output_path = Some(
+                    args_iter.next().ok_or("Missing output path after -o")?
+                );
+            }
+            // Machine-readable report argument
+            "--json" => {
+                self.report_file = Some(
+                    args_iter.next().ok_or("Missing report file after --json")?
+                );
+            }
+            // Item export arguments
+            "--csv" => {
+                let path = args_iter.next().ok_or("Missing items file after --csv")?;
+                self.items_file = Some((path, ExportFormat::Csv));
+            }
+            "--tsv" => {
+                let path = args_iter.next().ok_or("Missing items file after --tsv")?;
+                self.items_file = Some((path, ExportFormat::Tsv));
+            }
+            // HTML report argument
+            "--html" => {
+                self.html_file = Some(
+                    args_iter.next().ok_or("Missing report file after --html")?
+                );
+            }
+            // Link graph arguments
+            "--dot" => {
+                let path = args_iter.next().ok_or("Missing graph file after --dot")?;
+                self.graph_file = Some((path, GraphFormat::Dot));
+            }
+            "--graphml" => {
+                let path = args_iter.next().ok_or("Missing graph file after --graphml")?;
+                self.graph_file = Some((path, GraphFormat::GraphMl));
+            }
+            // Top-N argument
+            "--top" => {
+                let top_str = args_iter.next().ok_or("Missing number of items after --top")?;
+                self.top_n = Some(top_str.parse().map_err(|_| "Number of items after --top must be an integer")?);
+            }
+            // Spider trap threshold argument
+            "--trap-threshold" => {
+                let threshold_str = args_iter.next().ok_or("Missing number of menus after --trap-threshold")?;
+                self.trap_threshold = Some(threshold_str.parse().map_err(|_| "Number of menus after --trap-threshold must be an integer")?);
+            }
+            // Canonicalization argument
+            "--canonicalize" => {
+                let rules_str = args_iter.next().ok_or("Missing rules after --canonicalize")?;
+                self.canonical_rules = Some(rules_str.parse()?);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args_iter = env::args().skip(1).peekable();
+
+    if args_iter.next_if(|arg| arg == "serve").is_some() {
+        return serve(args_iter);
+    }
+    if args_iter.next_if(|arg| arg == "test-server").is_some() {
+        return test_server(args_iter);
+    }
+    if args_iter.next_if(|arg| arg == "replay").is_some() {
+        return replay(args_iter);
+    }
+    if args_iter.next_if(|arg| arg == "archive").is_some() {
+        return archive(args_iter);
+    }
+    if args_iter.next_if(|arg| arg == "diff").is_some() {
+        return diff(args_iter);
+    }
+    crawl(args_iter)
+}
+
+/// Crawls a Gopher server and reports the results
+fn crawl(mut args_iter: impl Iterator<Item = String>) -> Result<(), Box<dyn std::error::Error>> {
     let mut server_name = None;
     let mut server_port = None;
-    let mut remove_dirs = true;
+    let mut session_file = None;
+    let mut archive_file = None;
+    let mut state_file = None;
+    let mut resume = false;
+    let mut previous_folder = None;
+    let mut outputs = Outputs::default();
 
-    let mut args_iter = env::args().skip(1);
     while let Some(arg) = args_iter.next() {
         match arg.as_str() {
             // Server name argument
@@ -41,34 +206,309 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     }
                 };
             }
-            // Directory delete argument
-            "-d" => {
-                remove_dirs = false;
+            // Session recording argument
+            "-r" => {
+                session_file = Some(
+                    args_iter.next().ok_or("Missing session file after -r")?
+                );
+            }
+            // Checkpoint argument
+            "--checkpoint" => {
+                state_file = Some(
+                    args_iter.next().ok_or("Missing state file after --checkpoint")?
+                );
+            }
+            // Resume argument. The resumed crawl keeps checkpointing to the same file.
+            "--resume" => {
+                state_file = Some(
+                    args_iter.next().ok_or("Missing state file after --resume")?
+                );
+                resume = true;
             }
+            // Incremental crawl argument
+            "--incremental" => {
+                previous_folder = Some(
+                    args_iter.next().ok_or("Missing previous folder after --incremental")?
+                );
+            }
+            // WARC archive argument
+            "-w" => {
+                archive_file = Some(
+                    args_iter.next().ok_or("Missing archive file after -w")?
+                );
+            }
+            // Storage and report arguments
+            _ if outputs.parse_arg(&arg, &mut args_iter)? => (),
             // Invalid argument
             _ => {
-                eprintln!("Usage: gopher [-n <server_name>] [-p <server_port>] [-d]");
+                eprintln!("{USAGE}");
                 return Ok(())
             }
         }
     }
 
-    // Create output directory to store files
-    if let Err(error) = fs::create_dir(Path::new(&OUTPUT_FOLDER)) {
-        if error.kind() != ErrorKind::AlreadyExists {
-            panic!("Unable to create output folder: {error}");
+    // A resumed crawl continues on the server it was started on with the settings it
+    // was started with, keeps the items stored before and must not replace the
+    // session or archive of the interrupted crawl
+    if resume {
+        if server_name.is_some() || server_port.is_some() {
+            return Err("-n and -p can not be used with --resume".into());
+        }
+        if outputs.top_n.is_some() || outputs.trap_threshold.is_some() || outputs.canonical_rules.is_some() {
+            return Err("--top, --trap-threshold and --canonicalize are kept from the interrupted crawl, and can not be used with --resume".into());
+        }
+        if !outputs.storage_kind.is_resumable() {
+            return Err("-s tar and -s memory can not be used with --resume".into());
+        }
+        for file in session_file.iter().chain(&archive_file) {
+            if Path::new(file).exists() {
+                return Err(format!("{file} already exists, record a resumed crawl to a new file").into());
+            }
+        }
+    }
+
+    let mut transport: Box<dyn Transport> = Box::new(TcpTransport);
+    if let Some(session_file) = session_file {
+        transport = Box::new(RecordingTransport::create(transport, Path::new(&session_file))?);
+    }
+    if let Some(archive_file) = archive_file {
+        transport = Box::new(ArchivingTransport::create(transport, Path::new(&archive_file))?);
+    }
+
+    let crawler = match (&state_file, resume) {
+        (Some(state_file), true) => Crawler::resume(Path::new(state_file))?,
+        _ => Crawler::new(server_name, server_port),
+    };
+    let crawler = match state_file {
+        Some(state_file) => crawler.with_checkpoint(Path::new(&state_file), CHECKPOINT_INTERVAL),
+        None => crawler,
+    };
+    let crawler = match previous_folder {
+        Some(previous_folder) => crawler.with_previous(PreviousCrawl::load(Path::new(&previous_folder))?),
+        None => crawler,
+    };
+    run_crawl(crawler.with_transport(transport), outputs)
+}
+
+/// Crawls a recorded session without any network access and reports the results
+fn replay(mut args_iter: impl Iterator<Item = String>) -> Result<(), Box<dyn std::error::Error>> {
+    let session_file = args_iter.next().ok_or("Missing session file")?;
+    let mut outputs = Outputs::default();
+
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            // Storage and report arguments
+            _ if outputs.parse_arg(&arg, &mut args_iter)? => (),
+            // Invalid argument
+            _ => {
+                eprintln!("{USAGE}");
+                return Ok(())
+            }
         }
     }
 
-    // Crawl the Gopher server and report the results
-    let mut crawler = Crawler::new(server_name, server_port);
-    crawler.start_crawl()?;
+    let transactions = session::read_session(Path::new(&session_file))?;
+    let (server_name, server_port) = session::root_server(&transactions)
+        .ok_or("Session does not contain any requests")?;
+
+    let crawler = Crawler::new(Some(server_name), Some(server_port))
+        .with_transport(ReplayTransport::from_transactions(transactions));
+    run_crawl(crawler, outputs)
+}
+
+/// Lists the records of a WARC archive, or extracts the response to a single request
+fn archive(mut args_iter: impl Iterator<Item = String>) -> Result<(), Box<dyn std::error::Error>> {
+    let command = args_iter.next().ok_or("Missing archive command")?;
+    let archive_file = args_iter.next().ok_or("Missing archive file")?;
+    let records = archive::read_archive(Path::new(&archive_file))?;
+
+    match command.as_str() {
+        "list" => {
+            for record in &records {
+                println!("{}\t{}\t{}\t{}\t{}",
+                    record.header("WARC-Date").unwrap_or("-"),
+                    record.header("WARC-Type").unwrap_or("-"),
+                    record.header("WARC-Gopher-Outcome").unwrap_or("-"),
+                    record.block.len(),
+                    record.header("WARC-Target-URI").unwrap_or("-"),
+                );
+            }
+        }
+        "extract" => {
+            let target_uri = args_iter.next().ok_or("Missing gopher URI")?;
+            let record = archive::find_response(&records, &target_uri)
+                .ok_or_else(|| format!("{target_uri} is not in the archive"))?;
+
+            match args_iter.next() {
+                Some(output_file) => fs::write(output_file, &record.block)?,
+                None => io::stdout().write_all(&record.block)?,
+            }
+        }
+        // Invalid command
+        _ => eprintln!("{USAGE}"),
+    }
+    Ok(())
+}
+
+/// Reports what changed between two completed crawls saved with `--checkpoint`
+fn diff(mut args_iter: impl Iterator<Item = String>) -> Result<(), Box<dyn std::error::Error>> {
+    let old_state_file = args_iter.next().ok_or("Missing old state file")?;
+    let new_state_file = args_iter.next().ok_or("Missing new state file")?;
+    let mut json = false;
+
+    for arg in args_iter {
+        match arg.as_str() {
+            // Machine-readable output argument
+            "--json" => {
+                json = true;
+            }
+            // Invalid argument
+            _ => {
+                eprintln!("{USAGE}");
+                return Ok(())
+            }
+        }
+    }
+
+    let mut crawls = Vec::new();
+    for state_file in [old_state_file, new_state_file] {
+        let crawler = Crawler::resume(Path::new(&state_file))
+            .map_err(|error| format!("{state_file} is not a state file written with --checkpoint: {error}"))?;
+        if !crawler.is_complete() {
+            return Err(format!("{state_file} is not a completed crawl").into());
+        }
+        crawls.push(crawler);
+    }
+
+    let diff = CrawlDiff::between(&crawls[0], &crawls[1]);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        println!("{}", diff.render());
+    }
+    Ok(())
+}
+
+/// Runs a crawl into the chosen storage, reports the results and cleans up
+/// 
+/// # Arguments
+/// * `crawler`: Crawler to run
+/// * `outputs`: Where the results of the crawl are written to
+fn run_crawl(crawler: Crawler, outputs: Outputs) -> Result<(), Box<dyn std::error::Error>> {
+    let storage_kind = outputs.storage_kind;
+    let output_path = outputs.output_path.unwrap_or_else(|| match storage_kind {
+        StorageKind::Tar => format!("{OUTPUT_FOLDER}.tar"),
+        _ => String::from(OUTPUT_FOLDER),
+    });
+    let output_path = Path::new(&output_path);
+    // Only a folder created by the crawl is removed afterwards
+    let created_output = !output_path.exists();
+    let mut crawler = crawler.with_storage(storage_kind.open(output_path)?);
+    if let Some(top_n) = outputs.top_n {
+        crawler = crawler.with_top_n(top_n);
+    }
+    if let Some(trap_threshold) = outputs.trap_threshold {
+        crawler = crawler.with_trap_threshold(trap_threshold);
+    }
+    if let Some(canonical_rules) = outputs.canonical_rules {
+        crawler = crawler.with_canonicalization(canonical_rules);
+    }
+
+    // Crawl the Gopher server and report the results. A failed crawl still
+    // finishes the storage, so e.g. a tar archive of the items stored so far
+    // is complete.
+    if let Err(error) = crawler.start_crawl() {
+        let _ = crawler.save_snapshot();
+        return Err(error.into());
+    }
     crawler.report();
+    if let Some(report_file) = outputs.report_file {
+        fs::write(report_file, serde_json::to_string_pretty(&crawler.crawl_report())?)?;
+    }
+    if let Some((items_file, format)) = outputs.items_file {
+        let mut writer = BufWriter::new(File::create(items_file)?);
+        export::write_items(&mut writer, crawler.items(), format)?;
+    }
+    if let Some(html_file) = outputs.html_file {
+        fs::write(html_file, crawler.render_html())?;
+    }
+    if let Some((graph_file, format)) = outputs.graph_file {
+        let mut writer = BufWriter::new(File::create(graph_file)?);
+        graph::write_graph(&mut writer, crawler.items(), format)?;
+    }
 
-    // Remove output directory and all of its contents
-    if remove_dirs {
-        remove_dir_all(OUTPUT_FOLDER)?;
+    // Remove output directory and all of its contents if the crawl created it.
+    // Otherwise, keep a snapshot which can be served with `gopher serve`.
+    if storage_kind.is_folder() && outputs.remove_dirs && created_output {
+        if let Err(error) = remove_dir_all(output_path) {
+            if error.kind() != ErrorKind::NotFound {
+                return Err(error.into());
+            }
+        }
+    } else {
+        crawler.save_snapshot()?;
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Serves the snapshot in a folder (by default the output directory) as a Gopher server
+fn serve(args_iter: impl Iterator<Item = String>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut args_iter = args_iter.peekable();
+    let folder = args_iter.next_if(|arg| !arg.starts_with('-'))
+        .unwrap_or_else(|| String::from(OUTPUT_FOLDER));
+    let Some((server_name, server_port)) = server_args(args_iter)? else {
+        return Ok(())
+    };
+
+    server::serve(Path::new(&folder), &server_name, server_port)?;
+    Ok(())
+}
+
+/// Serves a misbehaving test server described by a scenario file
+fn test_server(mut args_iter: impl Iterator<Item = String>) -> Result<(), Box<dyn std::error::Error>> {
+    let scenario_file = args_iter.next().ok_or("Missing scenario file")?;
+    let Some((server_name, server_port)) = server_args(args_iter)? else {
+        return Ok(())
+    };
+
+    Scenario::load(Path::new(&scenario_file))?.serve(&server_name, server_port)?;
+    Ok(())
+}
+
+/// Parses the arguments shared by the server modes
+/// 
+/// # Returns
+/// The (server name, server port) pair to serve on. Nothing if the arguments
+/// are invalid.
+fn server_args(mut args_iter: impl Iterator<Item = String>) -> Result<Option<(String, u16)>, Box<dyn std::error::Error>> {
+    let mut server_name = String::from("localhost");
+    let mut server_port = 7070;
+
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            // Advertised server name argument
+            "-n" => {
+                server_name = args_iter.next().ok_or("Missing server name after -n")?;
+            }
+            // Server port argument
+            "-p" => {
+                let port_str = args_iter.next().ok_or("Missing server port after -p")?;
+                server_port = match port_str.parse() {
+                    Ok(port) => port,
+                    Err(_) => {
+                        eprintln!("Server port must be an integer");
+                        return Ok(None)
+                    }
+                };
+            }
+            // Invalid argument
+            _ => {
+                eprintln!("{USAGE}");
+                return Ok(None)
+            }
+        }
+    }
+
+    Ok(Some((server_name, server_port)))
+}
@@ -0,0 +1,362 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf}
+};
+
+use crate::gopher::response::ItemType;
+
+use crate::storage::{write_manifest, ManifestEntry, Storage, StorageState};
+use crate::MAX_FILENAME_LEN;
+
+/// File name of a menu within the directory of its selector
+pub const MENU_FILE: &str = ".gophermap";
+
+/// Directory holding selectors which do not start with `/`
+const BARE_DIR: &str = ".bare";
+
+/// Directory holding items whose natural path is taken by another item, e.g. a
+/// text file `/a` when `/a/b` was stored first
+const COLLISIONS_DIR: &str = ".collisions";
+
+/// Represents a mirror of one or more Gopher servers laid out as
+/// `<host>/<port>/<selector path>` within a folder.
+///
+/// Each `/` seperated segment of a selector becomes a directory, and the last
+/// segment names the file. Menus are stored as `.gophermap` within the directory
+/// of their selector. Segments are escaped with `escape_segment`, so every stored
+/// path can be turned back into its selector with `parse_mirror_path`.
+///
+/// Selectors are UTF-8 strings. Menu lines which are not valid UTF-8 are recorded
+/// as invalid references instead of being crawled, so items with such selectors
+/// are never stored.
+///
+/// * `folder`: Folder the mirror is written to
+/// * `entries`: Every stored item in order (item type, server details, selector,
+///   path relative to `folder`) quadruples
+/// * `files`: Path of every stored file, relative to `folder`
+/// * `dirs`: Path of every directory implied by the stored files, relative to
+///   `folder`
+pub struct Mirror {
+    folder: PathBuf,
+    entries: Vec<ManifestEntry>,
+    files: HashSet<String>,
+    dirs: HashSet<String>,
+}
+
+impl Mirror {
+    /// Constructs a new, empty `Mirror` writing to `folder`
+    pub fn new(folder: &Path) -> Mirror {
+        Mirror {
+            folder: folder.to_path_buf(),
+            entries: Vec::new(),
+            files: HashSet::new(),
+            dirs: HashSet::new(),
+        }
+    }
+
+    /// Returns every stored item in order (item type, server details, selector,
+    /// relative path) quadruples
+    pub fn entries(&self) -> &[ManifestEntry] {
+        &self.entries
+    }
+
+    /// Returns true if `relative` cannot be created because of an item already
+    /// stored. Files left in `folder` by earlier crawls are not taken into account,
+    /// so the layout only depends on the items stored.
+    fn is_blocked(&self, relative: &str) -> bool {
+        self.dirs.contains(relative) || ancestors(relative).any(|ancestor| self.files.contains(ancestor))
+    }
+}
+
+impl Storage for Mirror {
+    /// Stores the contents of an item at its mirror path, creating any missing
+    /// directories. If a stored file already occupies a directory of the path (or
+    /// a directory of stored files occupies the path itself), the item is stored
+    /// under `.collisions` instead.
+    ///
+    /// # Arguments
+    /// * `item_type`: The type of the item
+    /// * `server_details`: hostname:port of the server providing the item
+    /// * `selector`: String used to request the item
+    /// * `contents`: Bytes to store
+    ///
+    /// # Returns
+    /// Nothing if sucessfull. Otherwise, returns an IO error.
+    fn store(&mut self, item_type: ItemType, server_details: &str, selector: &str, contents: &[u8]) -> io::Result<()> {
+        let mut relative = mirror_path(item_type, server_details, selector);
+        if self.is_blocked(&relative) {
+            relative = collision_path(item_type, server_details, selector);
+        }
+
+        let path = self.folder.join(&relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        File::create(&path)?.write_all(contents)?;
+        self.dirs.extend(ancestors(&relative).map(String::from));
+        self.files.insert(relative.clone());
+
+        self.entries.push((item_type, server_details.to_string(), selector.to_string(), relative));
+        Ok(())
+    }
+
+    /// Writes the manifest of the mirror to `folder`
+    fn finish(&mut self) -> io::Result<()> {
+        write_manifest(&self.folder, &self.entries)
+    }
+
+    fn state(&self) -> Option<StorageState> {
+        Some(StorageState { entries: self.entries.clone(), objects: Vec::new() })
+    }
+
+    /// Takes the stored items of a checkpoint, and the files and directories
+    /// they occupy
+    fn restore(&mut self, state: StorageState) {
+        for (.., relative) in &state.entries {
+            self.dirs.extend(ancestors(relative).map(String::from));
+            self.files.insert(relative.clone());
+        }
+        self.entries = state.entries;
+    }
+}
+
+/// Returns the path (relative to the mirror folder, `/` seperated) an item is
+/// stored at.
+///
+/// * The empty selector of a menu is stored at `<host>/<port>/.gophermap`
+/// * Selectors starting with `/` are stored at `<host>/<port>/<segments>`
+/// * Any other selector is stored at `<host>/<port>/.bare/<segments>`
+///
+/// # Arguments
+/// * `item_type`: The type of the item. Menus get their own directory.
+/// * `server_details`: hostname:port of the server providing the item
+/// * `selector`: String used to request the item
+pub fn mirror_path(item_type: ItemType, server_details: &str, selector: &str) -> String {
+    let mut components = server_components(server_details);
+
+    if !(item_type == ItemType::Dir && selector.is_empty()) {
+        match selector.strip_prefix('/') {
+            Some(path) => components.extend(path.split('/').flat_map(escape_segment)),
+            None => {
+                components.push(String::from(BARE_DIR));
+                components.extend(selector.split('/').flat_map(escape_segment));
+            },
+        }
+    }
+    if item_type == ItemType::Dir {
+        components.push(String::from(MENU_FILE));
+    }
+    components.join("/")
+}
+
+/// Returns the fallback path of an item whose mirror path is taken. The whole
+/// selector is escaped as a single segment under `<host>/<port>/.collisions`.
+pub(crate) fn collision_path(item_type: ItemType, server_details: &str, selector: &str) -> String {
+    let mut components = server_components(server_details);
+    components.push(String::from(COLLISIONS_DIR));
+    components.extend(escape_segment(selector));
+    if item_type == ItemType::Dir {
+        components.push(String::from(MENU_FILE));
+    }
+    components.join("/")
+}
+
+/// Returns the `<host>/<port>` components of a mirror path
+fn server_components(server_details: &str) -> Vec<String> {
+    let (server_name, server_port) = server_details.rsplit_once(':').unwrap_or((server_details, ""));
+    let mut components = escape_segment(server_name);
+    components.extend(escape_segment(server_port));
+    components
+}
+
+/// Turns a path returned by `mirror_path` or `collision_path` back into the item
+/// it stores.
+///
+/// # Returns
+/// The (server details, selector, is menu) triple if the path is a valid mirror
+/// path. Nothing otherwise.
+pub fn parse_mirror_path(path: &str) -> Option<(String, String, bool)> {
+    let mut components = path.split('/').collect::<Vec<_>>();
+    let is_menu = components.last() == Some(&MENU_FILE);
+    if is_menu {
+        components.pop();
+    }
+
+    let mut segments = unescape_components(&components)?.into_iter();
+    let (server_name, _) = segments.next()?;
+    let (server_port, _) = segments.next()?;
+    let server_details = format!("{}:{}", server_name, server_port);
+
+    let rest = segments.collect::<Vec<_>>();
+    let names = rest.iter().map(|(segment, _)| segment.as_str()).collect::<Vec<_>>();
+    let selector = match rest.first() {
+        None if is_menu => String::new(),
+        None => return None,
+        Some((segment, true)) if segment == BARE_DIR => names[1..].join("/"),
+        Some((segment, true)) if segment == COLLISIONS_DIR && rest.len() > 1 => names[1..].join("/"),
+        Some((_, true)) => return None,
+        Some(_) => format!("/{}", names.join("/")),
+    };
+    Some((server_details, selector, is_menu))
+}
+
+/// Escapes a selector segment so it can be used as a file name on any common
+/// file system.
+///
+/// Control characters, `%`, `/`, `\` and the characters reserved by Windows are
+/// percent-encoded, as is a `.` starting a file name (so `.` and `..` never
+/// escape the mirror, and names starting with `.` are free for the mirror's own
+/// use). An empty segment becomes `%`. Segments longer than `MAX_FILENAME_LEN`
+/// bytes are split into several nested names, each but the last ending in a
+/// bare `%`.
+///
+/// # Returns
+/// The file names the segment is stored as, outermost first.
+pub fn escape_segment(segment: &str) -> Vec<String> {
+    if segment.is_empty() {
+        return vec![String::from("%")];
+    }
+
+    let mut names = Vec::new();
+    let mut name = String::new();
+    for c in segment.chars() {
+        let escaped = if (name.is_empty() && c == '.') || (c.is_ascii() && needs_escape(c as u8)) {
+            format!("%{:02X}", c as u8)
+        } else {
+            c.to_string()
+        };
+
+        // Leave room for the continuation marker
+        if name.len() + escaped.len() > MAX_FILENAME_LEN - 1 {
+            name.push('%');
+            names.push(std::mem::take(&mut name));
+            // A continuation may start with '.' too
+            let escaped = if c == '.' { String::from("%2E") } else { escaped };
+            name.push_str(&escaped);
+        } else {
+            name.push_str(&escaped);
+        }
+    }
+    names.push(name);
+    names
+}
+
+/// Reverses `escape_segment` over the components of a path, joining continued
+/// names back together.
+///
+/// # Returns
+/// The (unescaped segment, reserved) pairs if every component is validly escaped,
+/// where reserved names are the mirror's own (starting with an unescaped `.`).
+/// Nothing otherwise.
+fn unescape_components(components: &[&str]) -> Option<Vec<(String, bool)>> {
+    let mut segments = Vec::new();
+    let mut segment = Vec::new();
+
+    for component in components {
+        if component.starts_with('.') && segment.is_empty() {
+            segments.push((component.to_string(), true));
+            continue
+        }
+        if *component == "%" {
+            segments.push((String::new(), false));
+            continue
+        }
+
+        let bytes = component.as_bytes();
+        let mut continued = false;
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] != b'%' {
+                segment.push(bytes[i]);
+                i += 1;
+            } else if i + 1 == bytes.len() {
+                continued = true;
+                i += 1;
+            } else {
+                let hex = component.get(i + 1..i + 3)?;
+                segment.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+        }
+        if !continued {
+            segments.push((String::from_utf8(std::mem::take(&mut segment)).ok()?, false));
+        }
+    }
+    // A name cannot end with a continuation
+    segment.is_empty().then_some(segments)
+}
+
+/// Returns every directory a `/` seperated path is in, innermost first
+pub(crate) fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+    path.match_indices('/').rev().map(move |(i, _)| &path[..i])
+}
+
+/// Returns true if an ASCII byte cannot appear in a file name unescaped
+fn needs_escape(byte: u8) -> bool {
+    byte.is_ascii_control() || b"%/\\<>:\"|?*".contains(&byte)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_follow_the_selector() {
+        assert_eq!(mirror_path(ItemType::Dir, "gopher.test:70", ""), "gopher.test/70/.gophermap");
+        assert_eq!(mirror_path(ItemType::Dir, "gopher.test:70", "/"), "gopher.test/70/%/.gophermap");
+        assert_eq!(mirror_path(ItemType::Dir, "gopher.test:70", "/docs"), "gopher.test/70/docs/.gophermap");
+        assert_eq!(mirror_path(ItemType::Txt, "gopher.test:70", "/docs/a.txt"), "gopher.test/70/docs/a.txt");
+        assert_eq!(mirror_path(ItemType::Txt, "gopher.test:70", "docs/a.txt"), "gopher.test/70/.bare/docs/a.txt");
+        assert_eq!(mirror_path(ItemType::Bin, "gopher.test:70", ""), "gopher.test/70/.bare/%");
+    }
+
+    #[test]
+    fn similar_selectors_do_not_collide() {
+        let selectors = ["/a/b", "/a-b", "a/b", "/a//b", "/a/b/", "/a/%2F", "/a/.b", "/a/%2Eb", "", "/.bare/a"];
+        let paths = selectors.iter()
+            .map(|selector| mirror_path(ItemType::Txt, "gopher.test:70", selector))
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(paths.len(), selectors.len());
+    }
+
+    #[test]
+    fn unsafe_segments_are_escaped_reversibly() {
+        let long = "x".repeat(MAX_FILENAME_LEN * 2 + 3);
+        let selectors = [
+            String::from("/../../etc/passwd"),
+            String::from("/./a"),
+            String::from("/nul\0byte"),
+            String::from("/c:\\windows\\a?b*"),
+            String::from("/100%"),
+            format!("/{long}/.{long}"),
+            String::from("/ünïcödé/文件"),
+            String::from("/.bare/.collisions/.gophermap"),
+        ];
+        for selector in selectors {
+            for item_type in [ItemType::Txt, ItemType::Dir] {
+                let path = mirror_path(item_type, "gopher.test:70", &selector);
+                assert!(path.split('/').all(|name| name.len() <= MAX_FILENAME_LEN && name != "." && name != ".."));
+                assert!(!path.contains('\0'));
+                assert_eq!(
+                    parse_mirror_path(&path),
+                    Some((String::from("gopher.test:70"), selector.clone(), item_type == ItemType::Dir))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn collision_paths_are_reversible() {
+        let path = collision_path(ItemType::Txt, "gopher.test:70", "/a/b");
+        assert_eq!(path, "gopher.test/70/.collisions/%2Fa%2Fb");
+        assert_eq!(parse_mirror_path(&path), Some((String::from("gopher.test:70"), String::from("/a/b"), false)));
+
+        // Long selectors are split into several names
+        let selector = format!("/{}", "x".repeat(MAX_FILENAME_LEN * 2));
+        let path = collision_path(ItemType::Dir, "gopher.test:70", &selector);
+        assert!(path.split('/').count() > 5);
+        assert_eq!(parse_mirror_path(&path), Some((String::from("gopher.test:70"), selector, true)));
+    }
+}
@@ -0,0 +1,203 @@
+pub mod scenario;
+
+use::debug_print::debug_eprintln;
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::Duration
+};
+
+// Chrono imports for data-time functionality
+use chrono::Local;
+#[allow(unused_imports)]
+use chrono::Timelike;
+
+use crate::gopher::{
+    menu::{Menu, MenuItem},
+    response::ItemType
+};
+
+use crate::storage::read_manifest;
+use crate::CRLF;
+
+/// Longest selector line accepted from a client
+const MAX_SELECTOR_LEN: u64 = 4096;
+
+/// Represents a crawl snapshot that can be served as a Gopher server.
+///
+/// * `folder`: Folder containing the mirror and its manifest
+/// * `server_name`: Host name advertised in the rewritten menus
+/// * `server_port`: Port number advertised in the rewritten menus
+/// * `items`: Mirrored items keyed by their local selector
+///   (item type, path within `folder`) pairs
+/// * `local_selectors`: Local selector of every mirrored item, keyed by
+///   (server details, original selector) pairs
+pub struct Snapshot {
+    folder: PathBuf,
+    server_name: String,
+    server_port: u16,
+    items: HashMap<String, (ItemType, String)>,
+    local_selectors: HashMap<(String, String), String>,
+}
+
+impl Snapshot {
+    /// Loads a snapshot written by `Crawler::save_snapshot` from the manifest of its mirror.
+    ///
+    /// Items from the crawled (root) server keep their selector. Items from any
+    /// other server are served under `/<server details><selector>`.
+    ///
+    /// # Arguments
+    /// * `folder`: Folder containing the snapshot
+    /// * `server_name`: Host name the snapshot will be served on
+    /// * `server_port`: Port number the snapshot will be served on
+    ///
+    /// # Returns
+    /// A new `Snapshot` if the manifest could be read. Otherwise, returns an IO error.
+    pub fn load(folder: &Path, server_name: &str, server_port: u16) -> io::Result<Snapshot> {
+        let mut snapshot = Snapshot {
+            folder: folder.to_path_buf(),
+            server_name: server_name.to_string(),
+            server_port,
+            items: HashMap::new(),
+            local_selectors: HashMap::new(),
+        };
+        let mut root_details = None;
+
+        for (item_type, server_details, selector, path) in read_manifest(folder)? {
+            // The first line of the manifest is the root menu
+            let root_details = root_details.get_or_insert_with(|| server_details.clone());
+            let local_selector = if server_details == *root_details {
+                selector.clone()
+            } else {
+                format!("/{}{}", server_details, selector)
+            };
+
+            snapshot.items.insert(local_selector.clone(), (item_type, path));
+            snapshot.local_selectors.insert((server_details, selector), local_selector);
+        }
+        Ok(snapshot)
+    }
+
+    /// Returns the bytes to send in response to a selector.
+    ///
+    /// Menus have every link to a mirrored item rewritten to point at this server.
+    /// Text files and menus are terminated by the last line `.`CRLF, and lines of
+    /// text files starting with `.` get another `.` in front. Unknown selectors
+    /// are answered with an error menu.
+    pub fn respond(&self, selector: &str) -> io::Result<Vec<u8>> {
+        let Some((item_type, path)) = self.items.get(selector) else {
+            let menu = Menu::builder(&self.server_name, self.server_port)
+                .error(&format!("'{}' does not exist in this snapshot", selector))
+                .build();
+            return Ok(menu.serialize().unwrap_or_default().into_bytes())
+        };
+
+        let contents = fs::read(self.folder.join(path))?;
+        match item_type {
+            ItemType::Dir => {
+                let menu = Menu::parse(&contents)
+                    .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+                let menu = Menu {
+                    items: menu.items.into_iter().map(|item| self.rewrite(item)).collect()
+                };
+                menu.serialize()
+                    .map(String::into_bytes)
+                    .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+            },
+            ItemType::Txt => Ok(dot_stuff(&contents)),
+            _ => Ok(contents),
+        }
+    }
+
+    /// Points a menu item at this server if the item it links to was mirrored
+    fn rewrite(&self, item: MenuItem) -> MenuItem {
+        let server_details = format!("{}:{}", item.server_name, item.server_port);
+        match self.local_selectors.get(&(server_details, item.selector.clone())) {
+            Some(local_selector) => MenuItem {
+                selector: local_selector.clone(),
+                server_name: self.server_name.clone(),
+                server_port: self.server_port,
+                ..item
+            },
+            None => item,
+        }
+    }
+}
+
+/// Terminates a text file with the last line `.`CRLF as RFC 1436 requires. Every
+/// line starting with `.` gets another `.` in front, so clients do not mistake it
+/// for the last line, and a file not ending with a line break gets one.
+fn dot_stuff(contents: &[u8]) -> Vec<u8> {
+    let mut stuffed = Vec::with_capacity(contents.len() + 3);
+    for line in contents.split_inclusive(|&byte| byte == b'\n') {
+        if line.starts_with(b".") {
+            stuffed.push(b'.');
+        }
+        stuffed.extend_from_slice(line);
+    }
+    if !stuffed.is_empty() && !stuffed.ends_with(b"\n") {
+        stuffed.extend_from_slice(CRLF.as_bytes());
+    }
+    stuffed.extend_from_slice(b".");
+    stuffed.extend_from_slice(CRLF.as_bytes());
+    stuffed
+}
+
+/// Serves a crawl snapshot as a Gopher server until the process is stopped.
+///
+/// # Arguments
+/// * `folder`: Folder containing the snapshot
+/// * `server_name`: Host name advertised in the rewritten menus
+/// * `server_port`: Port number to listen on
+///
+/// # Returns
+/// Only returns if the snapshot cannot be loaded or the port cannot be bound.
+pub fn serve(folder: &Path, server_name: &str, server_port: u16) -> io::Result<()> {
+    let snapshot = Arc::new(Snapshot::load(folder, server_name, server_port)?);
+    let listener = TcpListener::bind(("0.0.0.0", server_port))?;
+
+    println!("SERVING {} ON {}:{}", folder.display(), server_name, server_port);
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                debug_eprintln!("Failed to accept connection: {error}");
+                continue
+            }
+        };
+        let snapshot = Arc::clone(&snapshot);
+        thread::spawn(move || {
+            if let Err(error) = handle_connection(stream, &snapshot) {
+                debug_eprintln!("Problem handling connection: {error}");
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Reads a single selector from a client and sends back the response
+fn handle_connection(mut stream: TcpStream, snapshot: &Snapshot) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).take(MAX_SELECTOR_LEN).read_line(&mut line)?;
+    let selector = line.trim_end_matches(['\r', '\n']);
+
+    // Get the current local time
+    let local_time = Local::now();
+
+    println!("[{:02}h:{:02}m:{:02}s]: SERVING {:?} TO {}",
+        local_time.time().hour(), local_time.time().minute(), local_time.time().second(),
+        selector, stream.peer_addr()?
+    );
+
+    let response = snapshot.respond(selector)?;
+    stream.write_all(&response)
+}
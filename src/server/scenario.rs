@@ -0,0 +1,363 @@
+use::debug_print::debug_eprintln;
+
+use std::{
+    collections::HashMap,
+    fmt,
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    path::Path,
+    sync::Arc,
+    thread,
+    time::Duration
+};
+
+use crate::CRLF;
+
+/// Prefix of the line which starts a new route in a scenario file
+const ROUTE_PREFIX: &str = "==";
+
+/// How long a `Silent` route keeps the connection open
+const SILENT_FOR: Duration = Duration::from_secs(30);
+
+/// Longest selector line accepted from a client
+const MAX_SELECTOR_LEN: u64 = 4096;
+
+/// Represents how the test server responds to a selector.
+///
+/// * `Menu(lines)`: Sends the lines as a menu
+/// * `Text(lines)`: Sends the lines as a text file
+/// * `Binary(len)`: Sends `len` pseudo-random bytes
+/// * `Endless`: Keeps sending text until the client disconnects
+/// * `Silent`: Accepts the request but never responds
+/// * `Close`: Closes the connection without responding
+/// * `Reset`: Resets the connection without responding
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Behaviour {
+    Menu(Vec<String>),
+    Text(Vec<String>),
+    Binary(usize),
+    Endless,
+    Silent,
+    Close,
+    Reset,
+}
+
+/// Represents a single route of a scenario.
+///
+/// * `behaviour`: How the server responds
+/// * `bare_lf`: Lines are ended with LF instead of CRLF
+/// * `terminated`: Menus and text files are followed by the last line `.`CRLF
+/// * `delay`: Time to wait before responding
+/// * `drip`: Response is sent in chunks of n bytes with a pause between them
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Route {
+    pub behaviour: Behaviour,
+    pub bare_lf: bool,
+    pub terminated: bool,
+    pub delay: Option<Duration>,
+    pub drip: Option<(usize, Duration)>,
+}
+
+/// Represents a declarative description of a (misbehaving) Gopher server.
+///
+/// A scenario file is a list of routes. Each route starts with a `==` line
+/// naming the selector, followed by a directive line and an optional body:
+///
+/// ```text
+/// # Comments before the first route are ignored
+/// ==
+/// menu
+/// 1Slow file\t/slow\t{host}\t{port}
+/// == /slow
+/// text drip=16:500
+/// Arrives 16 bytes at a time
+/// ```
+///
+/// Directives are `menu`, `text`, `binary <len>`, `endless`, `silent`, `close`
+/// and `reset`, followed by any of the options `lf` (bare LF line endings),
+/// `unterminated` (no last line), `delay=<ms>` and `drip=<bytes>:<ms>`.
+/// In bodies, `\t` may be written instead of a tab and
+/// `{host}`, `{port}` and `{refused_port}` are replaced with the address of
+/// the server and a port which refuses connections.
+///
+/// * `routes`: Routes keyed by selector
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Scenario {
+    pub routes: HashMap<String, Route>,
+}
+
+/// Represents a running test server.
+///
+/// * `server_name`: Host name the server is reachable on
+/// * `server_port`: Port number the server is listening on
+/// * `refused_port`: Port number on which connections are refused
+pub struct ScenarioServer {
+    pub server_name: String,
+    pub server_port: u16,
+    pub refused_port: u16,
+}
+
+impl Scenario {
+    /// Reads and parses a scenario file
+    pub fn load(path: &Path) -> io::Result<Scenario> {
+        let contents = fs::read_to_string(path)?;
+        Scenario::parse(&contents).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// Parses the contents of a scenario file
+    ///
+    /// # Returns
+    /// A new `Scenario` if the contents are valid. Otherwise, returns the
+    /// appropriate `ScenarioError`.
+    pub fn parse(contents: &str) -> Result<Scenario, ScenarioError> {
+        let mut scenario = Scenario::default();
+        let mut lines = contents.lines().enumerate().peekable();
+
+        // Skip everything before the first route
+        while lines.next_if(|(_, line)| !line.starts_with(ROUTE_PREFIX)).is_some() {}
+
+        while let Some((_, header)) = lines.next() {
+            let selector = header[ROUTE_PREFIX.len()..].trim().to_string();
+            let (line_number, directive) = lines.next()
+                .ok_or_else(|| ScenarioError::MissingDirective(selector.clone()))?;
+
+            let mut body = Vec::new();
+            while let Some((_, line)) = lines.next_if(|(_, line)| !line.starts_with(ROUTE_PREFIX)) {
+                body.push(line.replace("\\t", "\t"));
+            }
+            // Trailing blank lines only seperate routes
+            while body.last().is_some_and(|line| line.is_empty()) {
+                body.pop();
+            }
+
+            let route = Route::parse(directive, body)
+                .map_err(|message| ScenarioError::InvalidDirective(line_number + 1, message))?;
+            scenario.routes.insert(selector, route);
+        }
+        Ok(scenario)
+    }
+
+    /// Starts serving the scenario on an ephemeral port of 127.0.0.1 in the
+    /// background. The server runs until the process exits.
+    pub fn spawn(self) -> io::Result<ScenarioServer> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let server = ScenarioServer {
+            server_name: String::from("127.0.0.1"),
+            server_port: listener.local_addr()?.port(),
+            refused_port: refused_port()?,
+        };
+
+        let scenario = Arc::new(self);
+        let (server_name, refused_port) = (server.server_name.clone(), server.refused_port);
+        thread::spawn(move || accept_loop(listener, scenario, &server_name, refused_port));
+        Ok(server)
+    }
+
+    /// Serves the scenario until the process is stopped
+    ///
+    /// # Arguments
+    /// * `server_name`: Host name written into the menus
+    /// * `server_port`: Port number to listen on
+    pub fn serve(self, server_name: &str, server_port: u16) -> io::Result<()> {
+        let listener = TcpListener::bind(("0.0.0.0", server_port))?;
+        println!("SERVING SCENARIO ON {}:{}", server_name, server_port);
+        accept_loop(listener, Arc::new(self), server_name, refused_port()?);
+        Ok(())
+    }
+}
+
+impl Route {
+    /// Parses the directive line of a route
+    fn parse(directive: &str, body: Vec<String>) -> Result<Route, String> {
+        let mut words = directive.split_whitespace();
+        let behaviour = match words.next() {
+            Some("menu") => Behaviour::Menu(body),
+            Some("text") => Behaviour::Text(body),
+            Some("binary") => {
+                let len = words.next().and_then(|len| len.parse().ok())
+                    .ok_or("binary requires a length in bytes")?;
+                Behaviour::Binary(len)
+            },
+            Some("endless") => Behaviour::Endless,
+            Some("silent") => Behaviour::Silent,
+            Some("close") => Behaviour::Close,
+            Some("reset") => Behaviour::Reset,
+            Some(other) => return Err(format!("unknown directive '{other}'")),
+            None => return Err(String::from("missing directive")),
+        };
+
+        let mut route = Route { behaviour, bare_lf: false, terminated: true, delay: None, drip: None };
+        for option in words {
+            match option.split_once('=') {
+                None if option == "lf" => route.bare_lf = true,
+                None if option == "unterminated" => route.terminated = false,
+                Some(("delay", ms)) => {
+                    let ms = ms.parse().map_err(|_| format!("invalid delay '{ms}'"))?;
+                    route.delay = Some(Duration::from_millis(ms));
+                },
+                Some(("drip", value)) => {
+                    let drip = value.split_once(':')
+                        .and_then(|(bytes, ms)| Some((bytes.parse().ok()?, ms.parse().ok()?)))
+                        .filter(|(bytes, _)| *bytes > 0)
+                        .ok_or_else(|| format!("invalid drip '{value}', expected <bytes>:<ms>"))?;
+                    route.drip = Some((drip.0, Duration::from_millis(drip.1)));
+                },
+                _ => return Err(format!("unknown option '{option}'")),
+            }
+        }
+        Ok(route)
+    }
+
+    /// Returns the bytes of a `Menu`, `Text` or `Binary` response
+    fn body(&self, server_name: &str, server_port: u16, refused_port: u16) -> Vec<u8> {
+        let line_ending = if self.bare_lf { "\n" } else { CRLF };
+        let lines = match &self.behaviour {
+            Behaviour::Menu(lines) | Behaviour::Text(lines) => lines,
+            Behaviour::Binary(len) => return pseudo_random_bytes(*len),
+            _ => return Vec::new(),
+        };
+
+        let mut body = String::new();
+        for line in lines {
+            body.push_str(&line
+                .replace("{host}", server_name)
+                .replace("{port}", &server_port.to_string())
+                .replace("{refused_port}", &refused_port.to_string())
+            );
+            body.push_str(line_ending);
+        }
+        if self.terminated {
+            body.push('.');
+            body.push_str(line_ending);
+        }
+        body.into_bytes()
+    }
+}
+
+/// Accepts connections forever, handling each one on its own thread
+fn accept_loop(listener: TcpListener, scenario: Arc<Scenario>, server_name: &str, refused_port: u16) {
+    let server_port = listener.local_addr().map(|addr| addr.port()).unwrap_or_default();
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                debug_eprintln!("Failed to accept connection: {error}");
+                continue
+            }
+        };
+        let scenario = Arc::clone(&scenario);
+        let server_name = server_name.to_string();
+        thread::spawn(move || {
+            if let Err(error) = handle_connection(stream, &scenario, &server_name, server_port, refused_port) {
+                debug_eprintln!("Problem handling connection: {error}");
+            }
+        });
+    }
+}
+
+/// Reads a single selector from a client and misbehaves as instructed
+fn handle_connection(
+    mut stream: TcpStream,
+    scenario: &Scenario,
+    server_name: &str,
+    server_port: u16,
+    refused_port: u16
+) -> io::Result<()> {
+    // Peek at the request so that a reset route leaves it unread
+    let mut peeked = [0; MAX_SELECTOR_LEN as usize];
+    let n = stream.peek(&mut peeked)?;
+    if let Some(Route { behaviour: Behaviour::Reset, .. }) = scenario.routes.get(peek_selector(&peeked[..n])) {
+        // Closing a socket with unread data makes the kernel send a RST
+        return Ok(())
+    }
+
+    let mut line = String::new();
+    BufReader::new(&stream).take(MAX_SELECTOR_LEN).read_line(&mut line)?;
+    let selector = line.trim_end_matches(['\r', '\n']);
+
+    let Some(route) = scenario.routes.get(selector) else {
+        let not_found = format!("3'{selector}' does not exist\t\terror.host\t1{CRLF}.{CRLF}");
+        return stream.write_all(not_found.as_bytes())
+    };
+
+    if let Some(delay) = route.delay {
+        thread::sleep(delay);
+    }
+
+    match route.behaviour {
+        Behaviour::Silent => {
+            thread::sleep(SILENT_FOR);
+            Ok(())
+        },
+        Behaviour::Close | Behaviour::Reset => stream.shutdown(Shutdown::Both),
+        Behaviour::Endless => {
+            // Throttled, so an endless response does not flood the client's memory
+            let chunk = "All work and no play makes Jack a dull boy\r\n".repeat(64);
+            loop {
+                stream.write_all(chunk.as_bytes())?;
+                thread::sleep(Duration::from_millis(10));
+            }
+        },
+        _ => {
+            let body = route.body(server_name, server_port, refused_port);
+            match route.drip {
+                Some((bytes, pause)) => {
+                    for chunk in body.chunks(bytes) {
+                        stream.write_all(chunk)?;
+                        stream.flush()?;
+                        thread::sleep(pause);
+                    }
+                    Ok(())
+                },
+                None => stream.write_all(&body),
+            }
+        }
+    }
+}
+
+/// Returns the selector of a (possibly incomplete) request
+fn peek_selector(buffer: &[u8]) -> &str {
+    let end = buffer.iter().position(|&b| b == b'\r' || b == b'\n').unwrap_or(buffer.len());
+    std::str::from_utf8(&buffer[..end]).unwrap_or_default()
+}
+
+/// Returns a port on which nothing is listening
+fn refused_port() -> io::Result<u16> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    listener.local_addr().map(|addr| addr.port())
+}
+
+/// Generates deterministic bytes which are not valid text
+fn pseudo_random_bytes(len: usize) -> Vec<u8> {
+    let mut state: u32 = 0x2545_f491;
+    (0..len).map(|_| {
+        // xorshift32
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state as u8
+    }).collect()
+}
+
+/// Represents issues with a scenario file.
+///
+/// * `MissingDirective(selector)`: A route has no directive line
+/// * `InvalidDirective(line number, message)`: A directive line is invalid
+#[derive(Debug)]
+pub enum ScenarioError {
+    MissingDirective(String),
+    InvalidDirective(usize, String),
+}
+
+impl std::error::Error for ScenarioError {}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::MissingDirective(selector) => write!(f, "Missing directive for selector '{selector}'"),
+            ScenarioError::InvalidDirective(line, message) => write!(f, "Invalid directive on line {line}: {message}"),
+        }
+    }
+}
@@ -0,0 +1,431 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    net::IpAddr,
+    path::Path,
+    thread,
+    time::{Duration, Instant}
+};
+
+use crate::gopher::{
+    request::Request,
+    response::{ItemType, Response, ResponseOutcome}
+};
+
+use crate::transport::Transport;
+
+/// First line of every session file
+const SESSION_HEADER: &str = "GOPHER-SESSION 1";
+
+/// Represents a single recorded exchange with a Gopher server.
+///
+/// * `Request(server details, selector, item type, response, elapsed)`: A request
+///   and the response it received
+/// * `Error(server details, selector, item type, error, elapsed)`: A request which
+///   failed with an IO error instead of receiving a response
+/// * `Connect(server details, connected, elapsed)`: A connection attempt without
+///   a request
+/// * `Resolve(server details, addresses, elapsed)`: A hostname resolution and the
+///   addresses it resolved to. No addresses if it failed.
+#[derive(Clone, Debug)]
+pub enum Transaction {
+    Request(String, String, ItemType, Response, Duration),
+    Error(String, String, ItemType, String, Duration),
+    Connect(String, bool, Duration),
+    Resolve(String, Vec<IpAddr>, Duration),
+}
+
+/// Transport which records every transaction of an inner transport to a
+/// session file.
+///
+/// Each transaction is written as soon as it completes, so the session of a
+/// crawl which is killed part way through is kept.
+///
+/// * `inner`: Transport which does the actual work
+/// * `file`: Session file being written
+pub struct RecordingTransport<T: Transport> {
+    inner: T,
+    file: BufWriter<File>,
+}
+
+/// Transport which answers requests from a recorded session without any network
+/// access.
+///
+/// * `requests`: Recorded responses (or IO errors) keyed by (server details, 
+///   selector) pairs
+/// * `connects`: Recorded connection attempts keyed by server details
+/// * `resolutions`: Recorded hostname resolutions keyed by server details
+/// * `realtime`: Wait for the recorded duration of each transaction
+pub struct ReplayTransport {
+    requests: HashMap<(String, String), (Result<Response, String>, Duration)>,
+    connects: HashMap<String, (bool, Duration)>,
+    resolutions: HashMap<String, (Vec<IpAddr>, Duration)>,
+    realtime: bool,
+}
+
+impl<T: Transport> RecordingTransport<T> {
+    /// Constructs a new `RecordingTransport`, creating (or truncating) the session file
+    ///
+    /// # Arguments
+    /// * `inner`: Transport which does the actual work
+    /// * `path`: Path of the session file
+    pub fn create(inner: T, path: &Path) -> io::Result<RecordingTransport<T>> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "{}", SESSION_HEADER)?;
+        file.flush()?;
+
+        Ok(RecordingTransport { inner, file })
+    }
+
+    fn record(&mut self, transaction: &Transaction) -> io::Result<()> {
+        write_transaction(&mut self.file, transaction)?;
+        self.file.flush()
+    }
+}
+
+impl<T: Transport> Transport for RecordingTransport<T> {
+    fn send_and_recv(&mut self, request: &Request) -> io::Result<Response> {
+        let start = Instant::now();
+        let result = self.inner.send_and_recv(request);
+
+        let server_details = request.server_details.to_string();
+        let selector = request.selector.to_string();
+        self.record(&match &result {
+            Ok(response) => Transaction::Request(server_details, selector, request.item_type, response.clone(), start.elapsed()),
+            Err(error) => Transaction::Error(server_details, selector, request.item_type, error.to_string(), start.elapsed()),
+        })?;
+        result
+    }
+
+    fn connect(&mut self, server_details: &str) -> io::Result<()> {
+        let start = Instant::now();
+        let result = self.inner.connect(server_details);
+
+        self.record(&Transaction::Connect(server_details.to_string(), result.is_ok(), start.elapsed()))?;
+        result
+    }
+
+    fn resolve(&mut self, server_details: &str) -> io::Result<Vec<IpAddr>> {
+        let start = Instant::now();
+        let result = self.inner.resolve(server_details);
+
+        // Failed resolutions are recorded without any addresses
+        let addresses = result.as_ref().map_or_else(|_| Vec::new(), Vec::clone);
+        self.record(&Transaction::Resolve(server_details.to_string(), addresses, start.elapsed()))?;
+        result
+    }
+}
+
+impl ReplayTransport {
+    /// Loads a session file written by a `RecordingTransport`
+    pub fn load(path: &Path) -> io::Result<ReplayTransport> {
+        Ok(ReplayTransport::from_transactions(read_session(path)?))
+    }
+
+    /// Constructs a new `ReplayTransport` from recorded transactions. If a
+    /// request was recorded more than once, the first response is replayed.
+    pub fn from_transactions(transactions: Vec<Transaction>) -> ReplayTransport {
+        let mut replay = ReplayTransport {
+            requests: HashMap::new(),
+            connects: HashMap::new(),
+            resolutions: HashMap::new(),
+            realtime: false,
+        };
+        for transaction in transactions {
+            match transaction {
+                Transaction::Request(server_details, selector, _, response, elapsed) => {
+                    replay.requests.entry((server_details, selector)).or_insert((Ok(response), elapsed));
+                },
+                Transaction::Error(server_details, selector, _, error, elapsed) => {
+                    replay.requests.entry((server_details, selector)).or_insert((Err(error), elapsed));
+                },
+                Transaction::Connect(server_details, connected, elapsed) => {
+                    replay.connects.entry(server_details).or_insert((connected, elapsed));
+                },
+                Transaction::Resolve(server_details, addresses, elapsed) => {
+                    replay.resolutions.entry(server_details).or_insert((addresses, elapsed));
+                },
+            }
+        }
+        replay
+    }
+
+    /// Waits for the recorded duration of each transaction before answering it,
+    /// reproducing the timing of the recorded crawl
+    pub fn realtime(mut self, realtime: bool) -> ReplayTransport {
+        self.realtime = realtime;
+        self
+    }
+
+    fn wait(&self, elapsed: Duration) {
+        if self.realtime {
+            thread::sleep(elapsed);
+        }
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send_and_recv(&mut self, request: &Request) -> io::Result<Response> {
+        let key = (request.server_details.to_string(), request.selector.to_string());
+        match self.requests.get(&key) {
+            Some((result, elapsed)) => {
+                self.wait(*elapsed);
+                result.clone().map_err(io::Error::other)
+            },
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} {} was not recorded in the session", request.server_details, request.selector)
+            )),
+        }
+    }
+
+    fn connect(&mut self, server_details: &str) -> io::Result<()> {
+        match self.connects.get(server_details) {
+            Some((true, elapsed)) => {
+                self.wait(*elapsed);
+                Ok(())
+            },
+            Some((false, elapsed)) => {
+                self.wait(*elapsed);
+                Err(io::Error::new(io::ErrorKind::AddrNotAvailable, "Recorded connection failed"))
+            },
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Connection to {} was not recorded in the session", server_details)
+            )),
+        }
+    }
+
+    fn resolve(&mut self, server_details: &str) -> io::Result<Vec<IpAddr>> {
+        match self.resolutions.get(server_details) {
+            Some((addresses, elapsed)) => {
+                self.wait(*elapsed);
+                match addresses.is_empty() {
+                    true => Err(io::Error::new(io::ErrorKind::NotFound, "Recorded resolution failed")),
+                    false => Ok(addresses.clone()),
+                }
+            },
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Resolution of {} was not recorded in the session", server_details)
+            )),
+        }
+    }
+}
+
+/// Reads every transaction of a session file, in the order they were recorded.
+///
+/// A session file starts with the line `GOPHER-SESSION 1`, followed by one
+/// record per transaction:
+///
+/// ```text
+/// REQUEST <item type> <outcome> <elapsed µs> <server details length> <selector length> <response length>
+/// <server details bytes>
+/// <selector bytes>
+/// <response bytes>
+/// ERROR <item type> <elapsed µs> <server details length> <selector length> <error length>
+/// <server details bytes>
+/// <selector bytes>
+/// <error message bytes>
+/// CONNECT <ok|failed> <elapsed µs> <server details length>
+/// <server details bytes>
+/// RESOLVE <elapsed µs> <server details length> <addresses length>
+/// <server details bytes>
+/// <addresses, seperated by spaces>
+/// ```
+///
+/// A record which was cut off (e.g. because the crawl was killed) ends the session.
+pub fn read_session(path: &Path) -> io::Result<Vec<Transaction>> {
+    let contents = fs::read(path)?;
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), message));
+
+    let mut cursor = SessionCursor { contents: &contents, position: 0 };
+    if cursor.line() != Some(SESSION_HEADER.as_bytes()) {
+        return Err(invalid("not a session file"));
+    }
+
+    let mut transactions = Vec::new();
+    while let Some(line) = cursor.line() {
+        let Ok(line) = std::str::from_utf8(line) else {
+            return Err(invalid(&format!("record is not valid UTF-8: {}", String::from_utf8_lossy(line))));
+        };
+        let fields = line.split(' ').collect::<Vec<_>>();
+        let transaction = match fields[..] {
+            ["REQUEST", item_type, outcome, elapsed, details_len, selector_len, response_len] => {
+                let (Some(item_type), Some(outcome), Ok(elapsed), Ok(details_len), Ok(selector_len), Ok(response_len)) = (
+                    item_type.chars().next().map(ItemType::from_char),
+                    parse_outcome(outcome),
+                    elapsed.parse(),
+                    details_len.parse(),
+                    selector_len.parse(),
+                    response_len.parse(),
+                ) else {
+                    return Err(invalid(&format!("malformed record: {line}")));
+                };
+                let Some(server_details) = cursor.bytes(details_len) else { break };
+                let Some(selector) = cursor.bytes(selector_len) else { break };
+                let Some(buffer) = cursor.bytes(response_len) else { break };
+
+                Transaction::Request(
+                    String::from_utf8_lossy(server_details).into_owned(),
+                    String::from_utf8_lossy(selector).into_owned(),
+                    item_type,
+                    Response::new(buffer.to_vec(), outcome),
+                    Duration::from_micros(elapsed)
+                )
+            },
+            ["ERROR", item_type, elapsed, details_len, selector_len, error_len] => {
+                let (Some(item_type), Ok(elapsed), Ok(details_len), Ok(selector_len), Ok(error_len)) = (
+                    item_type.chars().next().map(ItemType::from_char),
+                    elapsed.parse(),
+                    details_len.parse(),
+                    selector_len.parse(),
+                    error_len.parse(),
+                ) else {
+                    return Err(invalid(&format!("malformed record: {line}")));
+                };
+                let Some(server_details) = cursor.bytes(details_len) else { break };
+                let Some(selector) = cursor.bytes(selector_len) else { break };
+                let Some(error) = cursor.bytes(error_len) else { break };
+
+                Transaction::Error(
+                    String::from_utf8_lossy(server_details).into_owned(),
+                    String::from_utf8_lossy(selector).into_owned(),
+                    item_type,
+                    String::from_utf8_lossy(error).into_owned(),
+                    Duration::from_micros(elapsed)
+                )
+            },
+            ["CONNECT", connected, elapsed, details_len] => {
+                let (Ok(elapsed), Ok(details_len)) = (elapsed.parse(), details_len.parse()) else {
+                    return Err(invalid(&format!("malformed record: {line}")));
+                };
+                let Some(server_details) = cursor.bytes(details_len) else { break };
+                Transaction::Connect(
+                    String::from_utf8_lossy(server_details).into_owned(), 
+                    connected == "ok", 
+                    Duration::from_micros(elapsed)
+                )
+            },
+            ["RESOLVE", elapsed, details_len, addresses_len] => {
+                let (Ok(elapsed), Ok(details_len), Ok(addresses_len)) = (elapsed.parse(), details_len.parse(), addresses_len.parse()) else {
+                    return Err(invalid(&format!("malformed record: {line}")));
+                };
+                let Some(server_details) = cursor.bytes(details_len) else { break };
+                let Some(addresses) = cursor.bytes(addresses_len) else { break };
+                let Ok(addresses) = String::from_utf8_lossy(addresses).split_whitespace().map(str::parse).collect() else {
+                    return Err(invalid(&format!("malformed addresses: {line}")));
+                };
+                Transaction::Resolve(
+                    String::from_utf8_lossy(server_details).into_owned(),
+                    addresses,
+                    Duration::from_micros(elapsed)
+                )
+            },
+            _ => return Err(invalid(&format!("malformed record: {line}"))),
+        };
+        transactions.push(transaction);
+    }
+    Ok(transactions)
+}
+
+/// Returns the root server (hostname, port) of a recorded crawl, i.e. the server
+/// of the first request.
+pub fn root_server(transactions: &[Transaction]) -> Option<(String, u16)> {
+    transactions.iter().find_map(|transaction| match transaction {
+        Transaction::Request(server_details, ..) | Transaction::Error(server_details, ..) => {
+            let (server_name, server_port) = server_details.rsplit_once(':')?;
+            Some((server_name.to_string(), server_port.parse().ok()?))
+        },
+        _ => None,
+    })
+}
+
+fn write_transaction(out: &mut impl Write, transaction: &Transaction) -> io::Result<()> {
+    match transaction {
+        Transaction::Request(server_details, selector, item_type, response, elapsed) => {
+            writeln!(out, "REQUEST {} {} {} {} {} {}",
+                item_type.to_char(), outcome_name(response.response_outcome), elapsed.as_micros(), 
+                server_details.len(), selector.len(), response.buffer.len()
+            )?;
+            for field in [server_details.as_bytes(), selector.as_bytes(), &response.buffer] {
+                out.write_all(field)?;
+                out.write_all(b"\n")?;
+            }
+            Ok(())
+        },
+        Transaction::Error(server_details, selector, item_type, error, elapsed) => {
+            writeln!(out, "ERROR {} {} {} {} {}",
+                item_type.to_char(), elapsed.as_micros(), server_details.len(), selector.len(), error.len()
+            )?;
+            for field in [server_details, selector, error] {
+                writeln!(out, "{}", field)?;
+            }
+            Ok(())
+        },
+        Transaction::Connect(server_details, connected, elapsed) => {
+            writeln!(out, "CONNECT {} {} {}",
+                if *connected { "ok" } else { "failed" }, elapsed.as_micros(), server_details.len()
+            )?;
+            writeln!(out, "{}", server_details)
+        },
+        Transaction::Resolve(server_details, addresses, elapsed) => {
+            let addresses = addresses.iter().map(IpAddr::to_string).collect::<Vec<_>>().join(" ");
+            writeln!(out, "RESOLVE {} {} {}", elapsed.as_micros(), server_details.len(), addresses.len())?;
+            writeln!(out, "{}", server_details)?;
+            writeln!(out, "{}", addresses)
+        },
+    }
+}
+
+/// Name used for a `ResponseOutcome` in session and archive files
+pub(crate) fn outcome_name(outcome: ResponseOutcome) -> &'static str {
+    match outcome {
+        ResponseOutcome::Complete              => "complete",
+        ResponseOutcome::Timeout               => "timeout",
+        ResponseOutcome::FileTooLong           => "file-too-long",
+        ResponseOutcome::ConnectionFailed      => "connection-failed",
+        ResponseOutcome::ConnectionReset       => "connection-reset",
+        ResponseOutcome::MissingEndLine        => "missing-end-line",
+        ResponseOutcome::MalformedResponseLine => "malformed-response-line",
+    }
+}
+
+fn parse_outcome(name: &str) -> Option<ResponseOutcome> {
+    [
+        ResponseOutcome::Complete,
+        ResponseOutcome::Timeout,
+        ResponseOutcome::FileTooLong,
+        ResponseOutcome::ConnectionFailed,
+        ResponseOutcome::ConnectionReset,
+        ResponseOutcome::MissingEndLine,
+        ResponseOutcome::MalformedResponseLine,
+    ].into_iter().find(|outcome| outcome_name(*outcome) == name)
+}
+
+/// Reads the lines and length-prefixed byte strings of a session file
+struct SessionCursor<'a> {
+    contents: &'a [u8],
+    position: usize,
+}
+
+impl<'a> SessionCursor<'a> {
+    /// Returns the next line, without the LF
+    fn line(&mut self) -> Option<&'a [u8]> {
+        let remaining = &self.contents[self.position..];
+        let end = remaining.iter().position(|&b| b == b'\n')?;
+        self.position += end + 1;
+        Some(&remaining[..end])
+    }
+
+    /// Returns the next `len` bytes, skipping the LF which follows them
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let remaining = &self.contents[self.position..];
+        if remaining.len() < len + 1 {
+            return None;
+        }
+        self.position += len + 1;
+        Some(&remaining[..len])
+    }
+}
@@ -0,0 +1,354 @@
+pub mod content;
+pub mod tarball;
+
+use std::{
+    cell::RefCell,
+    fmt,
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+    rc::Rc,
+    str::FromStr
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::gopher::response::ItemType;
+
+use crate::mirror::Mirror;
+use crate::MANIFEST;
+
+use self::{content::ContentStorage, tarball::TarStorage};
+
+/// Line of a manifest (item type, server details, selector, path) quadruple
+pub type ManifestEntry = (ItemType, String, String, String);
+
+/// Item kept by a `MemoryStorage` (item type, server details, selector, contents)
+type StoredItem = (ItemType, String, String, Vec<u8>);
+
+/// Represents somewhere the items downloaded by a crawl are kept.
+///
+/// `Crawler` hands every downloaded file (and, when a snapshot is saved, every
+/// menu) to its `Storage`, so where and how content is written is up to the
+/// embedding application.
+pub trait Storage {
+    /// Stores the contents of an item.
+    ///
+    /// # Arguments
+    /// * `item_type`: The type of the item
+    /// * `server_details`: hostname:port of the server providing the item
+    /// * `selector`: String used to request the item
+    /// * `contents`: Bytes received for the item
+    ///
+    /// # Returns
+    /// Nothing if sucessfull. Otherwise, returns an IO error.
+    fn store(&mut self, item_type: ItemType, server_details: &str, selector: &str, contents: &[u8]) -> io::Result<()>;
+
+    /// Completes the storage once every item was stored, e.g. by writing a manifest
+    fn finish(&mut self) -> io::Result<()>;
+
+    /// Returns every group of items with identical contents, if the storage keeps
+    /// track of them. Nothing otherwise.
+    fn duplicates(&self) -> Option<Vec<DuplicateGroup>> {
+        None
+    }
+
+    /// Returns what the storage remembers about the items stored so far, so it
+    /// can be saved in a checkpoint. Nothing if the storage can not be resumed.
+    fn state(&self) -> Option<StorageState> {
+        None
+    }
+
+    /// Puts back the state of a storage saved in a checkpoint, so a resumed crawl
+    /// keeps the items stored before it was interrupted. Storages which can not
+    /// be resumed ignore it.
+    fn restore(&mut self, _state: StorageState) {}
+}
+
+/// Represents what a storage remembers about the items stored so far.
+///
+/// * `entries`: Every stored item in order (item type, server details, selector,
+///   path) quadruples
+/// * `objects`: Every stored object of a content-addressed storage in the order
+///   they were first stored, with the items holding it
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageState {
+    pub entries: Vec<ManifestEntry>,
+    pub objects: Vec<DuplicateGroup>,
+}
+
+/// Represents items with identical contents which were stored once.
+///
+/// * `hash`: Hex encoded SHA-256 hash of the contents
+/// * `size`: Size of the contents (bytes)
+/// * `items`: Every item with these contents (server details, selector) pairs
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub size: u64,
+    pub items: Vec<(String, String)>,
+}
+
+/// Storage which throws every item away, e.g. for crawls which only need the report
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DiscardStorage;
+
+/// Storage which keeps every item in memory.
+///
+/// Clones share their contents, so an application can keep a clone of the
+/// storage it gives to a `Crawler` and read the items back after the crawl.
+///
+/// * `items`: Every stored item in order (item type, server details, selector,
+///   contents) quadruples
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStorage {
+    items: Rc<RefCell<Vec<StoredItem>>>,
+}
+
+/// Represents the kinds of storage which can be chosen at runtime.
+///
+/// * `Directory`: Mirror the items into a folder with `Mirror`
+/// * `Content`: Store identical items once in a folder with `ContentStorage`
+/// * `Tar`: Mirror the items into a tar archive with `TarStorage`
+/// * `Memory`: Keep the items in memory with `MemoryStorage`
+/// * `Discard`: Throw the items away with `DiscardStorage`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageKind {
+    Directory,
+    Content,
+    Tar,
+    Memory,
+    Discard,
+}
+
+impl DuplicateGroup {
+    /// Returns the number of bytes which did not have to be stored again
+    pub fn bytes_saved(&self) -> u64 {
+        self.size * (self.items.len() as u64 - 1)
+    }
+}
+
+impl<S: Storage + ?Sized> Storage for Box<S> {
+    fn store(&mut self, item_type: ItemType, server_details: &str, selector: &str, contents: &[u8]) -> io::Result<()> {
+        (**self).store(item_type, server_details, selector, contents)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        (**self).finish()
+    }
+
+    fn duplicates(&self) -> Option<Vec<DuplicateGroup>> {
+        (**self).duplicates()
+    }
+
+    fn state(&self) -> Option<StorageState> {
+        (**self).state()
+    }
+
+    fn restore(&mut self, state: StorageState) {
+        (**self).restore(state)
+    }
+}
+
+impl Storage for DiscardStorage {
+    fn store(&mut self, _: ItemType, _: &str, _: &str, _: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl MemoryStorage {
+    /// Constructs a new, empty `MemoryStorage`
+    pub fn new() -> MemoryStorage {
+        MemoryStorage::default()
+    }
+
+    /// Returns the contents of an item, if it was stored
+    ///
+    /// # Arguments
+    /// * `server_details`: hostname:port of the server providing the item
+    /// * `selector`: String used to request the item
+    pub fn get(&self, server_details: &str, selector: &str) -> Option<Vec<u8>> {
+        self.items.borrow().iter()
+            .find(|(_, details, sel, _)| details == server_details && sel == selector)
+            .map(|(.., contents)| contents.clone())
+    }
+
+    /// Returns the number of stored items
+    pub fn len(&self) -> usize {
+        self.items.borrow().len()
+    }
+
+    /// Returns true if no item was stored
+    pub fn is_empty(&self) -> bool {
+        self.items.borrow().is_empty()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn store(&mut self, item_type: ItemType, server_details: &str, selector: &str, contents: &[u8]) -> io::Result<()> {
+        self.items.borrow_mut().push((item_type, server_details.to_string(), selector.to_string(), contents.to_vec()));
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl StorageKind {
+    /// Opens a new storage of this kind.
+    ///
+    /// # Arguments
+    /// * `path`: Folder (or, for `Tar`, file) the storage writes to. Ignored by
+    ///   storages which do not write anything.
+    ///
+    /// # Returns
+    /// The storage if it could be opened. Otherwise, returns an IO error.
+    pub fn open(self, path: &Path) -> io::Result<Box<dyn Storage>> {
+        Ok(match self {
+            StorageKind::Directory => Box::new(Mirror::new(path)),
+            StorageKind::Content   => Box::new(ContentStorage::new(path)),
+            StorageKind::Tar       => Box::new(TarStorage::create(path)?),
+            StorageKind::Memory    => Box::new(MemoryStorage::new()),
+            StorageKind::Discard   => Box::new(DiscardStorage),
+        })
+    }
+
+    /// Returns true if the storage writes into a folder
+    pub fn is_folder(self) -> bool {
+        matches!(self, StorageKind::Directory | StorageKind::Content)
+    }
+
+    /// Returns true if a resumed crawl keeps the items the storage held before
+    /// the crawl was interrupted. A tar archive is written from scratch and
+    /// memory is lost with the process, so neither can be resumed.
+    pub fn is_resumable(self) -> bool {
+        !matches!(self, StorageKind::Tar | StorageKind::Memory)
+    }
+}
+
+impl FromStr for StorageKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<StorageKind, String> {
+        match name {
+            "dir"     => Ok(StorageKind::Directory),
+            "content" => Ok(StorageKind::Content),
+            "tar"     => Ok(StorageKind::Tar),
+            "memory"  => Ok(StorageKind::Memory),
+            "discard" => Ok(StorageKind::Discard),
+            _ => Err(format!("Unknown storage: {name}")),
+        }
+    }
+}
+
+impl fmt::Display for StorageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageKind::Directory => write!(f, "dir"),
+            StorageKind::Content   => write!(f, "content"),
+            StorageKind::Tar       => write!(f, "tar"),
+            StorageKind::Memory    => write!(f, "memory"),
+            StorageKind::Discard   => write!(f, "discard"),
+        }
+    }
+}
+
+/// Renders the manifest of a storage.
+///
+/// Each line of the manifest is a tab seperated (item type, server details,
+/// selector, relative path) quadruple. Tabs, line breaks and backslashes within
+/// a field are escaped as `\t`, `\n`, `\r` and `\\`. Menus are listed before
+/// any other item, in the order they were stored.
+pub(crate) fn render_manifest(entries: &[ManifestEntry]) -> String {
+    let mut entries = entries.iter().collect::<Vec<_>>();
+    entries.sort_by_key(|(item_type, ..)| *item_type != ItemType::Dir);
+
+    let mut manifest = String::new();
+    for (item_type, server_details, selector, path) in entries {
+        let fields = [&item_type.to_char().to_string(), server_details, selector, path].map(|field| escape_field(field));
+        manifest.push_str(&format!("{}\n", fields.join("\t")));
+    }
+    manifest
+}
+
+/// Writes the manifest of a storage to `folder`, creating the folder if needed
+pub(crate) fn write_manifest(folder: &Path, entries: &[ManifestEntry]) -> io::Result<()> {
+    fs::create_dir_all(folder)?;
+    File::create(folder.join(MANIFEST))?.write_all(render_manifest(entries).as_bytes())
+}
+
+/// Reads the manifest written to `folder` by `write_manifest`
+///
+/// # Returns
+/// Every line of the manifest in order if it could be read. Otherwise, returns 
+/// an IO error.
+pub(crate) fn read_manifest(folder: &Path) -> io::Result<Vec<ManifestEntry>> {
+    let manifest = fs::read_to_string(folder.join(MANIFEST))?;
+
+    let mut entries = Vec::new();
+    for line in manifest.lines() {
+        let malformed = || io::Error::new(io::ErrorKind::InvalidData, format!("Malformed manifest line: {line}"));
+        let parts = line.split('\t').map(unescape_field).collect::<Option<Vec<_>>>().ok_or_else(malformed)?;
+        let [item_type, server_details, selector, path] = <[String; 4]>::try_from(parts).map_err(|_| malformed())?;
+        let item_type = ItemType::from_char(item_type.chars().next().unwrap_or_default());
+        entries.push((item_type, server_details, selector, path));
+    }
+    Ok(entries)
+}
+
+/// Escapes the tabs, line breaks and backslashes within a field of the manifest
+fn escape_field(field: &str) -> String {
+    field.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Reverses `escape_field`
+///
+/// # Returns
+/// The field if it is validly escaped. Nothing otherwise.
+fn unescape_field(field: &str) -> Option<String> {
+    let mut unescaped = String::new();
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue
+        }
+        unescaped.push(match chars.next()? {
+            '\\' => '\\',
+            't'  => '\t',
+            'n'  => '\n',
+            'r'  => '\r',
+            _    => return None,
+        });
+    }
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_fields_may_hold_tabs_and_line_breaks() {
+        let folder = std::env::temp_dir().join(format!("gopher-manifest-{}", std::process::id()));
+        let entries = vec![
+            (ItemType::Dir, String::from("gopher.test:70"), String::new(), String::from("gopher.test/70/.gophermap")),
+            (ItemType::Txt, String::from("gopher.test:70"), String::from("/a\nb\r\n"), String::from("gopher.test/70/a%0Ab%0D%0A")),
+            (ItemType::Bin, String::from("gopher.test:70"), String::from("c:\\\\tab\t\\n"), String::from("gopher.test/70/.bare/c%3A%5C%5Ctab%09%5Cn")),
+        ];
+
+        write_manifest(&folder, &entries).unwrap();
+        assert_eq!(fs::read_to_string(folder.join(MANIFEST)).unwrap().lines().count(), entries.len());
+        assert_eq!(read_manifest(&folder).unwrap(), entries);
+
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
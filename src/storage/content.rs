@@ -0,0 +1,121 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf}
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::gopher::response::ItemType;
+
+use super::{write_manifest, DuplicateGroup, ManifestEntry, Storage, StorageState};
+
+/// Folder holding the stored contents, named by their hash
+const OBJECTS_DIR: &str = "objects";
+
+/// Storage which keeps the contents of identical items once.
+///
+/// Contents are stored as `objects/<first 2 hex digits>/<remaining hex digits>`
+/// of their SHA-256 hash, and the manifest points every selector at the object
+/// holding its contents. The manifest has the same format as the one written by
+/// `Mirror`, so a content-addressed crawl can be served with `server::serve`.
+///
+/// * `folder`: Folder the objects and manifest are written to
+/// * `entries`: Every stored item in order (item type, server details, selector,
+///   object path) quadruples
+/// * `objects`: Size and items of every stored object, keyed by hash
+/// * `hashes`: Hashes in the order they were first stored
+pub struct ContentStorage {
+    folder: PathBuf,
+    entries: Vec<ManifestEntry>,
+    objects: HashMap<String, (u64, Vec<(String, String)>)>,
+    hashes: Vec<String>,
+}
+
+impl ContentStorage {
+    /// Constructs a new, empty `ContentStorage` writing to `folder`
+    pub fn new(folder: &Path) -> ContentStorage {
+        ContentStorage {
+            folder: folder.to_path_buf(),
+            entries: Vec::new(),
+            objects: HashMap::new(),
+            hashes: Vec::new(),
+        }
+    }
+
+    /// Returns the path (relative to the folder) of the object with a given hash
+    pub fn object_path(hash: &str) -> String {
+        format!("{}/{}/{}", OBJECTS_DIR, &hash[..2], &hash[2..])
+    }
+}
+
+/// Returns the hex encoded SHA-256 hash of the contents of an item
+pub fn content_hash(contents: &[u8]) -> String {
+    Sha256::digest(contents).iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+impl Storage for ContentStorage {
+    fn store(&mut self, item_type: ItemType, server_details: &str, selector: &str, contents: &[u8]) -> io::Result<()> {
+        let hash = content_hash(contents);
+        let object_path = ContentStorage::object_path(&hash);
+
+        // Only the first item with these contents is written
+        if !self.objects.contains_key(&hash) {
+            let path = self.folder.join(&object_path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            File::create(&path)?.write_all(contents)?;
+            self.hashes.push(hash.clone());
+        }
+
+        // Menus are only stored for snapshots, so only files count as duplicates
+        let (_, items) = self.objects.entry(hash).or_insert_with(|| (contents.len() as u64, Vec::new()));
+        if item_type != ItemType::Dir {
+            items.push((server_details.to_string(), selector.to_string()));
+        }
+
+        self.entries.push((item_type, server_details.to_string(), selector.to_string(), object_path));
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        write_manifest(&self.folder, &self.entries)
+    }
+
+    fn duplicates(&self) -> Option<Vec<DuplicateGroup>> {
+        Some(self.hashes.iter()
+            .filter_map(|hash| {
+                let (size, items) = &self.objects[hash];
+                (items.len() > 1).then(|| DuplicateGroup {
+                    hash: hash.clone(),
+                    size: *size,
+                    items: items.clone(),
+                })
+            })
+            .collect())
+    }
+
+    fn state(&self) -> Option<StorageState> {
+        Some(StorageState {
+            entries: self.entries.clone(),
+            objects: self.hashes.iter()
+                .map(|hash| {
+                    let (size, items) = &self.objects[hash];
+                    DuplicateGroup { hash: hash.clone(), size: *size, items: items.clone() }
+                })
+                .collect(),
+        })
+    }
+
+    /// Takes the stored items and objects of a checkpoint, so objects stored
+    /// before the crawl was interrupted are not written again
+    fn restore(&mut self, state: StorageState) {
+        self.entries = state.entries;
+        self.hashes = state.objects.iter().map(|object| object.hash.clone()).collect();
+        self.objects = state.objects.into_iter()
+            .map(|object| (object.hash, (object.size, object.items)))
+            .collect();
+    }
+}
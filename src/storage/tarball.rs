@@ -0,0 +1,87 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH}
+};
+
+use tar::{Builder, EntryType, Header};
+
+use crate::gopher::response::ItemType;
+use crate::mirror::{ancestors, collision_path, mirror_path};
+
+use super::{render_manifest, ManifestEntry, Storage};
+use crate::MANIFEST;
+
+/// Storage which mirrors items into a tar archive.
+///
+/// Items are laid out exactly like a `Mirror`, and the manifest is added as the
+/// last entry, so extracting the archive gives a folder which can be served with
+/// `server::serve`.
+///
+/// * `builder`: Tar archive being written
+/// * `entries`: Every stored item in order (item type, server details, selector,
+///   path within the archive) quadruples
+/// * `files`: Path of every file in the archive
+/// * `dirs`: Path of every directory implied by the files in the archive
+pub struct TarStorage {
+    builder: Builder<BufWriter<File>>,
+    entries: Vec<ManifestEntry>,
+    files: HashSet<String>,
+    dirs: HashSet<String>,
+}
+
+impl TarStorage {
+    /// Constructs a new `TarStorage`, creating (or truncating) the tar archive
+    pub fn create(path: &Path) -> io::Result<TarStorage> {
+        Ok(TarStorage {
+            builder: Builder::new(BufWriter::new(File::create(path)?)),
+            entries: Vec::new(),
+            files: HashSet::new(),
+            dirs: HashSet::new(),
+        })
+    }
+
+    /// Appends a regular file to the archive
+    fn append(&mut self, path: &str, contents: &[u8]) -> io::Result<()> {
+        let mtime = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or_default();
+
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Regular);
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        self.builder.append_data(&mut header, path, contents)
+    }
+
+    /// Returns true if `path` cannot be extracted because of a file or directory
+    /// already in the archive
+    fn is_blocked(&self, path: &str) -> bool {
+        self.dirs.contains(path) || ancestors(path).any(|ancestor| self.files.contains(ancestor))
+    }
+}
+
+impl Storage for TarStorage {
+    fn store(&mut self, item_type: ItemType, server_details: &str, selector: &str, contents: &[u8]) -> io::Result<()> {
+        let mut path = mirror_path(item_type, server_details, selector);
+        if self.is_blocked(&path) {
+            path = collision_path(item_type, server_details, selector);
+        }
+
+        self.append(&path, contents)?;
+        self.dirs.extend(ancestors(&path).map(String::from));
+        self.files.insert(path.clone());
+
+        self.entries.push((item_type, server_details.to_string(), selector.to_string(), path));
+        Ok(())
+    }
+
+    /// Adds the manifest and completes the archive
+    fn finish(&mut self) -> io::Result<()> {
+        let manifest = render_manifest(&self.entries);
+        self.append(MANIFEST, manifest.as_bytes())?;
+        self.builder.finish()?;
+        self.builder.get_mut().flush()
+    }
+}
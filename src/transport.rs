@@ -0,0 +1,162 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    net::{IpAddr, ToSocketAddrs}
+};
+
+use crate::gopher::{
+    self, 
+    request::Request, 
+    response::{Response, ResponseOutcome}
+};
+
+/// Represents a way of exchanging Gopher transactions with servers.
+/// 
+/// `Crawler` only talks to servers through a `Transport`, so the crawl logic
+/// can run against real sockets, canned responses or recorded sessions.
+pub trait Transport {
+    /// Attempts to send a `Request` to a Gopher server and receive its `Response`
+    /// 
+    /// # Returns
+    /// A `Response` from the server if sucessfull. Otherwise, returns the appropriate
+    /// IO error.
+    fn send_and_recv(&mut self, request: &Request) -> io::Result<Response>;
+
+    /// Attempts to connect to the provided Gopher server without sending a request.
+    /// 
+    /// # Arguments
+    /// * `server_details`: hostname:port of the server
+    /// 
+    /// # Returns
+    /// Nothing if the server accepted the connection. Returns an IO error otherwise.
+    fn connect(&mut self, server_details: &str) -> io::Result<()>;
+
+    /// Attempts to resolve the hostname of a Gopher server to its addresses, to
+    /// find hostnames referring to the same server. Transports which can not
+    /// resolve hostnames return an `io::ErrorKind::Unsupported` error.
+    /// 
+    /// # Arguments
+    /// * `server_details`: hostname:port of the server
+    /// 
+    /// # Returns
+    /// Every address of the server if sucessfull. Otherwise, returns an IO error.
+    fn resolve(&mut self, server_details: &str) -> io::Result<Vec<IpAddr>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Unable to resolve {server_details} with this transport")
+        ))
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn send_and_recv(&mut self, request: &Request) -> io::Result<Response> {
+        (**self).send_and_recv(request)
+    }
+
+    fn connect(&mut self, server_details: &str) -> io::Result<()> {
+        (**self).connect(server_details)
+    }
+
+    fn resolve(&mut self, server_details: &str) -> io::Result<Vec<IpAddr>> {
+        (**self).resolve(server_details)
+    }
+}
+
+/// Transport which talks to Gopher servers over TCP. This is the default 
+/// transport of a `Crawler`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TcpTransport;
+
+impl Transport for TcpTransport {
+    fn send_and_recv(&mut self, request: &Request) -> io::Result<Response> {
+        gopher::send_and_recv(request)
+    }
+
+    fn connect(&mut self, server_details: &str) -> io::Result<()> {
+        gopher::connect(server_details).map(|_| ())
+    }
+
+    fn resolve(&mut self, server_details: &str) -> io::Result<Vec<IpAddr>> {
+        Ok(server_details.to_socket_addrs()?.map(|socket_addr| socket_addr.ip()).collect())
+    }
+}
+
+/// Transport which answers requests with canned responses held in memory.
+/// 
+/// * `responses`: Responses keyed by (server details, selector) pairs
+/// * `servers`: Server details of every server that accepts connections
+/// * `addresses`: Address of every hostname which can be resolved
+/// 
+/// Requests without a canned response are answered with a 
+/// `ResponseOutcome::ConnectionFailed` response, as if the server was down.
+/// Only IP addresses and hostnames with an address resolve.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryTransport {
+    responses: HashMap<(String, String), Response>,
+    servers: HashSet<String>,
+    addresses: HashMap<String, IpAddr>,
+}
+
+impl MemoryTransport {
+    /// Constructs a new `MemoryTransport` without any responses
+    pub fn new() -> MemoryTransport {
+        MemoryTransport::default()
+    }
+
+    /// Adds a canned response. The server is also marked as accepting connections.
+    /// 
+    /// # Arguments
+    /// * `server_details`: hostname:port of the server
+    /// * `selector`: Selector the response answers
+    /// * `response`: Response as returned by `gopher::send_and_recv`, i.e. with 
+    ///   the last line of text and directory items removed
+    pub fn with_response(mut self, server_details: &str, selector: &str, response: Response) -> MemoryTransport {
+        self.servers.insert(server_details.to_string());
+        self.responses.insert((server_details.to_string(), selector.to_string()), response);
+        self
+    }
+
+    /// Marks a server as accepting connections
+    pub fn with_server(mut self, server_details: &str) -> MemoryTransport {
+        self.servers.insert(server_details.to_string());
+        self
+    }
+
+    /// Makes a hostname resolve to an address, on any port
+    pub fn with_address(mut self, server_name: &str, address: IpAddr) -> MemoryTransport {
+        self.addresses.insert(server_name.to_string(), address);
+        self
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send_and_recv(&mut self, request: &Request) -> io::Result<Response> {
+        let key = (request.server_details.to_string(), request.selector.to_string());
+        match self.responses.get(&key) {
+            Some(response) => Ok(response.clone()),
+            None => Ok(Response::new(Vec::new(), ResponseOutcome::ConnectionFailed)),
+        }
+    }
+
+    fn connect(&mut self, server_details: &str) -> io::Result<()> {
+        if self.servers.contains(server_details) {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                "Unable to connect to provided hostname and port"
+            ))
+        }
+    }
+
+    fn resolve(&mut self, server_details: &str) -> io::Result<Vec<IpAddr>> {
+        let server_name = server_details.rsplit_once(':').map_or(server_details, |(server_name, _)| server_name);
+        match (server_name.parse(), self.addresses.get(server_name)) {
+            (Ok(address), _) | (Err(_), Some(&address)) => Ok(vec![address]),
+            _ => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Unable to resolve {server_name}")
+            )),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Treats equivalent references to an item as the same item, so it is crawled once.
+
+use std::net::{IpAddr, Ipv4Addr};
+
+use gopher::{
+    crawler::{
+        canonical::{CanonicalRules, MergedVariant, PathRules},
+        item::ItemOutcome,
+        report::CrawlReport,
+        Crawler
+    },
+    gopher::{
+        menu::MenuItem,
+        response::ItemType
+    },
+    storage::DiscardStorage,
+    transport::MemoryTransport
+};
+
+mod common;
+use common::{builder, complete, menu_response, ROOT};
+
+/// The root menu links to /docs twice, once on the same host spelled in
+/// uppercase, and to its text file with and without a trailing slash
+fn transport() -> MemoryTransport {
+    let root = builder()
+        .dir("Docs", "/docs")
+        .item(MenuItem::new(ItemType::Dir, "Docs again", "/docs", "Gopher.Test", 70))
+        .text("Readme", "/docs/readme")
+        .text("Readme again", "/docs/readme/")
+        .build();
+    let docs = builder()
+        .info("Documentation")
+        .build();
+    MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/docs", menu_response(docs))
+        .with_response(ROOT, "/docs/readme", complete(b"readme\r\n"))
+        .with_response(ROOT, "/docs/readme/", complete(b"readme\r\n"))
+}
+
+fn crawl(transport: MemoryTransport, rules: CanonicalRules) -> Crawler {
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(DiscardStorage)
+        .with_canonicalization(rules);
+    crawler.start_crawl().unwrap();
+    crawler
+}
+
+fn variant(server_details: &str, selector: &str, canonical_details: &str, canonical_selector: &str) -> MergedVariant {
+    MergedVariant {
+        server_details: String::from(server_details),
+        selector: String::from(selector),
+        canonical_details: String::from(canonical_details),
+        canonical_selector: String::from(canonical_selector),
+    }
+}
+
+#[test]
+fn hostnames_are_case_folded_by_default() {
+    let crawler = crawl(transport(), CanonicalRules::default());
+    let report = crawler.crawl_report();
+
+    // Not an external server, and only listed once
+    assert!(report.external_servers.is_empty());
+    assert!(crawler.items().get("Gopher.Test:70", "/docs").is_none());
+    assert_eq!(crawler.items().get(ROOT, "/docs").unwrap().referrers.len(), 2);
+
+    assert_eq!(report.merged_variants, vec![variant("Gopher.Test:70", "/docs", ROOT, "/docs")]);
+    let rendered = report.render();
+    assert!(rendered.contains("\tMerged variants: 1\n\t\tGopher.Test:70: /docs merged into gopher.test:70: /docs\n\n"), "{rendered}");
+}
+
+#[test]
+fn canonicalization_can_be_turned_off() {
+    let crawler = crawl(transport(), "none".parse().unwrap());
+    let report = crawler.crawl_report();
+
+    assert_eq!(crawler.items().get("Gopher.Test:70", "/docs").unwrap().outcome, ItemOutcome::External(false));
+    assert_eq!(report.external_servers.len(), 1);
+    assert!(report.merged_variants.is_empty());
+    assert!(!report.render().contains("Merged variants"));
+}
+
+#[test]
+fn trailing_slashes_are_ignored_if_enabled() {
+    let crawler = crawl(transport(), "host-case,trailing-slash".parse().unwrap());
+    let report = crawler.crawl_report();
+
+    assert_eq!(report.txt_file_count, 1);
+    assert!(crawler.items().get(ROOT, "/docs/readme/").is_none());
+    assert!(report.merged_variants.contains(&variant(ROOT, "/docs/readme/", ROOT, "/docs/readme")));
+
+    // Without the rule, both are downloaded
+    assert_eq!(crawl(transport(), CanonicalRules::default()).crawl_report().txt_file_count, 2);
+}
+
+#[test]
+fn the_slash_selector_is_not_the_root_menu() {
+    let root = builder()
+        .dir("Slash", "/")
+        .dir("Slashes", "//")
+        .build();
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/", menu_response(builder().info("Slash").build()));
+
+    let crawler = crawl(transport, "host-case,trailing-slash".parse().unwrap());
+    let report = crawler.crawl_report();
+
+    assert_eq!(report.dir_count, 2);
+    assert_eq!(crawler.items().get(ROOT, "/").unwrap().depth, 1);
+    assert_eq!(report.merged_variants, vec![variant(ROOT, "//", ROOT, "/")]);
+}
+
+#[test]
+fn hostnames_resolving_to_the_same_address_are_the_same_server() {
+    let address = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    let root = builder()
+        .item(MenuItem::new(ItemType::Txt, "By address", "/about.txt", "192.0.2.1", 70))
+        .item(MenuItem::new(ItemType::Dir, "By alias", "/about", "www.gopher.test", 70))
+        .build();
+    let transport = MemoryTransport::new()
+        .with_address("gopher.test", address)
+        .with_address("www.gopher.test", address)
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/about", menu_response(builder().info("About").build()))
+        .with_response(ROOT, "/about.txt", complete(b"about\r\n"));
+
+    let crawler = crawl(transport, "resolve-hosts".parse().unwrap());
+    let report = crawler.crawl_report();
+
+    assert!(report.external_servers.is_empty());
+    assert_eq!((report.dir_count, report.txt_file_count), (2, 1));
+    assert_eq!(report.merged_variants, vec![
+        variant("192.0.2.1:70", "/about.txt", ROOT, "/about.txt"),
+        variant("www.gopher.test:70", "/about", ROOT, "/about"),
+    ]);
+}
+
+#[test]
+fn selectors_follow_the_path_rules_of_caps_txt() {
+    let root = builder()
+        .text("Notes", ":notes:today")
+        .text("Notes again", ":notes:.:old:^:today")
+        .item(MenuItem::new(ItemType::Txt, "Elsewhere", ":notes:today", "elsewhere.test", 70))
+        .item(MenuItem::new(ItemType::Txt, "Elsewhere again", ":notes:.:old:^:today", "elsewhere.test", 70))
+        .build();
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "caps.txt", complete(b"CAPS\r\n\r\n# Paths\r\nPathDelimeter=:\r\nPathIdentity=.\r\nPathParent=^\r\n"))
+        .with_response(ROOT, ":notes:today", complete(b"today\r\n"));
+
+    let crawler = crawl(transport, "caps-paths".parse().unwrap());
+    let report = crawler.crawl_report();
+
+    // caps.txt describes the server, it is not an item
+    assert!(crawler.items().get(ROOT, "caps.txt").is_none());
+    assert_eq!(report.txt_file_count, 1);
+    // The path rules of the root server do not apply to other servers
+    assert!(crawler.items().get("elsewhere.test:70", ":notes:.:old:^:today").is_some());
+    assert_eq!(report.merged_variants, vec![variant(ROOT, ":notes:.:old:^:today", ROOT, ":notes:today")]);
+}
+
+#[test]
+fn path_rules_default_to_slashes() {
+    let rules = PathRules::parse("CAPS\n");
+    assert_eq!(rules, PathRules::default());
+    assert_eq!(rules.normalise("/a//./b/../c/"), "/a/c/");
+    assert_eq!(rules.normalise("/../.."), "/");
+    assert_eq!(rules.normalise("URL:http://example.test/../"), "URL:http://example.test/../");
+
+    let double = PathRules::parse("PathParentDouble=TRUE");
+    assert_eq!(double.normalise("/a/b//c"), "/a/c");
+}
+
+#[test]
+fn unknown_rules_are_rejected() {
+    assert_eq!(
+        "host-case,lowercase".parse::<CanonicalRules>(),
+        Err(String::from("Unknown canonicalization rule: lowercase"))
+    );
+}
+
+#[test]
+fn reports_without_merged_variants_still_parse() {
+    let mut json = serde_json::to_value(crawl(transport(), CanonicalRules::default()).crawl_report()).unwrap();
+    json.as_object_mut().unwrap().remove("merged_variants");
+
+    let report: CrawlReport = serde_json::from_value(json).unwrap();
+    assert!(report.merged_variants.is_empty());
+}
@@ -0,0 +1,197 @@
+//! Interrupts crawls part way through and resumes them from their last checkpoint.
+
+use std::{fs, io, path::PathBuf, time::Duration};
+
+use gopher::{
+    crawler::Crawler,
+    gopher::{
+        menu::MenuItem,
+        request::Request,
+        response::{ItemType, Response}
+    },
+    storage::{content::ContentStorage, DiscardStorage},
+    transport::{MemoryTransport, Transport},
+    MANIFEST
+};
+
+mod common;
+use common::{builder, complete, menu_response, temp_file, temp_folder, ROOT};
+
+fn state_file(name: &str) -> PathBuf {
+    temp_file(name, "checkpoint")
+}
+
+fn transport() -> MemoryTransport {
+    let root = builder()
+        .info("Welcome")
+        .dir("A", "/resume/a")
+        .text("Root text", "/resume/root.txt")
+        .error("Broken link")
+        .dir("B", "/resume/b")
+        .item(MenuItem::new(ItemType::Dir, "Elsewhere", "/", "elsewhere.test", 70))
+        .build();
+    let a = builder()
+        .text("Same size", "/resume/a.txt")
+        .binary("Image", "/resume/a.bin")
+        .dir("B", "/resume/b")
+        .build();
+    let b = builder()
+        .text("Same size", "/resume/b.txt")
+        .error("Broken link")
+        .binary("Gone", "/resume/gone.bin")
+        .build();
+
+    MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/resume/a", menu_response(a))
+        .with_response(ROOT, "/resume/b", menu_response(b))
+        .with_response(ROOT, "/resume/root.txt", complete(b"root text\r\n"))
+        .with_response(ROOT, "/resume/a.txt", complete(b"a\r\n"))
+        .with_response(ROOT, "/resume/b.txt", complete(b"b\r\n"))
+        .with_response(ROOT, "/resume/a.bin", complete(&[1; 10]))
+}
+
+/// Transport which fails every request after the first `remaining`, as if the
+/// crawl was killed
+struct KilledTransport {
+    inner: MemoryTransport,
+    remaining: usize,
+}
+
+impl Transport for KilledTransport {
+    fn send_and_recv(&mut self, request: &Request) -> io::Result<Response> {
+        if self.remaining == 0 {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "killed"));
+        }
+        self.remaining -= 1;
+        self.inner.send_and_recv(request)
+    }
+
+    fn connect(&mut self, server_details: &str) -> io::Result<()> {
+        self.inner.connect(server_details)
+    }
+}
+
+fn uninterrupted_report() -> String {
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport())
+        .with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+    crawler.render_report()
+}
+
+#[test]
+fn resumed_crawls_match_uninterrupted_crawls() {
+    let expected = uninterrupted_report();
+
+    // Kill the crawl after every possible number of requests
+    for requests in 0..8 {
+        let path = state_file(&format!("killed-{requests}"));
+
+        let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+            .with_transport(KilledTransport { inner: transport(), remaining: requests })
+            .with_storage(DiscardStorage)
+            .with_checkpoint(&path, Duration::ZERO);
+        assert!(crawler.start_crawl().is_err(), "crawl with {requests} requests was not killed");
+
+        let mut resumed = Crawler::resume(&path).unwrap()
+            .with_transport(transport())
+            .with_storage(DiscardStorage);
+        resumed.start_crawl().unwrap();
+        assert_eq!(resumed.render_report(), expected, "crawl killed after {requests} requests");
+
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn completed_crawls_resume_to_the_same_report() {
+    let path = state_file("completed");
+
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport())
+        .with_storage(DiscardStorage)
+        .with_checkpoint(&path, Duration::from_secs(3600));
+    crawler.start_crawl().unwrap();
+
+    // Nothing is left in the frontier, so no request is sent
+    let mut resumed = Crawler::resume(&path).unwrap()
+        .with_transport(MemoryTransport::new())
+        .with_storage(DiscardStorage);
+    resumed.start_crawl().unwrap();
+    assert_eq!(resumed.render_report(), crawler.render_report());
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn resumed_crawls_keep_their_settings() {
+    let settings = |crawler: Crawler| crawler.with_top_n(1).with_trap_threshold(1);
+    let mut expected = settings(Crawler::new(Some(String::from("gopher.test")), Some(70)))
+        .with_transport(transport())
+        .with_storage(DiscardStorage);
+    expected.start_crawl().unwrap();
+    assert_ne!(expected.render_report(), uninterrupted_report());
+
+    // A threshold of one cuts off both nested menus, so the crawl only sends two requests
+    for requests in 0..2 {
+        let path = state_file(&format!("settings-{requests}"));
+
+        let mut crawler = settings(Crawler::new(Some(String::from("gopher.test")), Some(70)))
+            .with_transport(KilledTransport { inner: transport(), remaining: requests })
+            .with_storage(DiscardStorage)
+            .with_checkpoint(&path, Duration::ZERO);
+        assert!(crawler.start_crawl().is_err(), "crawl with {requests} requests was not killed");
+
+        let mut resumed = Crawler::resume(&path).unwrap()
+            .with_transport(transport())
+            .with_storage(DiscardStorage);
+        resumed.start_crawl().unwrap();
+        assert_eq!(resumed.render_report(), expected.render_report(), "crawl killed after {requests} requests");
+
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn resumed_crawls_keep_the_items_stored_before() {
+    // /resume/b.txt has the same contents as /resume/a.txt
+    let duplicated = || transport().with_response(ROOT, "/resume/b.txt", complete(b"a\r\n"));
+    let content_crawl = |folder: &PathBuf| {
+        let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+            .with_transport(duplicated())
+            .with_storage(ContentStorage::new(folder));
+        crawler.start_crawl().unwrap();
+        crawler.save_snapshot().unwrap();
+        crawler
+    };
+
+    let expected_folder = temp_folder("resume-content");
+    let expected = content_crawl(&expected_folder);
+    let expected_manifest = fs::read_to_string(expected_folder.join(MANIFEST)).unwrap();
+    assert_eq!(expected.duplicates().unwrap().len(), 1);
+
+    for requests in 1..8 {
+        let path = state_file(&format!("content-{requests}"));
+        let folder = temp_folder(&format!("resume-content-{requests}"));
+
+        let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+            .with_transport(KilledTransport { inner: duplicated(), remaining: requests })
+            .with_storage(ContentStorage::new(&folder))
+            .with_checkpoint(&path, Duration::ZERO);
+        assert!(crawler.start_crawl().is_err(), "crawl with {requests} requests was not killed");
+
+        let mut resumed = Crawler::resume(&path).unwrap()
+            .with_transport(duplicated())
+            .with_storage(ContentStorage::new(&folder));
+        resumed.start_crawl().unwrap();
+        resumed.save_snapshot().unwrap();
+
+        assert_eq!(resumed.duplicates(), expected.duplicates(), "crawl killed after {requests} requests");
+        assert_eq!(fs::read_to_string(folder.join(MANIFEST)).unwrap(), expected_manifest, "crawl killed after {requests} requests");
+
+        fs::remove_file(path).unwrap();
+        fs::remove_dir_all(folder).unwrap();
+    }
+    fs::remove_dir_all(expected_folder).unwrap();
+}
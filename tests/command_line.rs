@@ -0,0 +1,85 @@
+//! Runs the gopher command on recorded sessions, so no network access is needed.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output}
+};
+
+use gopher::{
+    crawler::Crawler,
+    session::RecordingTransport,
+    storage::DiscardStorage,
+    transport::MemoryTransport
+};
+
+mod common;
+use common::{builder, complete, menu_response, temp_file, temp_folder, ROOT};
+
+/// Records a crawl of a server with a single text file into a session file
+fn record_session(name: &str) -> PathBuf {
+    let path = temp_file(name, "session");
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(builder().text("Notes", "/notes.txt").build()))
+        .with_response(ROOT, "/notes.txt", complete(b"notes\r\n"));
+
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(RecordingTransport::create(transport, &path).unwrap())
+        .with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+    path
+}
+
+fn gopher(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gopher")).args(args).output().unwrap()
+}
+
+fn path_str(path: &Path) -> &str {
+    path.to_str().unwrap()
+}
+
+#[test]
+fn output_folders_created_by_the_crawl_are_removed() {
+    let session = record_session("created-output");
+    let folder = temp_folder("created-output");
+
+    let output = gopher(&["replay", path_str(&session), "-o", path_str(&folder)]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(!folder.exists());
+
+    fs::remove_file(session).unwrap();
+}
+
+#[test]
+fn existing_output_folders_survive_a_crawl() {
+    let session = record_session("existing-output");
+    let folder = temp_folder("existing-output");
+    fs::create_dir_all(&folder).unwrap();
+    fs::write(folder.join("keep.txt"), b"keep me").unwrap();
+
+    let output = gopher(&["replay", path_str(&session), "-o", path_str(&folder)]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read(folder.join("keep.txt")).unwrap(), b"keep me");
+    assert!(folder.join("gopher.test/70/notes.txt").exists());
+
+    fs::remove_dir_all(folder).unwrap();
+    fs::remove_file(session).unwrap();
+}
+
+#[test]
+fn storages_which_can_not_be_resumed_are_rejected() {
+    for storage in ["tar", "memory"] {
+        let output = gopher(&["--resume", "missing.checkpoint", "-s", storage]);
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("-s tar and -s memory can not be used with --resume"));
+    }
+}
+
+#[test]
+fn settings_of_the_interrupted_crawl_can_not_be_changed_on_resume() {
+    for flag in [["--top", "3"], ["--trap-threshold", "3"], ["--canonicalize", "host-case"]] {
+        let output = gopher(&["--resume", "missing.checkpoint", flag[0], flag[1]]);
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("can not be used with --resume"));
+    }
+}
@@ -0,0 +1,42 @@
+//! Fixtures shared by the integration tests: a test server and the responses it serves.
+#![allow(dead_code)]
+
+use std::{env, fs, path::PathBuf};
+
+use gopher::gopher::{
+    menu::{Menu, MenuBuilder},
+    response::{Response, ResponseOutcome}
+};
+
+/// Server details of the server every test crawls
+pub const ROOT: &str = "gopher.test:70";
+
+/// A completed directory response, with the last line removed like `gopher::recv` does
+pub fn menu_response(menu: Menu) -> Response {
+    let serialized = menu.serialize().unwrap();
+    Response::new(serialized.strip_suffix(".\r\n").unwrap().as_bytes().to_vec(), ResponseOutcome::Complete)
+}
+
+/// A completed file response
+pub fn complete(buffer: &[u8]) -> Response {
+    Response::new(buffer.to_vec(), ResponseOutcome::Complete)
+}
+
+/// Builds a menu served by the root server
+pub fn builder() -> MenuBuilder {
+    Menu::builder("gopher.test", 70)
+}
+
+/// Returns a path in the temporary folder unique to this test run, e.g. for a
+/// session or checkpoint file
+pub fn temp_file(name: &str, extension: &str) -> PathBuf {
+    env::temp_dir().join(format!("gopher-{}-{}.{}", name, std::process::id(), extension))
+}
+
+/// Returns an empty folder in the temporary folder unique to this test run,
+/// removing whatever an earlier run left there
+pub fn temp_folder(name: &str) -> PathBuf {
+    let folder = env::temp_dir().join(format!("gopher-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&folder);
+    folder
+}
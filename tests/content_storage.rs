@@ -0,0 +1,69 @@
+//! Crawls into content-addressed storage, where identical files are kept once.
+
+use std::fs;
+
+use gopher::{
+    crawler::Crawler,
+    gopher::{
+        menu::MenuItem,
+        response::ItemType
+    },
+    server::Snapshot,
+    storage::content::ContentStorage,
+    transport::MemoryTransport,
+    MANIFEST
+};
+
+mod common;
+use common::{builder, complete, menu_response, temp_folder, ROOT};
+
+#[test]
+fn identical_files_are_stored_once() {
+    let folder = temp_folder("content");
+    fs::create_dir_all(&folder).unwrap();
+
+    let image = (0..=255).cycle().take(1000).collect::<Vec<u8>>();
+    let root = builder()
+        .binary("Image", "/content/image.bin")
+        .binary("Same image", "/content/copy.bin")
+        .item(MenuItem::new(ItemType::Bin, "Mirrored image", "/image.bin", "mirror.test", 70))
+        .text("Notes", "/content/notes.txt")
+        .build();
+
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/content/image.bin", complete(&image))
+        .with_response(ROOT, "/content/copy.bin", complete(&image))
+        .with_response("mirror.test:70", "/image.bin", complete(&image))
+        .with_response(ROOT, "/content/notes.txt", complete(b"notes\r\n"));
+
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(ContentStorage::new(&folder));
+    crawler.start_crawl().unwrap();
+
+    let duplicates = crawler.duplicates().unwrap();
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!(duplicates[0].size, 1000);
+    assert_eq!(duplicates[0].items.len(), 3);
+    assert_eq!(duplicates[0].bytes_saved(), 2000);
+
+    let report = crawler.render_report();
+    assert!(report.contains("Duplicate file groups: 1\n\t\tBytes saved: 2000"));
+
+    // Two objects for the files and one for the root menu
+    crawler.save_snapshot().unwrap();
+    let objects = fs::read_dir(folder.join("objects")).unwrap()
+        .flat_map(|dir| fs::read_dir(dir.unwrap().path()).unwrap())
+        .count();
+    assert_eq!(objects, 3);
+
+    // Every selector points at its object, so the crawl can still be served
+    let manifest = fs::read_to_string(folder.join(MANIFEST)).unwrap();
+    assert_eq!(manifest.lines().count(), 5);
+    let snapshot = Snapshot::load(&folder, "localhost", 7070).unwrap();
+    assert_eq!(snapshot.respond("/content/copy.bin").unwrap(), image);
+    assert_eq!(snapshot.respond("/mirror.test:70/image.bin").unwrap(), image);
+
+    fs::remove_dir_all(folder).unwrap();
+}
@@ -0,0 +1,150 @@
+//! Compares two crawls of a server which changed in between.
+
+use std::{fs, time::Duration};
+
+use gopher::{
+    crawler::{
+        diff::{ChangedFile, CrawlDiff, DiffItem, DiffReference, ExternalServerChange},
+        Crawler
+    },
+    gopher::{
+        menu::MenuItem,
+        response::{ItemType, Response, ResponseOutcome}
+    },
+    storage::DiscardStorage,
+    transport::MemoryTransport
+};
+
+mod common;
+use common::{builder, complete, menu_response, temp_file, ROOT};
+
+fn before() -> MemoryTransport {
+    MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(builder()
+            .dir("Old", "/diff/old")
+            .text("Same", "/diff/same.txt")
+            .text("Grows", "/diff/grows.txt")
+            .binary("Edited", "/diff/edited.bin")
+            .text("Broken", "/diff/broken.txt")
+            .item(MenuItem::new(ItemType::Dir, "Up", "/", "up.test", 70))
+            .item(MenuItem::new(ItemType::Dir, "Down", "/", "down.test", 70))
+            .build()))
+        .with_response(ROOT, "/diff/old", menu_response(builder().text("Old text", "/diff/old.txt").build()))
+        .with_response(ROOT, "/diff/old.txt", complete(b"old\r\n"))
+        .with_response(ROOT, "/diff/same.txt", complete(b"same\r\n"))
+        .with_response(ROOT, "/diff/grows.txt", complete(b"short\r\n"))
+        .with_response(ROOT, "/diff/edited.bin", complete(&[1; 8]))
+        .with_server("up.test:70")
+}
+
+fn after() -> MemoryTransport {
+    MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(builder()
+            .text("Same", "/diff/same.txt")
+            .text("Grows", "/diff/grows.txt")
+            .binary("Edited", "/diff/edited.bin")
+            .text("Broken", "/diff/broken.txt")
+            .binary("New", "/diff/new.bin")
+            .item(MenuItem::new(ItemType::Dir, "Up", "/", "up.test", 70))
+            .item(MenuItem::new(ItemType::Dir, "Down", "/", "down.test", 70))
+            .item(MenuItem::new(ItemType::Dir, "Elsewhere", "/", "elsewhere.test", 70))
+            .build()))
+        .with_response(ROOT, "/diff/same.txt", complete(b"same\r\n"))
+        .with_response(ROOT, "/diff/grows.txt", complete(b"much longer\r\n"))
+        .with_response(ROOT, "/diff/edited.bin", complete(&[2; 8]))
+        .with_response(ROOT, "/diff/broken.txt", complete(b"fixed\r\n"))
+        .with_response(ROOT, "/diff/new.bin", Response::new(Vec::new(), ResponseOutcome::Timeout))
+        .with_server("down.test:70")
+        .with_server("elsewhere.test:70")
+}
+
+fn crawl(transport: MemoryTransport) -> Crawler {
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+    crawler
+}
+
+fn item(selector: &str) -> DiffItem {
+    DiffItem { server_details: String::from(ROOT), selector: String::from(selector) }
+}
+
+fn external(server_name: &str, before: Option<bool>, after: Option<bool>) -> ExternalServerChange {
+    ExternalServerChange { server_name: String::from(server_name), server_port: 70, before, after }
+}
+
+#[test]
+fn diffs_report_every_kind_of_change() {
+    let diff = CrawlDiff::between(&crawl(before()), &crawl(after()));
+
+    assert_eq!(diff.added_dirs, vec![]);
+    assert_eq!(diff.removed_dirs, vec![item("/diff/old")]);
+    assert_eq!(diff.added_txt_files, vec![item("/diff/broken.txt")]);
+    assert_eq!(diff.removed_txt_files, vec![item("/diff/old.txt")]);
+    assert_eq!(diff.added_bin_files, vec![]);
+    assert_eq!(diff.removed_bin_files, vec![]);
+
+    let changed = diff.changed_files.iter()
+        .map(|file| (file.item_type, file.selector.as_str(), file.old_size, file.new_size))
+        .collect::<Vec<_>>();
+    assert_eq!(changed, vec![(ItemType::Txt, "/diff/grows.txt", 7, 13), (ItemType::Bin, "/diff/edited.bin", 8, 8)]);
+    let ChangedFile { old_hash, new_hash, .. } = &diff.changed_files[1];
+    assert_ne!(old_hash, new_hash);
+
+    assert_eq!(diff.new_invalid_references, vec![
+        DiffReference { details: format!("{ROOT} /diff/new.bin"), outcome: ResponseOutcome::Timeout }
+    ]);
+    assert_eq!(diff.fixed_invalid_references, vec![
+        DiffReference { details: format!("{ROOT} /diff/broken.txt"), outcome: ResponseOutcome::ConnectionFailed }
+    ]);
+    assert_eq!(diff.external_servers, vec![
+        external("down.test", Some(false), Some(true)),
+        external("elsewhere.test", None, Some(true)),
+        external("up.test", Some(true), Some(false)),
+    ]);
+
+    let rendered = diff.render();
+    assert!(rendered.contains("\tRemoved Gopher directories: 1\n\t\tgopher.test:70: /diff/old\n\n"), "{rendered}");
+    assert!(rendered.contains("\t\tgopher.test:70: /diff/grows.txt (7 -> 13 bytes, contents changed)"), "{rendered}");
+    assert!(rendered.contains("\t\tdown.test:70 now connects"), "{rendered}");
+    assert!(rendered.contains("\t\telsewhere.test:70 appeared, connected successfully"), "{rendered}");
+    assert!(rendered.contains("\t\tup.test:70 no longer connects"), "{rendered}");
+}
+
+#[test]
+fn identical_crawls_have_an_empty_diff() {
+    let diff = CrawlDiff::between(&crawl(before()), &crawl(before()));
+    assert!(diff.is_empty());
+    assert!(diff.render().contains("\tChanged files: 0\n\n"));
+}
+
+#[test]
+fn diffs_of_saved_crawls_serialize_to_json() {
+    let paths = ["before", "after"]
+        .map(|name| temp_file(&format!("diff-{name}"), "checkpoint"));
+
+    for (path, transport) in paths.iter().zip([before(), after()]) {
+        let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+            .with_transport(transport)
+            .with_storage(DiscardStorage)
+            .with_checkpoint(path, Duration::from_secs(3600));
+        crawler.start_crawl().unwrap();
+    }
+
+    let old = Crawler::resume(&paths[0]).unwrap();
+    let new = Crawler::resume(&paths[1]).unwrap();
+    assert!(old.is_complete() && new.is_complete());
+
+    let diff = CrawlDiff::between(&old, &new);
+    assert_eq!(diff, CrawlDiff::between(&crawl(before()), &crawl(after())));
+
+    let json: serde_json::Value = serde_json::to_value(&diff).unwrap();
+    assert_eq!(json["removed_dirs"][0]["selector"], "/diff/old");
+    assert_eq!(json["changed_files"][0]["new_size"], 13);
+    assert_eq!(json["external_servers"][1]["before"], serde_json::Value::Null);
+
+    for path in paths {
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+//! Crawls local misbehaving Gopher servers described by scenario files and checks
+//! the resulting crawl report.
+
+use std::path::Path;
+
+use gopher::{
+    crawler::Crawler,
+    server::scenario::{Scenario, ScenarioError, ScenarioServer},
+    storage::DiscardStorage
+};
+
+/// Crawls the server and returns the rendered report
+fn crawl(server: &ScenarioServer) -> String {
+    let mut crawler = Crawler::new(Some(server.server_name.clone()), Some(server.server_port))
+        .with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+    crawler.render_report()
+}
+
+#[test]
+fn misbehaving_server_report() {
+    let scenario = Scenario::load(Path::new("tests/scenarios/misbehaving.scenario")).unwrap();
+    let server = scenario.spawn().unwrap();
+    let host = format!("{}:{}", server.server_name, server.server_port);
+
+    let report = crawl(&server);
+
+    // Well-behaved items
+    assert!(report.contains("Number of Gopher directories: 2\n"), "{report}");
+    assert!(report.contains(&format!("{host}: /nested (Nested)")), "{report}");
+    assert!(report.contains("Number of simple text files: 2\n"), "{report}");
+    assert!(report.contains(&format!("{host}: /fine (Fine text)")), "{report}");
+    assert!(report.contains(&format!("{host}: /delayed (Delayed text)")), "{report}");
+    assert!(report.contains("Number of binary files: 2\n"), "{report}");
+    assert!(report.contains(&format!("Size of the largest binary file: 1024 bytes\n\t\t{host}: /fine.bin")), "{report}");
+    assert!(report.contains(&format!("Size of the smallest binary file: 0 bytes\n\t\t{host}: /closed")), "{report}");
+
+    // Misbehaving items
+    assert!(report.contains(&format!("File too long {host} /drip")), "{report}");
+    assert!(report.contains(&format!("File too long {host} /endless")), "{report}");
+    assert!(report.contains(&format!("Connection timed out {host} /silent")), "{report}");
+    assert!(report.contains(&format!("Missing end-line {host} /unterminated")), "{report}");
+    assert!(report.contains(&format!("Missing end-line {host} /lf")), "{report}");
+    assert!(report.contains(&format!("Connection reset {host} /reset")), "{report}");
+    assert!(report.contains(&format!("{}:{} did not connect", server.server_name, server.refused_port)), "{report}");
+
+    // Malformed menu lines
+    assert!(report.contains("The number of unique invalid references (error types): 1\n"), "{report}");
+    assert!(report.contains(&format!("\"Deliberate error\" in {host}:  (line 13)")), "{report}");
+    assert!(report.contains(&format!("Malformed response line :{} /nohost", server.server_port)), "{report}");
+    assert!(report.contains(&format!("Malformed response line {}:seventy /badport", server.server_name)), "{report}");
+    assert!(report.contains("Malformed response line Just some words"), "{report}");
+}
+
+#[test]
+fn nested_menus_are_crawled_once() {
+    let scenario = Scenario::parse(
+        "==\n\
+         menu\n\
+         1Loop\\t/loop\\t{host}\\t{port}\n\
+         == /loop\n\
+         menu\n\
+         1Root\\t\\t{host}\\t{port}\n\
+         1Loop\\t/loop\\t{host}\\t{port}\n\
+         0Text\\t/loop.txt\\t{host}\\t{port}\n\
+         == /loop.txt\n\
+         text\n\
+         Round and round\n"
+    ).unwrap();
+    let server = scenario.spawn().unwrap();
+
+    let report = crawl(&server);
+
+    assert!(report.contains("Number of Gopher directories: 2\n"), "{report}");
+    assert!(report.contains("Number of simple text files: 1\n"), "{report}");
+    assert!(report.contains("Contents: Round and round\r\n"), "{report}");
+}
+
+#[test]
+fn invalid_scenarios_are_rejected() {
+    assert!(matches!(
+        Scenario::parse("== /a\nteleport\n"),
+        Err(ScenarioError::InvalidDirective(2, _))
+    ));
+    assert!(matches!(
+        Scenario::parse("== /a\ntext drip=fast\n"),
+        Err(ScenarioError::InvalidDirective(2, _))
+    ));
+    assert!(matches!(
+        Scenario::parse("== /a"),
+        Err(ScenarioError::MissingDirective(_))
+    ));
+}
@@ -0,0 +1,117 @@
+//! Queries the items discovered by a crawl, from which its statistics are derived.
+
+use gopher::{
+    crawler::{
+        item::{CrawledItem, CrawledItems, ItemOutcome, Referrer},
+        Crawler
+    },
+    gopher::response::{ItemType, ResponseOutcome},
+    storage::DiscardStorage,
+    transport::MemoryTransport
+};
+
+mod common;
+use common::{builder, complete, menu_response, ROOT};
+
+fn crawl() -> Crawler {
+    let root = builder()
+        .dir("Docs", "/items/docs")
+        .text("First", "/items/first.txt")
+        .text("Second", "/items/second.txt")
+        .text("Long", "/items/long.txt")
+        .binary("Image", "/items/image.bin")
+        .text("Missing", "/items/missing.txt")
+        .build();
+    let docs = builder()
+        .dir("Home", "")
+        .text("First again", "/items/first.txt")
+        .binary("Small image", "/items/small.bin")
+        .build();
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/items/docs", menu_response(docs))
+        .with_response(ROOT, "/items/first.txt", complete(b"same\r\n"))
+        .with_response(ROOT, "/items/second.txt", complete(b"size\r\n"))
+        .with_response(ROOT, "/items/long.txt", complete(b"a much longer file\r\n"))
+        .with_response(ROOT, "/items/image.bin", complete(&[0; 64]))
+        .with_response(ROOT, "/items/small.bin", complete(&[0; 8]));
+
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+    crawler
+}
+
+fn selectors<'a>(items: impl IntoIterator<Item = &'a CrawledItem>) -> Vec<&'a str> {
+    items.into_iter().map(|item| item.selector.as_str()).collect()
+}
+
+#[test]
+fn items_are_looked_up_by_server_and_selector() {
+    let crawler = crawl();
+    let items = crawler.items();
+
+    let first = items.get(ROOT, "/items/first.txt").unwrap();
+    assert_eq!(first.display_string, "First again");
+    assert_eq!(items.position(ROOT, "/items/first.txt"), Some(2));
+    assert!(items.get(ROOT, "/items/unknown.txt").is_none());
+    assert!(items.get("elsewhere.test:70", "/items/first.txt").is_none());
+
+    let missing = items.get(ROOT, "/items/missing.txt").unwrap();
+    assert_eq!(missing.outcome, ItemOutcome::Fetched(ResponseOutcome::ConnectionFailed));
+    assert!(!missing.is_received() && missing.is_requested());
+}
+
+#[test]
+fn items_keep_every_menu_linking_to_them() {
+    let crawler = crawl();
+    let items = crawler.items();
+
+    let first = items.get(ROOT, "/items/first.txt").unwrap();
+    let referrer = |selector: &str, line: usize, display_string: &str| Referrer {
+        server_details: String::from(ROOT),
+        selector: String::from(selector),
+        line,
+        display_string: String::from(display_string),
+    };
+    assert_eq!(first.referrers, vec![referrer("/items/docs", 2, "First again"), referrer("", 2, "First")]);
+    assert_eq!(first.referrer(), Some(&first.referrers[0]));
+
+    // The root menu is linked to, but was not discovered in a menu
+    let root = items.get(ROOT, "").unwrap();
+    assert_eq!(root.referrers.len(), 1);
+    assert_eq!(root.referrer(), None);
+    assert_eq!(root.display_string, "Home");
+    assert_eq!(crawler.title(ROOT, ""), Some("Home"));
+}
+
+#[test]
+fn statistics_are_derived_from_the_items() {
+    let crawler = crawl();
+    let items = crawler.items();
+
+    assert_eq!(selectors(items.received(ItemType::Dir)), vec!["", "/items/docs"]);
+    assert_eq!(items.received(ItemType::Txt).count(), 3);
+
+    // Files of the same size are in the order they were discovered
+    assert_eq!(selectors(items.smallest(ItemType::Txt, 2)), vec!["/items/first.txt", "/items/second.txt"]);
+    assert_eq!(selectors(items.largest(ItemType::Txt, 2)), vec!["/items/long.txt", "/items/first.txt"]);
+    assert_eq!(selectors(items.largest(ItemType::Bin, 5)), vec!["/items/image.bin", "/items/small.bin"]);
+
+    let report = crawler.crawl_report();
+    assert_eq!(report.smallest_txt.unwrap().item.selector, "/items/first.txt");
+    assert_eq!(report.smallest_txt_contents, "same\r\n");
+    assert_eq!(report.smallest_bin.unwrap().size, 8);
+}
+
+#[test]
+fn items_round_trip_through_json() {
+    let crawler = crawl();
+    let json = serde_json::to_value(crawler.items()).unwrap();
+    assert!(json.is_array());
+
+    let parsed: CrawledItems = serde_json::from_value(json).unwrap();
+    assert_eq!(&parsed, crawler.items());
+    assert_eq!(parsed.position(ROOT, "/items/long.txt"), crawler.items().position(ROOT, "/items/long.txt"));
+}
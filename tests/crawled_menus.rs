@@ -0,0 +1,58 @@
+//! Keeps the display strings of discovered items and the info lines of crawled menus.
+
+use gopher::{
+    crawler::Crawler,
+    gopher::menu::Menu,
+    storage::DiscardStorage,
+    transport::MemoryTransport
+};
+
+mod common;
+use common::{builder, complete, menu_response, ROOT};
+
+fn root() -> Menu {
+    builder()
+        .info("Welcome\nto the test server")
+        .text("Readme", "/readme.txt")
+        .dir("Documentation", "/docs")
+        .build()
+}
+
+fn crawl() -> Crawler {
+    let docs = builder()
+        .info("All about it")
+        .text("Read me again", "/readme.txt")
+        .build();
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root()))
+        .with_response(ROOT, "/docs", menu_response(docs))
+        .with_response(ROOT, "/readme.txt", complete(b"readme\r\n"));
+
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+    crawler
+}
+
+#[test]
+fn items_keep_the_display_string_they_were_discovered_with() {
+    let crawler = crawl();
+
+    assert_eq!(crawler.title(ROOT, "/docs"), Some("Documentation"));
+    assert_eq!(crawler.title(ROOT, "/readme.txt"), Some("Readme"));
+    assert_eq!(crawler.title(ROOT, "/missing.txt"), None);
+}
+
+#[test]
+fn menus_keep_their_info_lines() {
+    let crawler = crawl();
+    let menu = |selector: &str| crawler.menus().iter().find(|menu| *menu.selector == selector).unwrap();
+
+    assert_eq!(crawler.menus().len(), 2);
+    assert_eq!(menu("").info_text(), "Welcome\nto the test server");
+    assert_eq!(menu("/docs").info_text(), "All about it");
+
+    // Info lines are kept in place, so the menu can be rebuilt as it was served
+    assert_eq!(menu("").to_menu(), root());
+}
@@ -0,0 +1,128 @@
+//! Deterministic crawls against canned responses held in a `MemoryTransport`.
+
+use gopher::{
+    crawler::Crawler,
+    gopher::{
+        menu::MenuItem,
+        response::{ItemType, Response, ResponseOutcome}
+    },
+    storage::DiscardStorage,
+    transport::MemoryTransport
+};
+
+mod common;
+use common::{builder, complete, menu_response, ROOT};
+
+fn crawl(transport: MemoryTransport) -> String {
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+    crawler.render_report()
+}
+
+#[test]
+fn files_update_smallest_and_largest_stats() {
+    let root = builder()
+        .text("Short", "/transport/short.txt")
+        .text("Long", "/transport/long.txt")
+        .binary("Tiny", "/transport/tiny.bin")
+        .binary("Huge", "/transport/huge.bin")
+        .build();
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/transport/short.txt", complete(b"hi\r\n"))
+        .with_response(ROOT, "/transport/long.txt", complete(&[b'a'; 500]))
+        .with_response(ROOT, "/transport/tiny.bin", complete(&[0; 3]))
+        .with_response(ROOT, "/transport/huge.bin", complete(&[0; 4096]));
+
+    let report = crawl(transport);
+
+    assert!(report.contains("Number of simple text files: 2\n"), "{report}");
+    assert!(report.contains("Smallest text file: gopher.test:70: /transport/short.txt (Short)\n\t\tSize: 4 bytes\n\t\tContents: hi\r\n"), "{report}");
+    assert!(report.contains("Size of the largest text file: 500 bytes\n\t\tgopher.test:70: /transport/long.txt (Long)"), "{report}");
+    assert!(report.contains("Number of binary files: 2\n"), "{report}");
+    assert!(report.contains("Size of the smallest binary file: 3 bytes\n\t\tgopher.test:70: /transport/tiny.bin (Tiny)"), "{report}");
+    assert!(report.contains("Size of the largest binary file: 4096 bytes\n\t\tgopher.test:70: /transport/huge.bin (Huge)"), "{report}");
+}
+
+#[test]
+fn text_files_which_are_not_utf8_are_the_smallest_by_their_size() {
+    let root = builder()
+        .text("Latin-1", "/transport/latin1.txt")
+        .text("Longer", "/transport/longer.txt")
+        .build();
+    // Replacing the invalid bytes makes the contents longer than the second file
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/transport/latin1.txt", complete(b"\xe9\xe0\r\n"))
+        .with_response(ROOT, "/transport/longer.txt", complete(b"abcd\r\n"));
+
+    let report = crawl(transport);
+    assert!(report.contains("Smallest text file: gopher.test:70: /transport/latin1.txt (Latin-1)\n\t\tSize: 4 bytes\n\t\tContents: \u{fffd}\u{fffd}\r\n"), "{report}");
+}
+
+#[test]
+fn failed_files_are_invalid_references() {
+    let root = builder()
+        .text("Slow", "/transport/slow.txt")
+        .binary("Gone", "/transport/gone.bin")
+        .build();
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/transport/slow.txt", Response::new(b"partial".to_vec(), ResponseOutcome::Timeout));
+
+    let report = crawl(transport);
+
+    assert!(report.contains("Number of simple text files: 0\n"), "{report}");
+    assert!(report.contains("Number of binary files: 0\n"), "{report}");
+    assert!(report.contains("Connection timed out gopher.test:70 /transport/slow.txt"), "{report}");
+    assert!(report.contains("Failed to connect gopher.test:70 /transport/gone.bin"), "{report}");
+}
+
+#[test]
+fn external_directories_are_only_connected_to() {
+    let root = builder()
+        .item(MenuItem::new(ItemType::Dir, "Up", "/", "up.test", 70))
+        .item(MenuItem::new(ItemType::Dir, "Down", "/", "down.test", 70))
+        .item(MenuItem::new(ItemType::Dir, "Other port", "/", "gopher.test", 71))
+        .build();
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_server("up.test:70")
+        .with_server("gopher.test:71");
+
+    let report = crawl(transport);
+
+    assert!(report.contains("Number of Gopher directories: 1\n"), "{report}");
+    assert!(report.contains("up.test:70 connected successfully"), "{report}");
+    assert!(report.contains("down.test:70 did not connect"), "{report}");
+    assert!(report.contains("gopher.test:71 connected successfully"), "{report}");
+}
+
+#[test]
+fn directories_are_crawled_once() {
+    let root = builder()
+        .dir("A", "/transport/a")
+        .dir("B", "/transport/b")
+        .build();
+    let a = builder()
+        .dir("B", "/transport/b")
+        .dir("Root", "")
+        .text("Shared", "/transport/shared.txt")
+        .build();
+    let b = builder()
+        .dir("A", "/transport/a")
+        .text("Shared", "/transport/shared.txt")
+        .build();
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/transport/a", menu_response(a))
+        .with_response(ROOT, "/transport/b", menu_response(b))
+        .with_response(ROOT, "/transport/shared.txt", complete(b"shared"));
+
+    let report = crawl(transport);
+
+    assert!(report.contains("Number of Gopher directories: 3\n"), "{report}");
+    assert!(report.contains("Number of simple text files: 1\n"), "{report}");
+}
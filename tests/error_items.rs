@@ -0,0 +1,51 @@
+//! Records every error (type 3) item with the menu lines it appeared on.
+
+use gopher::{
+    crawler::{
+        report::{ReportErrorItem, ReportLocation},
+        Crawler
+    },
+    storage::DiscardStorage,
+    transport::MemoryTransport
+};
+
+mod common;
+use common::{builder, menu_response, ROOT};
+
+fn location(selector: &str, line: usize) -> ReportLocation {
+    ReportLocation { server_details: String::from(ROOT), selector: String::from(selector), line }
+}
+
+#[test]
+fn error_items_are_grouped_by_their_message() {
+    let root = builder()
+        .error("Gone")
+        .dir("Docs", "/docs")
+        .error("Moved")
+        .build();
+    let docs = builder()
+        .info("Documentation")
+        .error("Gone")
+        .build();
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/docs", menu_response(docs));
+
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+
+    let report = crawler.crawl_report();
+    assert_eq!(report.error_items, vec![
+        ReportErrorItem { display_string: String::from("Gone"), locations: vec![location("", 1), location("/docs", 2)] },
+        ReportErrorItem { display_string: String::from("Moved"), locations: vec![location("", 3)] },
+    ]);
+
+    let rendered = report.render();
+    assert!(rendered.contains(
+        "The number of unique invalid references (error types): 2\n\
+        \t\t\"Gone\" in gopher.test:70:  (line 1), gopher.test:70: /docs (line 2)\n\
+        \t\t\"Moved\" in gopher.test:70:  (line 3)\n"
+    ), "{rendered}");
+}
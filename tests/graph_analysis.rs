@@ -0,0 +1,146 @@
+//! Analyses the structure of the links from menus to items in the report.
+
+use gopher::{
+    crawler::{
+        analysis::{GraphAnalysis, GraphNode},
+        report::{CrawlReport, ReportItem},
+        Crawler
+    },
+    storage::DiscardStorage,
+    transport::MemoryTransport
+};
+
+mod common;
+use common::{builder, complete, menu_response, ROOT};
+
+/// The root menu and /a link to each other, /a is the only way to /c and
+/// /c/deep.txt, /b is the only way to the empty menu /e, and /shared.txt is
+/// linked from both /a and /b
+fn crawl() -> Crawler {
+    let root = builder()
+        .dir("A", "/a")
+        .dir("B", "/b")
+        .text("Shortcut", "/shortcut.txt")
+        .build();
+    let a = builder()
+        .dir("Home", "")
+        .dir("C", "/c")
+        .text("Shared", "/shared.txt")
+        .text("Shortcut", "/shortcut.txt")
+        .build();
+    let b = builder()
+        .text("Shared", "/shared.txt")
+        .dir("Empty", "/e")
+        .build();
+    let c = builder()
+        .text("Deep", "/c/deep.txt")
+        .build();
+    let e = builder()
+        .info("Nothing here yet")
+        .build();
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/a", menu_response(a))
+        .with_response(ROOT, "/b", menu_response(b))
+        .with_response(ROOT, "/c", menu_response(c))
+        .with_response(ROOT, "/e", menu_response(e))
+        .with_response(ROOT, "/shared.txt", complete(b"shared\r\n"))
+        .with_response(ROOT, "/shortcut.txt", complete(b"shortcut\r\n"))
+        .with_response(ROOT, "/c/deep.txt", complete(b"deep\r\n"));
+
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(DiscardStorage)
+        .with_top_n(2);
+    crawler.start_crawl().unwrap();
+    crawler
+}
+
+fn analysis(report: &CrawlReport) -> &GraphAnalysis {
+    report.graph.as_ref().unwrap()
+}
+
+fn node<'a>(analysis: &'a GraphAnalysis, selector: &str) -> &'a GraphNode {
+    analysis.nodes.iter().find(|node| node.item.selector == selector).unwrap()
+}
+
+fn selectors(items: &[ReportItem]) -> Vec<&str> {
+    items.iter().map(|item| item.selector.as_str()).collect()
+}
+
+#[test]
+fn depths_are_the_shortest_path_from_the_root() {
+    let crawler = crawl();
+    let report = crawler.crawl_report();
+    let analysis = analysis(&report);
+
+    // Discovered through /a first, but the root menu links to it directly
+    assert_eq!(crawler.items().get(ROOT, "/shortcut.txt").unwrap().depth, 2);
+    assert_eq!(node(analysis, "/shortcut.txt").depth, Some(1));
+
+    assert_eq!(node(analysis, "").depth, Some(0));
+    assert_eq!(node(analysis, "/c/deep.txt").depth, Some(3));
+    assert_eq!(analysis.max_depth, 3);
+}
+
+#[test]
+fn degrees_count_links_to_and_from_items() {
+    let report = crawl().crawl_report();
+    let analysis = analysis(&report);
+
+    let degrees = |selector: &str| {
+        let node = node(analysis, selector);
+        (node.in_degree, node.out_degree)
+    };
+    assert_eq!(degrees(""), (1, 3));
+    assert_eq!(degrees("/a"), (1, 4));
+    assert_eq!(degrees("/shared.txt"), (2, 0));
+    assert_eq!(analysis.link_count, 10);
+    assert_eq!(analysis.nodes.len(), crawl().items().len());
+}
+
+#[test]
+fn menus_linking_to_each_other_form_cycles() {
+    let report = crawl().crawl_report();
+    let analysis = analysis(&report);
+
+    assert_eq!(analysis.cycles.len(), 1);
+    assert_eq!(selectors(&analysis.cycles[0]), vec!["", "/a"]);
+}
+
+#[test]
+fn single_points_of_failure_and_dead_ends_are_found() {
+    let report = crawl().crawl_report();
+    let analysis = analysis(&report);
+
+    let cut_offs = analysis.single_points_of_failure.iter()
+        .map(|cut_off| (cut_off.menu.selector.as_str(), selectors(&cut_off.items)))
+        .collect::<Vec<_>>();
+    assert_eq!(cut_offs, vec![
+        ("/a", vec!["/c", "/c/deep.txt"]),
+        ("/b", vec!["/e"]),
+        ("/c", vec!["/c/deep.txt"]),
+    ]);
+    assert_eq!(selectors(&analysis.dead_end_menus), vec!["/e"]);
+}
+
+#[test]
+fn the_analysis_is_part_of_the_text_report() {
+    let rendered = crawl().crawl_report().render();
+
+    assert!(rendered.contains("\tLink graph: 8 items, 10 links, maximum depth 3\n"), "{rendered}");
+    assert!(rendered.contains("\t\tMost linked items:\n\t\t\tlinked from 2: gopher.test:70: /shared.txt (Shared)\n"), "{rendered}");
+    assert!(rendered.contains("\tMenu cycles: 1\n\t\tgopher.test:70:  (Home), gopher.test:70: /a (A)\n"), "{rendered}");
+    assert!(rendered.contains("\t\t2 items only reached through gopher.test:70: /a (A)\n"), "{rendered}");
+    assert!(rendered.contains("\tDead-end menus: 1\n\t\tgopher.test:70: /e (Empty)\n"), "{rendered}");
+}
+
+#[test]
+fn reports_without_an_analysis_still_parse() {
+    let mut json = serde_json::to_value(crawl().crawl_report()).unwrap();
+    json.as_object_mut().unwrap().remove("graph");
+
+    let report: CrawlReport = serde_json::from_value(json).unwrap();
+    assert!(report.graph.is_none());
+    assert!(!report.render().contains("Link graph"));
+}
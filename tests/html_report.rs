@@ -0,0 +1,118 @@
+//! Renders the outcome of a crawl as a self-contained HTML page.
+
+use gopher::{
+    crawler::{html, Crawler},
+    gopher::{
+        menu::MenuItem,
+        response::{ItemType, Response, ResponseOutcome}
+    },
+    storage::DiscardStorage,
+    transport::MemoryTransport
+};
+
+mod common;
+use common::{builder, complete, menu_response, ROOT};
+
+fn crawl() -> Crawler {
+    let root = builder()
+        .dir("Docs <draft>", "/html/docs")
+        .text("Notes", "/html/notes.txt")
+        .item(MenuItem::new(ItemType::Dir, "Elsewhere", "/", "elsewhere.test", 70))
+        .build();
+    let docs = builder()
+        .binary("Image", "/html/image.bin")
+        .text("Gone", "/html/gone.txt")
+        .build();
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/html/docs", menu_response(docs))
+        .with_response(ROOT, "/html/notes.txt", complete(b"notes\r\n"))
+        .with_response(ROOT, "/html/image.bin", complete(&[0; 2048]))
+        .with_response(ROOT, "/html/gone.txt", Response::new(Vec::new(), ResponseOutcome::Timeout));
+
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+    crawler
+}
+
+#[test]
+fn pages_do_not_load_external_assets() {
+    let page = crawl().render_html();
+
+    assert!(page.starts_with("<!DOCTYPE html>"));
+    assert!(page.contains("<style>") && page.contains("<script>"));
+    assert!(!page.contains(" src="), "{page}");
+    assert!(!page.contains("href=\"http"), "{page}");
+    assert!(!page.contains("<link"), "{page}");
+}
+
+#[test]
+fn menus_form_a_collapsible_tree() {
+    let crawler = crawl();
+    let page = crawler.render_html();
+
+    // Items are anchored by their position in `Crawler::items`
+    let docs = crawler.items().iter().position(|item| item.selector == "/html/docs").unwrap();
+    let image = crawler.items().iter().position(|item| item.selector == "/html/image.bin").unwrap();
+
+    let docs_node = page.find(&format!("<li id=\"item-{docs}\"><details open>")).unwrap();
+    let image_node = page.find(&format!("<li id=\"item-{image}\">")).unwrap();
+    let docs_end = docs_node + page[docs_node..].find("</details>").unwrap();
+    assert!(docs_node < image_node && image_node < docs_end, "{page}");
+
+    // Display strings are escaped
+    assert!(page.contains("Docs &lt;draft&gt;"), "{page}");
+    assert!(!page.contains("Docs <draft>"), "{page}");
+}
+
+#[test]
+fn item_tables_are_sortable() {
+    let page = crawl().render_html();
+
+    assert!(page.contains("function sortTable(header)"));
+    assert!(page.contains("<th class=\"sortable\" onclick=\"sortTable(this)\">Size (bytes)</th>"), "{page}");
+    assert!(page.contains("<td class=\"number\" data-value=\"2048\">2048</td>"), "{page}");
+}
+
+#[test]
+fn histograms_bucket_sizes_by_powers_of_ten() {
+    let page = crawl().render_html();
+
+    let binary = &page[page.find("<h3>Histogram of binary files</h3>").unwrap()..];
+    assert!(binary.contains("<td>1000 - 9999 bytes</td><td class=\"number\">1</td>"), "{binary}");
+    let text = &page[page.find("<h3>Histogram of simple text files</h3>").unwrap()..];
+    assert!(text.contains("<td>0 - 9 bytes</td><td class=\"number\">1</td>"), "{text}");
+}
+
+#[test]
+fn problems_link_to_the_menus_containing_them() {
+    let crawler = crawl();
+    let page = crawler.render_html();
+    let root = crawler.items().iter().position(|item| item.selector.is_empty()).unwrap();
+    let docs = crawler.items().iter().position(|item| item.selector == "/html/docs").unwrap();
+
+    let references = &page[page.find("<h2>Invalid references</h2>").unwrap()..];
+    assert!(references.contains(&format!(
+        "<tr><td><code>{ROOT} /html/gone.txt</code></td><td class=\"error\">timeout</td>\
+        <td><a href=\"#item-{docs}\">Docs &lt;draft&gt;</a> (line 2)</td></tr>"
+    )), "{references}");
+
+    let servers = &page[page.find("<h2>External servers</h2>").unwrap()..];
+    assert!(servers.contains("<td>elsewhere.test</td>"), "{servers}");
+    assert!(servers.contains("<td class=\"error\">did not connect</td>"), "{servers}");
+    assert!(servers.contains(&format!("<a href=\"#item-{root}\">/</a> (line 3)")), "{servers}");
+}
+
+#[test]
+fn pages_render_from_a_report_and_items() {
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(MemoryTransport::new())
+        .with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+    let page = html::render_html(&crawler.crawl_report(), crawler.items());
+
+    assert!(page.contains("<h1>Crawl report for <code>gopher.test:70</code></h1>"));
+    assert!(page.contains("<p>No files received.</p>"));
+}
@@ -0,0 +1,171 @@
+//! Recrawls servers against the snapshot of an earlier crawl.
+
+use std::{cell::RefCell, fs, io, path::{Path, PathBuf}, rc::Rc};
+
+use gopher::{
+    crawler::{incremental::PreviousCrawl, Crawler},
+    gopher::{request::Request, response::Response},
+    mirror::Mirror,
+    transport::{MemoryTransport, Transport}
+};
+
+mod common;
+use common::{builder, complete, menu_response, temp_folder, ROOT};
+
+/// Server as it was served for the first crawl
+fn first_night() -> MemoryTransport {
+    MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(builder()
+            .dir("A", "/nightly/a")
+            .dir("B", "/nightly/b")
+            .text("Root text", "/nightly/root.txt")
+            .build()))
+        .with_response(ROOT, "/nightly/a", menu_response(builder()
+            .text("A text", "/nightly/a.txt")
+            .binary("A binary", "/nightly/a.bin")
+            .error("Broken link")
+            .build()))
+        .with_response(ROOT, "/nightly/b", menu_response(builder()
+            .text("B text", "/nightly/b.txt")
+            .build()))
+        .with_response(ROOT, "/nightly/root.txt", complete(b"root text\r\n"))
+        .with_response(ROOT, "/nightly/a.txt", complete(b"a\r\n"))
+        .with_response(ROOT, "/nightly/a.bin", complete(&[1; 10]))
+        .with_response(ROOT, "/nightly/b.txt", complete(b"b text\r\n"))
+}
+
+/// Server for the second crawl, with a new file in the root menu and in menu B
+fn second_night() -> MemoryTransport {
+    first_night()
+        .with_response(ROOT, "", menu_response(builder()
+            .dir("A", "/nightly/a")
+            .dir("B", "/nightly/b")
+            .text("Root text", "/nightly/root.txt")
+            .text("New text", "/nightly/new.txt")
+            .build()))
+        .with_response(ROOT, "/nightly/b", menu_response(builder()
+            .text("B text", "/nightly/b.txt")
+            .binary("New binary", "/nightly/new.bin")
+            .build()))
+        .with_response(ROOT, "/nightly/new.txt", complete(b"new\r\n"))
+        .with_response(ROOT, "/nightly/new.bin", complete(&[2; 100]))
+}
+
+/// Transport which keeps the selector of every request it sends
+struct LoggingTransport {
+    inner: MemoryTransport,
+    requests: Rc<RefCell<Vec<String>>>,
+}
+
+impl Transport for LoggingTransport {
+    fn send_and_recv(&mut self, request: &Request) -> io::Result<Response> {
+        self.requests.borrow_mut().push(request.selector.to_string());
+        self.inner.send_and_recv(request)
+    }
+
+    fn connect(&mut self, server_details: &str) -> io::Result<()> {
+        self.inner.connect(server_details)
+    }
+}
+
+/// Crawls a server into a mirror and saves the snapshot
+fn crawl(transport: impl Transport + 'static, folder: &Path, previous: Option<&PathBuf>) -> String {
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(Mirror::new(folder));
+    if let Some(previous) = previous {
+        crawler = crawler.with_previous(PreviousCrawl::load(previous).unwrap());
+    }
+    crawler.start_crawl().unwrap();
+    crawler.save_snapshot().unwrap();
+    crawler.render_report()
+}
+
+/// Removes the section only incremental crawls report
+fn without_incremental_section(report: &str) -> String {
+    let start = report.find("\tIncremental crawl:").expect("incremental crawl section");
+    let end = start + report[start..].find("\n\n").unwrap() + 2;
+    format!("{}{}", &report[..start], &report[end..])
+}
+
+#[test]
+fn unchanged_servers_are_not_downloaded_again() {
+    let first = temp_folder("incremental-unchanged-first");
+    let second = temp_folder("incremental-unchanged-second");
+    let expected = crawl(first_night(), &first, None);
+
+    let requests = Rc::new(RefCell::new(Vec::new()));
+    let transport = LoggingTransport { inner: first_night(), requests: Rc::clone(&requests) };
+    let report = crawl(transport, &second, Some(&first));
+
+    // Only the menus are requested to find out that nothing changed
+    assert_eq!(*requests.borrow(), vec!["", "/nightly/a", "/nightly/b"]);
+    assert!(report.contains("\tIncremental crawl:\n\t\tUnchanged menus: 3\n\t\tItems carried forward: 4\n\n"), "{report}");
+    assert_eq!(without_incremental_section(&report), expected);
+
+    // The snapshot of an incremental crawl is complete, so it can be used for the next one
+    let previous = PreviousCrawl::load(&first).unwrap();
+    assert_eq!(PreviousCrawl::load(&second).unwrap().len(), previous.len());
+    assert_eq!(previous.len(), 7);
+
+    fs::remove_dir_all(first).unwrap();
+    fs::remove_dir_all(second).unwrap();
+}
+
+#[test]
+fn items_of_changed_menus_are_crawled_again() {
+    let first = temp_folder("incremental-changed-first");
+    let second = temp_folder("incremental-changed-second");
+    let fresh = temp_folder("incremental-changed-fresh");
+    crawl(first_night(), &first, None);
+    let expected = crawl(second_night(), &fresh, None);
+
+    let requests = Rc::new(RefCell::new(Vec::new()));
+    let transport = LoggingTransport { inner: second_night(), requests: Rc::clone(&requests) };
+    let report = crawl(transport, &second, Some(&first));
+
+    // Menu A did not change, so its files are carried forward
+    assert_eq!(*requests.borrow(), vec![
+        "", "/nightly/a", "/nightly/b", "/nightly/b.txt", "/nightly/new.bin", "/nightly/root.txt", "/nightly/new.txt"
+    ]);
+    assert!(report.contains("\tIncremental crawl:\n\t\tUnchanged menus: 1\n\t\tItems carried forward: 2\n\n"), "{report}");
+    assert_eq!(without_incremental_section(&report), expected);
+
+    for folder in [first, second, fresh] {
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
+
+#[test]
+fn changed_menus_below_unchanged_menus_are_crawled_again() {
+    let first = temp_folder("incremental-nested-first");
+    let second = temp_folder("incremental-nested-second");
+    let fresh = temp_folder("incremental-nested-fresh");
+
+    // Only menu B changed, the root menu linking to it did not
+    let changed_b = || first_night()
+        .with_response(ROOT, "/nightly/b", menu_response(builder()
+            .text("B text", "/nightly/b.txt")
+            .binary("New binary", "/nightly/new.bin")
+            .build()))
+        .with_response(ROOT, "/nightly/new.bin", complete(&[2; 100]));
+    crawl(first_night(), &first, None);
+    let expected = crawl(changed_b(), &fresh, None);
+
+    let requests = Rc::new(RefCell::new(Vec::new()));
+    let transport = LoggingTransport { inner: changed_b(), requests: Rc::clone(&requests) };
+    let report = crawl(transport, &second, Some(&first));
+
+    assert_eq!(*requests.borrow(), vec!["", "/nightly/a", "/nightly/b", "/nightly/b.txt", "/nightly/new.bin"]);
+    assert!(report.contains("\tIncremental crawl:\n\t\tUnchanged menus: 2\n\t\tItems carried forward: 3\n\n"), "{report}");
+    assert_eq!(without_incremental_section(&report), expected);
+
+    for folder in [first, second, fresh] {
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
+
+#[test]
+fn crawls_without_a_previous_snapshot_cannot_be_incremental() {
+    assert!(PreviousCrawl::load(&temp_folder("incremental-missing")).is_err());
+}
@@ -0,0 +1,108 @@
+//! Exports the items discovered by a crawl as CSV and TSV tables.
+
+use gopher::{
+    crawler::{
+        export::{self, ExportFormat},
+        item::{ItemOutcome, Referrer},
+        Crawler
+    },
+    gopher::{
+        menu::MenuItem,
+        response::{ItemType, Response, ResponseOutcome}
+    },
+    storage::DiscardStorage,
+    transport::MemoryTransport
+};
+
+mod common;
+use common::{builder, complete, menu_response, ROOT};
+
+fn crawl() -> Crawler {
+    let root = builder()
+        .dir("Docs", "/export/docs")
+        .text("Notes", "/export/notes.txt")
+        .item(MenuItem::new(ItemType::Dir, "Elsewhere", "/", "elsewhere.test", 70))
+        .build();
+    let docs = builder()
+        .binary("Image", "/export/image.bin")
+        .text("Gone", "/export/gone.txt")
+        .text("Notes again", "/export/notes.txt")
+        .build();
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/export/docs", menu_response(docs))
+        .with_response(ROOT, "/export/notes.txt", complete(b"notes\r\n"))
+        .with_response(ROOT, "/export/image.bin", complete(&[0; 16]))
+        .with_response(ROOT, "/export/gone.txt", Response::new(Vec::new(), ResponseOutcome::Timeout));
+
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+    crawler
+}
+
+#[test]
+fn every_discovered_item_is_recorded_once() {
+    let crawler = crawl();
+    let items = crawler.items().iter()
+        .map(|item| (item.selector.as_str(), item.item_type, item.depth, item.outcome))
+        .collect::<Vec<_>>();
+
+    assert_eq!(items, vec![
+        ("", ItemType::Dir, 0, ItemOutcome::Fetched(ResponseOutcome::Complete)),
+        ("/export/docs", ItemType::Dir, 1, ItemOutcome::Fetched(ResponseOutcome::Complete)),
+        ("/export/image.bin", ItemType::Bin, 2, ItemOutcome::Fetched(ResponseOutcome::Complete)),
+        ("/export/gone.txt", ItemType::Txt, 2, ItemOutcome::Fetched(ResponseOutcome::Timeout)),
+        ("/export/notes.txt", ItemType::Txt, 2, ItemOutcome::Fetched(ResponseOutcome::Complete)),
+        ("/", ItemType::Dir, 1, ItemOutcome::External(false)),
+    ]);
+
+    let notes = &crawler.items()[4];
+    assert_eq!(notes.display_string, "Notes again");
+    assert_eq!(notes.referrer(), Some(&Referrer {
+        server_details: String::from(ROOT),
+        selector: String::from("/export/docs"),
+        line: 3,
+        display_string: String::from("Notes again"),
+    }));
+    assert_eq!(notes.size, Some(7));
+    assert!(notes.latency.is_some());
+    assert_eq!(notes.hash.as_ref().map(String::len), Some(64));
+
+    let gone = &crawler.items()[3];
+    assert_eq!((gone.size, gone.hash.as_ref()), (None, None));
+}
+
+#[test]
+fn csv_exports_quote_fields_with_commas_and_quotes() {
+    let mut items = crawl().items().to_vec();
+    items[4].display_string = String::from("Notes, \"draft\"");
+
+    let mut csv = Vec::new();
+    export::write_items(&mut csv, &items, ExportFormat::Csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines = csv.lines().collect::<Vec<_>>();
+
+    assert_eq!(lines.len(), items.len() + 1);
+    assert_eq!(lines[0], "host,port,selector,item_type,display_string,size,outcome,depth,referrer_server,referrer_selector,latency_ms,hash");
+    assert!(lines[1].starts_with("gopher.test,70,,1,,"), "{}", lines[1]);
+    assert!(lines[4].contains(",timeout,2,gopher.test:70,/export/docs,"), "{}", lines[4]);
+    assert!(lines[5].contains(",\"Notes, \"\"draft\"\"\",7,complete,"), "{}", lines[5]);
+    assert!(lines[6].starts_with("elsewhere.test,70,/,1,Elsewhere,,external-not-connected,1,gopher.test:70,,,"), "{}", lines[6]);
+}
+
+#[test]
+fn tsv_exports_escape_tabs_and_line_breaks() {
+    let mut items = crawl().items().to_vec();
+    items[4].display_string = String::from("Tab\there\\\nnext");
+
+    let mut tsv = Vec::new();
+    export::write_items(&mut tsv, &items, ExportFormat::Tsv).unwrap();
+    let tsv = String::from_utf8(tsv).unwrap();
+    let lines = tsv.lines().collect::<Vec<_>>();
+
+    assert_eq!(lines.len(), items.len() + 1);
+    assert!(lines.iter().all(|line| line.split('\t').count() == 12));
+    assert!(lines[5].contains("\tTab\\there\\\\\\nnext\t"), "{}", lines[5]);
+}
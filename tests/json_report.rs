@@ -0,0 +1,113 @@
+//! Checks the structured crawl report and its JSON schema.
+
+use gopher::{
+    crawler::{
+        report::{CrawlReport, REPORT_SCHEMA_VERSION},
+        Crawler
+    },
+    gopher::{
+        menu::MenuItem,
+        response::ItemType
+    },
+    storage::DiscardStorage,
+    transport::MemoryTransport
+};
+
+mod common;
+use common::{builder, complete, menu_response, ROOT};
+
+fn crawl(transport: MemoryTransport) -> Crawler {
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+    crawler
+}
+
+fn transport() -> MemoryTransport {
+    let root = builder()
+        .dir("Docs", "/json/docs")
+        .text("Short", "/json/short.txt")
+        .binary("Image", "/json/image.bin")
+        .error("Broken link")
+        .text("Missing", "/json/missing.txt")
+        .item(MenuItem::new(ItemType::Dir, "Elsewhere", "/", "elsewhere.test", 70))
+        .build();
+    let docs = builder()
+        .text("Long", "/json/long.txt")
+        .error("Broken link")
+        .build();
+
+    MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/json/docs", menu_response(docs))
+        .with_response(ROOT, "/json/short.txt", complete(b"hi\r\n"))
+        .with_response(ROOT, "/json/long.txt", complete(b"a longer text\r\n"))
+        .with_response(ROOT, "/json/image.bin", complete(&[0; 32]))
+}
+
+#[test]
+fn reports_hold_everything_in_the_text_report() {
+    let crawler = crawl(transport());
+    let report = crawler.crawl_report();
+
+    assert_eq!(report.schema_version, REPORT_SCHEMA_VERSION);
+    assert_eq!(report.root_server, ROOT);
+    assert_eq!(report.dir_count, 2);
+    assert_eq!(report.txt_file_count, 2);
+    assert_eq!(report.bin_file_count, 1);
+
+    let smallest_txt = report.smallest_txt.as_ref().unwrap();
+    assert_eq!((smallest_txt.item.selector.as_str(), smallest_txt.size), ("/json/short.txt", 4));
+    assert_eq!(smallest_txt.item.title.as_deref(), Some("Short"));
+    assert_eq!(report.smallest_txt_contents, "hi\r\n");
+    assert_eq!(report.largest_txt.as_ref().unwrap().item.selector, "/json/long.txt");
+    assert_eq!(report.largest_bin.as_ref().unwrap().size, 32);
+
+    assert_eq!(report.error_items.len(), 1);
+    assert_eq!(report.error_items[0].locations.len(), 2);
+    assert_eq!(report.external_servers[0].server_name, "elsewhere.test");
+    assert!(!report.external_servers[0].connected);
+    assert_eq!(report.invalid_references[0].details, format!("{ROOT} /json/missing.txt"));
+    assert_eq!(report.incremental, None);
+    assert_eq!(report.duplicates, None);
+
+    assert_eq!(report.render(), crawler.render_report());
+}
+
+#[test]
+fn reports_round_trip_through_json() {
+    let report = crawl(transport()).crawl_report();
+    let json = serde_json::to_string(&report).unwrap();
+    let parsed: CrawlReport = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(parsed, report);
+    assert_eq!(parsed.render(), report.render());
+}
+
+#[test]
+fn json_field_names_follow_the_schema() {
+    let json = serde_json::to_value(crawl(transport()).crawl_report()).unwrap();
+
+    assert_eq!(json["schema_version"], 1);
+    assert_eq!(json["dirs"][0]["server_details"], ROOT);
+    assert_eq!(json["smallest_txt"]["item"]["selector"], "/json/short.txt");
+    assert_eq!(json["smallest_txt"]["size"], 4);
+    assert_eq!(json["error_items"][0]["display_string"], "Broken link");
+    assert_eq!(json["error_items"][0]["locations"][0]["selector"], "/json/docs");
+    assert_eq!(json["error_items"][0]["locations"][0]["line"], 2);
+    assert_eq!(json["external_servers"][0]["server_port"], 70);
+    assert_eq!(json["invalid_references"][0]["outcome"], "ConnectionFailed");
+    assert!(json["incremental"].is_null());
+}
+
+#[test]
+fn crawls_without_files_have_no_smallest_or_largest_files() {
+    let crawler = crawl(MemoryTransport::new());
+    let report = crawler.crawl_report();
+
+    assert_eq!(report.dir_count, 0);
+    assert_eq!(report.smallest_txt, None);
+    assert_eq!(report.largest_bin, None);
+    assert!(report.render().contains(&format!("\tSmallest text file: : \n\t\tSize: {} bytes", u64::MAX)));
+}
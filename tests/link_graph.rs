@@ -0,0 +1,124 @@
+//! Exports the links from menus to the items they list as DOT and GraphML.
+
+use gopher::{
+    crawler::{
+        graph::{self, GraphFormat, LinkGraph},
+        Crawler
+    },
+    gopher::{
+        menu::MenuItem,
+        response::{ItemType, Response, ResponseOutcome}
+    },
+    storage::DiscardStorage,
+    transport::MemoryTransport
+};
+
+mod common;
+use common::{builder, complete, menu_response, ROOT};
+
+fn crawl() -> Crawler {
+    let root = builder()
+        .dir("Docs \"v2\"", "/graph/docs")
+        .text("Notes", "/graph/notes.txt")
+        .text("Notes again", "/graph/notes.txt")
+        .item(MenuItem::new(ItemType::Dir, "Elsewhere", "/", "elsewhere.test", 70))
+        .build();
+    let docs = builder()
+        .dir("Home", "")
+        .text("Notes", "/graph/notes.txt")
+        .binary("Gone", "/graph/gone.bin")
+        .build();
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/graph/docs", menu_response(docs))
+        .with_response(ROOT, "/graph/notes.txt", complete(b"notes\r\n"))
+        .with_response(ROOT, "/graph/gone.bin", Response::new(Vec::new(), ResponseOutcome::Timeout));
+
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+    crawler
+}
+
+fn export(crawler: &Crawler, format: GraphFormat) -> String {
+    let mut buffer = Vec::new();
+    graph::write_graph(&mut buffer, crawler.items(), format).unwrap();
+    String::from_utf8(buffer).unwrap()
+}
+
+#[test]
+fn every_menu_links_to_the_items_it_lists() {
+    let crawler = crawl();
+    let items = crawler.items();
+    let node = |selector: &str| items.iter().position(|item| item.selector == selector).unwrap();
+    let (root, docs, notes, gone) = (node(""), node("/graph/docs"), node("/graph/notes.txt"), node("/graph/gone.bin"));
+    let elsewhere = items.position("elsewhere.test:70", "/").unwrap();
+
+    // Listing notes twice in the root menu is one link, and the loop back to the root is kept
+    assert_eq!(crawler.link_graph(), LinkGraph {
+        node_count: 5,
+        links: vec![(root, docs), (root, notes), (root, elsewhere), (docs, root), (docs, notes), (docs, gone)],
+    });
+}
+
+#[test]
+fn dot_clusters_nodes_by_server() {
+    let crawler = crawl();
+    let dot = export(&crawler, GraphFormat::Dot);
+
+    assert!(dot.starts_with("digraph links {\n"), "{dot}");
+    assert!(dot.contains("\tsubgraph \"cluster_0\" {\n\t\tlabel=\"gopher.test:70\";\n"), "{dot}");
+    assert!(dot.contains("\tsubgraph \"cluster_1\" {\n\t\tlabel=\"elsewhere.test:70\";\n"), "{dot}");
+    assert!(dot.contains("\tn0 -> n1;\n"), "{dot}");
+    assert_eq!(dot.matches(" -> ").count(), crawler.link_graph().links.len());
+
+    // Quotes in display strings are escaped
+    assert!(dot.contains("label=\"Docs \\\"v2\\\"\""), "{dot}");
+}
+
+#[test]
+fn nodes_are_coloured_by_type_and_outcome() {
+    let crawler = crawl();
+    let dot = export(&crawler, GraphFormat::Dot);
+    let line = |selector: &str| {
+        let index = crawler.items().iter().position(|item| item.selector == selector).unwrap();
+        dot.lines().find(|line| line.starts_with(&format!("\t\tn{index} ["))).unwrap().to_string()
+    };
+
+    let notes = line("/graph/notes.txt");
+    assert!(notes.contains("shape=note, fillcolor=\"#fff2cc\", color=\"#274e13\""), "{notes}");
+    let gone = line("/graph/gone.bin");
+    assert!(gone.contains("shape=box3d, fillcolor=\"#d9d2e9\", color=\"#cc0000\""), "{gone}");
+    let elsewhere = line("/");
+    assert!(elsewhere.contains("shape=folder, fillcolor=\"#cfe2f3\", color=\"#cc0000\""), "{elsewhere}");
+}
+
+#[test]
+fn graphml_nests_a_graph_per_server() {
+    let crawler = crawl();
+    let graphml = export(&crawler, GraphFormat::GraphMl);
+
+    assert!(graphml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<graphml "), "{graphml}");
+    assert!(graphml.contains("\t<key id=\"size\" for=\"node\" attr.name=\"size\" attr.type=\"long\"/>\n"), "{graphml}");
+    assert!(graphml.contains("\t\t<node id=\"server-1\">\n\t\t\t<data key=\"label\">elsewhere.test:70</data>\n\t\t\t<graph id=\"server-1:\" edgedefault=\"directed\">\n"), "{graphml}");
+    assert!(graphml.contains("<data key=\"label\">Docs &quot;v2&quot;</data>"), "{graphml}");
+    assert!(graphml.contains("<data key=\"outcome\">timeout</data>"), "{graphml}");
+    assert!(graphml.contains("\t\t<edge source=\"n0\" target=\"n1\"/>\n"), "{graphml}");
+    assert!(graphml.ends_with("\t</graph>\n</graphml>\n"), "{graphml}");
+    assert_eq!(graphml.matches("<edge ").count(), crawler.link_graph().links.len());
+}
+
+#[test]
+fn dominators_give_the_nodes_reached_only_through_each_node() {
+    // 0 → 1 → 2 → 4 and 0 → 3 → 2, with a loop from 4 back to 1 and 5 not reached
+    let graph = LinkGraph {
+        node_count: 6,
+        links: vec![(0, 1), (0, 3), (1, 2), (2, 4), (3, 2), (4, 1)],
+    };
+
+    assert_eq!(graph.dominators(0), vec![Some(0), Some(0), Some(0), Some(0), Some(2), None]);
+    assert_eq!(graph.cut_offs(0), vec![vec![], vec![], vec![4], vec![], vec![], vec![]]);
+    assert_eq!(graph.reached_only_through(0, 2), vec![4]);
+    assert_eq!(graph.reached_only_through(0, 1), Vec::<usize>::new());
+}
@@ -0,0 +1,98 @@
+//! Round-trip property tests between the menu serializer (`Menu::serialize`) and
+//! the response line parser (`Menu::parse`).
+
+use gopher::gopher::{
+    menu::{Menu, MenuError, MenuItem},
+    response::ItemType
+};
+
+use proptest::prelude::*;
+
+/// Item types, excluding the ones which cannot start a response line
+fn item_type() -> impl Strategy<Value = ItemType> {
+    any::<char>()
+        .prop_filter("reserved item type", |c| !matches!(c, '.' | '\t' | '\r' | '\n'))
+        .prop_map(ItemType::from_char)
+}
+
+/// Strings without tabs or line breaks
+fn field() -> impl Strategy<Value = String> {
+    "[^\t\r\n]{0,40}"
+}
+
+fn menu_item() -> impl Strategy<Value = MenuItem> {
+    (
+        item_type(), 
+        any::<String>(), 
+        field(), 
+        "[^\t\r\n]{1,40}", 
+        any::<u16>(), 
+        proptest::option::of("[^\r\n]{0,10}"),
+    ).prop_map(|(item_type, display_string, selector, server_name, server_port, gopher_plus)| {
+        MenuItem { item_type, display_string, selector, server_name, server_port, gopher_plus }
+    })
+}
+
+/// The display string after tabs and line breaks have been escaped
+fn escaped(item: &MenuItem) -> MenuItem {
+    MenuItem {
+        display_string: item.display_string.replace(['\t', '\r', '\n'], " "),
+        ..item.clone()
+    }
+}
+
+proptest! {
+    #[test]
+    fn serialized_menus_parse_back(items in proptest::collection::vec(menu_item(), 0..20)) {
+        let menu = Menu { items };
+        let serialized = menu.serialize().unwrap();
+
+        prop_assert!(serialized.ends_with(".\r\n"));
+        let parsed = Menu::parse(serialized.as_bytes()).unwrap();
+        prop_assert_eq!(parsed.items, menu.items.iter().map(escaped).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn parsed_menus_serialize_back(items in proptest::collection::vec(menu_item(), 0..20)) {
+        let serialized = Menu { items: items.iter().map(escaped).collect() }.serialize().unwrap();
+        let reserialized = Menu::parse(serialized.as_bytes()).unwrap().serialize().unwrap();
+        prop_assert_eq!(reserialized, serialized);
+    }
+
+    #[test]
+    fn selectors_with_line_breaks_are_rejected(prefix in field(), suffix in field(), sep in "[\t\r\n]") {
+        let selector = format!("{prefix}{sep}{suffix}");
+        let menu = Menu::builder("localhost", 70).text("File", &selector).build();
+        prop_assert!(matches!(menu.serialize(), Err(MenuError::InvalidSelector(_))));
+    }
+}
+
+#[test]
+fn builder_produces_rfc1436_menu() {
+    let menu = Menu::builder("gopher.example.org", 70)
+        .info("Welcome\nto the server")
+        .dir("Documents", "/docs")
+        .text("About", "/about.txt")
+        .binary("Logo", "/logo.png")
+        .error("Broken link")
+        .item(MenuItem::new(ItemType::Dir, "Floodgap", "/", "gopher.floodgap.com", 70).with_gopher_plus("+"))
+        .build();
+
+    assert_eq!(
+        menu.serialize().unwrap(),
+        "iWelcome\t\tnull.host\t1\r\n\
+         ito the server\t\tnull.host\t1\r\n\
+         1Documents\t/docs\tgopher.example.org\t70\r\n\
+         0About\t/about.txt\tgopher.example.org\t70\r\n\
+         9Logo\t/logo.png\tgopher.example.org\t70\r\n\
+         3Broken link\t\tnull.host\t1\r\n\
+         1Floodgap\t/\tgopher.floodgap.com\t70\t+\r\n\
+         .\r\n"
+    );
+}
+
+#[test]
+fn empty_host_is_rejected_for_links() {
+    let menu = Menu { items: vec![MenuItem::new(ItemType::Txt, "File", "/file", "", 70)] };
+    assert!(matches!(menu.serialize(), Err(MenuError::InvalidHost(_))));
+}
@@ -0,0 +1,106 @@
+//! Records every menu line linking to an item, so broken links can be fixed.
+
+use gopher::{
+    crawler::{
+        item::Referrer,
+        report::CrawlReport,
+        Crawler
+    },
+    gopher::{
+        menu::MenuItem,
+        response::{ItemType, Response, ResponseOutcome}
+    },
+    storage::DiscardStorage,
+    transport::MemoryTransport
+};
+
+mod common;
+use common::{builder, complete, menu_response, ROOT};
+
+fn referrer(selector: &str, line: usize, display_string: &str) -> Referrer {
+    Referrer {
+        server_details: String::from(ROOT),
+        selector: String::from(selector),
+        line,
+        display_string: String::from(display_string),
+    }
+}
+
+/// /gone.txt is linked from both menus, and /docs ends with a malformed line
+fn crawl() -> Crawler {
+    let root = builder()
+        .info("Welcome")
+        .dir("Docs", "/docs")
+        .text("Gone", "/gone.txt")
+        .item(MenuItem::new(ItemType::Dir, "Elsewhere", "/", "elsewhere.test", 70))
+        .build();
+    let docs = builder()
+        .text("Still gone", "/gone.txt")
+        .item(MenuItem::new(ItemType::Dir, "Elsewhere again", "/other", "elsewhere.test", 70))
+        .build();
+    let mut docs = menu_response(docs).buffer;
+    docs.extend_from_slice(b"0Broken link without tabs\r\n");
+
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/docs", complete(&docs))
+        .with_response(ROOT, "/gone.txt", Response::new(Vec::new(), ResponseOutcome::Timeout));
+
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+    crawler
+}
+
+#[test]
+fn items_keep_the_line_and_display_string_of_every_link() {
+    let crawler = crawl();
+    let gone = crawler.items().get(ROOT, "/gone.txt").unwrap();
+
+    assert_eq!(gone.referrers, vec![referrer("/docs", 1, "Still gone"), referrer("", 3, "Gone")]);
+    assert_eq!(gone.referrer(), Some(&referrer("/docs", 1, "Still gone")));
+}
+
+#[test]
+fn invalid_references_show_where_they_are_linked_from() {
+    let report = crawl().crawl_report();
+
+    let gone = report.invalid_references.iter().find(|reference| reference.details == "gopher.test:70 /gone.txt").unwrap();
+    assert_eq!(gone.linked_from, vec![referrer("/docs", 1, "Still gone"), referrer("", 3, "Gone")]);
+
+    // Malformed lines are linked from the line itself
+    let broken = report.invalid_references.iter().find(|reference| reference.outcome == ResponseOutcome::MalformedResponseLine).unwrap();
+    assert_eq!(broken.linked_from, vec![referrer("/docs", 3, "Broken link without tabs")]);
+
+    let rendered = report.render();
+    assert!(rendered.contains(
+        "\t\tConnection timed out gopher.test:70 /gone.txt, linked from gopher.test:70: /docs (line 1: Still gone), gopher.test:70:  (line 3: Gone)\n"
+    ), "{rendered}");
+}
+
+#[test]
+fn external_servers_show_every_link_to_them() {
+    let report = crawl().crawl_report();
+
+    // Listed once, in the order the directories on the server were discovered, /docs being crawled first
+    assert_eq!(report.external_servers.len(), 1);
+    assert_eq!(report.external_servers[0].linked_from, vec![referrer("/docs", 2, "Elsewhere again"), referrer("", 4, "Elsewhere")]);
+    assert!(report.render().contains(
+        "elsewhere.test:70 did not connect, linked from gopher.test:70: /docs (line 2: Elsewhere again), gopher.test:70:  (line 4: Elsewhere)"
+    ));
+}
+
+#[test]
+fn reports_without_referrers_still_parse() {
+    let mut json = serde_json::to_value(crawl().crawl_report()).unwrap();
+    for field in ["invalid_references", "external_servers"] {
+        for entry in json[field].as_array_mut().unwrap() {
+            entry.as_object_mut().unwrap().remove("linked_from");
+        }
+    }
+
+    let report: CrawlReport = serde_json::from_value(json).unwrap();
+    assert!(report.invalid_references.iter().all(|reference| reference.linked_from.is_empty()));
+    assert!(report.render().contains("\t\tConnection timed out gopher.test:70 /gone.txt\n"));
+}
@@ -0,0 +1,38 @@
+//! Serves a saved crawl snapshot as a Gopher server.
+
+use std::fs;
+
+use gopher::{
+    crawler::Crawler,
+    mirror::Mirror,
+    server::Snapshot,
+    transport::MemoryTransport
+};
+
+mod common;
+use common::{builder, complete, menu_response, temp_folder, ROOT};
+
+#[test]
+fn text_lines_starting_with_a_dot_are_stuffed() {
+    let folder = temp_folder("serve");
+    let root = builder()
+        .text("Dots", "/dots.txt")
+        .text("No line break", "/unterminated.txt")
+        .build();
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/dots.txt", complete(b".hidden\r\nshown\r\n.\r\nend\r\n"))
+        .with_response(ROOT, "/unterminated.txt", complete(b"no line break"));
+
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(Mirror::new(&folder));
+    crawler.start_crawl().unwrap();
+    crawler.save_snapshot().unwrap();
+
+    let snapshot = Snapshot::load(&folder, "localhost", 7070).unwrap();
+    assert_eq!(snapshot.respond("/dots.txt").unwrap(), b"..hidden\r\nshown\r\n..\r\nend\r\n.\r\n");
+    assert_eq!(snapshot.respond("/unterminated.txt").unwrap(), b"no line break\r\n.\r\n");
+
+    fs::remove_dir_all(folder).unwrap();
+}
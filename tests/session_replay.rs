@@ -0,0 +1,152 @@
+//! Records crawls into session files and replays them without any network access.
+
+use std::{
+    fs,
+    io,
+    net::{IpAddr, Ipv4Addr},
+    path::PathBuf,
+    rc::Rc
+};
+
+use gopher::{
+    crawler::Crawler,
+    gopher::{
+        menu::MenuItem,
+        request::Request,
+        response::{ItemType, Response, ResponseOutcome}
+    },
+    session::{self, RecordingTransport, ReplayTransport, Transaction},
+    storage::DiscardStorage,
+    transport::{MemoryTransport, Transport}
+};
+
+mod common;
+use common::{builder, menu_response, temp_file, ROOT};
+
+fn session_file(name: &str) -> PathBuf {
+    temp_file(name, "session")
+}
+
+fn transport() -> MemoryTransport {
+    let root = builder()
+        .text("Notes", "/session/notes.txt")
+        .binary("Image", "/session/image.bin")
+        .text("Slow", "/session/slow.txt")
+        .item(MenuItem::new(ItemType::Dir, "Elsewhere", "/", "elsewhere.test", 70))
+        .build();
+
+    MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/session/notes.txt", Response::new(b"line one\r\nline two\r\n".to_vec(), ResponseOutcome::Complete))
+        .with_response(ROOT, "/session/image.bin", Response::new((0..=255).collect(), ResponseOutcome::Complete))
+        .with_response(ROOT, "/session/slow.txt", Response::new(b"par".to_vec(), ResponseOutcome::Timeout))
+}
+
+fn crawl(crawler: Crawler) -> String {
+    let mut crawler = crawler.with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+    crawler.render_report()
+}
+
+#[test]
+fn replayed_crawl_matches_recorded_crawl() {
+    let path = session_file("replay");
+
+    let recorded = crawl(
+        Crawler::new(Some(String::from("gopher.test")), Some(70))
+            .with_transport(RecordingTransport::create(transport(), &path).unwrap())
+    );
+
+    let transactions = session::read_session(&path).unwrap();
+    assert_eq!(session::root_server(&transactions), Some((String::from("gopher.test"), 70)));
+    assert_eq!(transactions.len(), 5);
+    assert!(transactions.iter().any(|transaction| matches!(
+        transaction, 
+        Transaction::Connect(server_details, false, _) if server_details == "elsewhere.test:70"
+    )));
+
+    let replayed = crawl(
+        Crawler::new(Some(String::from("gopher.test")), Some(70))
+            .with_transport(ReplayTransport::load(&path).unwrap())
+    );
+    assert_eq!(replayed, recorded);
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn truncated_sessions_keep_complete_records() {
+    let path = session_file("truncated");
+
+    crawl(
+        Crawler::new(Some(String::from("gopher.test")), Some(70))
+            .with_transport(RecordingTransport::create(transport(), &path).unwrap())
+    );
+    let complete = session::read_session(&path).unwrap().len();
+
+    // Cut the last record in half, as if the crawl was killed while writing it
+    let contents = fs::read(&path).unwrap();
+    fs::write(&path, &contents[..contents.len() - 5]).unwrap();
+
+    assert_eq!(session::read_session(&path).unwrap().len(), complete - 1);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn failed_requests_are_recorded() {
+    let path = session_file("failed");
+
+    // Nothing was recorded, so every request fails with an IO error
+    let mut transport = RecordingTransport::create(ReplayTransport::from_transactions(Vec::new()), &path).unwrap();
+    let request = Request::new(Rc::new(String::from("/missing")), Rc::new(String::from("gopher.test")), 70, ItemType::Txt);
+    assert!(transport.send_and_recv(&request).is_err());
+
+    let transactions = session::read_session(&path).unwrap();
+    assert!(matches!(
+        &transactions[..],
+        [Transaction::Error(server_details, selector, ItemType::Txt, error, _)]
+            if server_details == ROOT && selector == "/missing" && error.contains("was not recorded")
+    ));
+
+    // Replaying the session fails the request again
+    let mut replay = ReplayTransport::load(&path).unwrap();
+    assert!(replay.send_and_recv(&request).unwrap_err().to_string().contains("was not recorded"));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn records_which_are_not_utf8_are_rejected() {
+    let path = session_file("not-utf8");
+    fs::write(&path, b"GOPHER-SESSION 1\nCONNECT ok 5 \xff\nelsewhere.test:70\n").unwrap();
+
+    let error = session::read_session(&path).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn resolved_hostnames_are_recorded_and_replayed() {
+    let path = session_file("resolve");
+    let address = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    let crawler = || Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_canonicalization("resolve-hosts".parse().unwrap());
+
+    let recorded = crawl(crawler().with_transport(
+        RecordingTransport::create(transport().with_address("gopher.test", address), &path).unwrap()
+    ));
+
+    let transactions = session::read_session(&path).unwrap();
+    assert!(transactions.iter().any(|transaction| matches!(
+        transaction,
+        Transaction::Resolve(server_details, addresses, _) if server_details == ROOT && addresses == &vec![address]
+    )));
+    assert!(transactions.iter().any(|transaction| matches!(
+        transaction,
+        Transaction::Resolve(server_details, addresses, _) if server_details == "elsewhere.test:70" && addresses.is_empty()
+    )));
+
+    let replayed = crawl(crawler().with_transport(ReplayTransport::load(&path).unwrap()));
+    assert_eq!(replayed, recorded);
+
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,155 @@
+//! Reports top-N lists and the distribution of item sizes.
+
+use gopher::{
+    crawler::{
+        report::{CrawlReport, ReportFile},
+        stats::{HostTotal, SizeBucket, SizeStats, DEFAULT_TOP_N},
+        Crawler
+    },
+    gopher::{
+        menu::MenuItem,
+        response::{ItemType, Response, ResponseOutcome}
+    },
+    storage::DiscardStorage,
+    transport::MemoryTransport
+};
+
+mod common;
+use common::{builder, menu_response, ROOT};
+
+const OTHER: &str = "other.test:70";
+
+fn complete(size: usize) -> Response {
+    Response::new(vec![b'a'; size], ResponseOutcome::Complete)
+}
+
+/// Text files of 1, 5, 5, 5, 20 and 300 bytes, binary files of 10 and 2000 bytes,
+/// one of which is on another server
+fn crawl(top_n: usize) -> Crawler {
+    let root = builder()
+        .text("One", "/stats/1.txt")
+        .text("Five", "/stats/5a.txt")
+        .text("Five again", "/stats/5b.txt")
+        .text("Twenty", "/stats/20.txt")
+        .text("Five once more", "/stats/5c.txt")
+        .text("Three hundred", "/stats/300.txt")
+        .binary("Ten", "/stats/10.bin")
+        .item(MenuItem::new(ItemType::Bin, "Elsewhere", "/2000.bin", "other.test", 70))
+        .build();
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/stats/1.txt", complete(1))
+        .with_response(ROOT, "/stats/5a.txt", complete(5))
+        .with_response(ROOT, "/stats/5b.txt", complete(5))
+        .with_response(ROOT, "/stats/20.txt", complete(20))
+        .with_response(ROOT, "/stats/5c.txt", complete(5))
+        .with_response(ROOT, "/stats/300.txt", complete(300))
+        .with_response(ROOT, "/stats/10.bin", complete(10))
+        .with_response(OTHER, "/2000.bin", complete(2000));
+
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(DiscardStorage)
+        .with_top_n(top_n);
+    crawler.start_crawl().unwrap();
+    crawler
+}
+
+fn stats(report: &CrawlReport, item_type: ItemType) -> &SizeStats {
+    report.size_stats.iter().find(|stats| stats.item_type == item_type).unwrap()
+}
+
+fn selectors(files: &[ReportFile]) -> Vec<&str> {
+    files.iter().map(|file| file.item.selector.as_str()).collect()
+}
+
+#[test]
+fn sizes_are_summarised_per_item_type() {
+    let report = crawl(DEFAULT_TOP_N).crawl_report();
+    let txt = stats(&report, ItemType::Txt);
+
+    assert_eq!((txt.count, txt.total_bytes), (6, 336));
+    assert_eq!((txt.min, txt.median, txt.p95, txt.max), (Some(1), Some(5), Some(300), Some(300)));
+    assert_eq!(txt.mean, Some(56.0));
+
+    let dir = stats(&report, ItemType::Dir);
+    assert_eq!(dir.count, 1);
+    assert_eq!(dir.min, dir.max);
+
+    let types = report.size_stats.iter().map(|stats| stats.item_type).collect::<Vec<_>>();
+    assert_eq!(types, vec![ItemType::Dir, ItemType::Txt, ItemType::Bin]);
+}
+
+#[test]
+fn top_n_lists_include_ties() {
+    let report = crawl(2).crawl_report();
+    let txt = stats(&report, ItemType::Txt);
+
+    // The second smallest is tied with two more files of 5 bytes
+    assert_eq!(selectors(&txt.smallest), vec!["/stats/1.txt", "/stats/5a.txt", "/stats/5b.txt", "/stats/5c.txt"]);
+    assert_eq!(selectors(&txt.largest), vec!["/stats/300.txt", "/stats/20.txt"]);
+
+    let rendered = report.render();
+    assert!(rendered.contains("\t\tSmallest 2 (including ties):\n\t\t\t1 bytes: gopher.test:70: /stats/1.txt (One)\n"), "{rendered}");
+    assert!(rendered.contains("\t\tLargest 2:\n\t\t\t300 bytes: gopher.test:70: /stats/300.txt (Three hundred)\n"), "{rendered}");
+
+    let none = crawl(0).crawl_report();
+    assert!(stats(&none, ItemType::Txt).smallest.is_empty());
+    assert!(!none.render().contains("Smallest 0"));
+}
+
+#[test]
+fn histograms_bucket_sizes_by_powers_of_ten() {
+    let report = crawl(DEFAULT_TOP_N).crawl_report();
+
+    assert_eq!(stats(&report, ItemType::Txt).histogram, vec![
+        SizeBucket { min: 0, max: 9, count: 4 },
+        SizeBucket { min: 10, max: 99, count: 1 },
+        SizeBucket { min: 100, max: 999, count: 1 },
+    ]);
+    assert_eq!(stats(&report, ItemType::Bin).histogram, vec![
+        SizeBucket { min: 10, max: 99, count: 1 },
+        SizeBucket { min: 100, max: 999, count: 0 },
+        SizeBucket { min: 1000, max: 9999, count: 1 },
+    ]);
+
+    let rendered = report.render();
+    assert!(rendered.contains(&format!("\t\t\t0 - 9 bytes: 4 {}\n\t\t\t10 - 99 bytes: 1 {}\n", "#".repeat(30), "#".repeat(8))), "{rendered}");
+}
+
+#[test]
+fn bytes_are_totalled_per_server() {
+    let report = crawl(DEFAULT_TOP_N).crawl_report();
+    let menu_bytes = stats(&report, ItemType::Dir).total_bytes;
+
+    assert_eq!(report.host_totals, vec![
+        HostTotal { server_details: String::from(ROOT), dir_bytes: menu_bytes, txt_bytes: 336, bin_bytes: 10, total_bytes: menu_bytes + 346 },
+        HostTotal { server_details: String::from(OTHER), dir_bytes: 0, txt_bytes: 0, bin_bytes: 2000, total_bytes: 2000 },
+    ]);
+    assert!(report.render().contains("\tBytes received per server:\n\t\tgopher.test:70: "));
+}
+
+#[test]
+fn crawls_without_files_only_report_counts() {
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(MemoryTransport::new())
+        .with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+    let report = crawler.crawl_report();
+
+    let txt = stats(&report, ItemType::Txt);
+    assert_eq!((txt.count, txt.min, txt.mean), (0, None, None));
+    assert!(txt.histogram.is_empty());
+    assert!(report.render().contains("\tSize statistics of simple text files: 0 received, 0 bytes in total\n\n"));
+}
+
+#[test]
+fn reports_without_statistics_still_parse() {
+    let mut json = serde_json::to_value(crawl(DEFAULT_TOP_N).crawl_report()).unwrap();
+    for field in ["top_n", "size_stats", "host_totals"] {
+        json.as_object_mut().unwrap().remove(field);
+    }
+
+    let report: CrawlReport = serde_json::from_value(json).unwrap();
+    assert!(report.size_stats.is_empty() && report.host_totals.is_empty());
+}
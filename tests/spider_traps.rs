@@ -0,0 +1,216 @@
+//! Cuts off menus which look like part of a spider trap, so generated mazes end.
+
+use std::io;
+
+use gopher::{
+    crawler::{
+        item::ItemOutcome,
+        report::CrawlReport,
+        trap::TrapKind,
+        Crawler
+    },
+    gopher::{
+        menu::Menu,
+        request::Request,
+        response::{Response, ResponseOutcome}
+    },
+    storage::DiscardStorage,
+    transport::{MemoryTransport, Transport}
+};
+
+mod common;
+use common::{builder, menu_response, ROOT};
+
+/// Transport generating a menu for every selector, like a server serving an
+/// endless maze
+struct MazeTransport {
+    menu: fn(&str) -> Menu,
+}
+
+impl Transport for MazeTransport {
+    fn send_and_recv(&mut self, request: &Request) -> io::Result<Response> {
+        Ok(menu_response((self.menu)(&request.selector)))
+    }
+
+    fn connect(&mut self, _server_details: &str) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Every room of the maze leads to the next one: /maze/1, /maze/2, ... Rooms
+/// are lettered too, so they are not similar menus.
+fn numbered_maze(selector: &str) -> Menu {
+    let room = selector.strip_prefix("/maze/").map_or(0, |room| room.parse::<u8>().unwrap());
+    builder()
+        .info(&format!("Room {room} ({})", char::from(b'a' + room)))
+        .dir("Next room", &format!("/maze/{}", room + 1))
+        .build()
+}
+
+/// Every room of the maze leads to one with a longer name: /maze/x, /maze/xx, ...
+fn growing_maze(selector: &str) -> Menu {
+    let room = selector.strip_prefix("/maze/").unwrap_or_default();
+    builder()
+        .info(&format!("Room {room}"))
+        .dir("Next room", &format!("/maze/{room}x"))
+        .build()
+}
+
+/// Every menu links to itself one level deeper, as a symbolic link to a parent
+/// folder would, and to a few empty menus
+fn looping_folder(selector: &str) -> Menu {
+    if !selector.is_empty() && !selector.ends_with("/loop") {
+        return builder().info("Empty").build()
+    }
+    builder()
+        .dir("Loop", &format!("{selector}/loop"))
+        .dir("A", &format!("{selector}/a"))
+        .dir("B", &format!("{selector}/b"))
+        .build()
+}
+
+fn crawl(menu: fn(&str) -> Menu, threshold: usize) -> (Crawler, CrawlReport) {
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(MazeTransport { menu })
+        .with_storage(DiscardStorage)
+        .with_trap_threshold(threshold);
+    crawler.start_crawl().unwrap();
+    let report = crawler.crawl_report();
+    (crawler, report)
+}
+
+#[test]
+fn endless_numbered_menus_are_cut_off() {
+    let (crawler, report) = crawl(numbered_maze, 5);
+
+    // The root menu, the first room linked from it, then 5 rooms linked from the room before
+    assert_eq!(crawler.menus().len(), 7);
+    assert_eq!(crawler.items().get(ROOT, "/maze/6").unwrap().outcome, ItemOutcome::Fetched(ResponseOutcome::Complete));
+    assert_eq!(crawler.items().get(ROOT, "/maze/7").unwrap().outcome, ItemOutcome::Trapped);
+    assert_eq!(crawler.items().get(ROOT, "/maze/7").unwrap().outcome.to_string(), "suspected-trap");
+    assert!(crawler.items().get(ROOT, "/maze/8").is_none());
+
+    assert_eq!(report.traps.len(), 1);
+    let trap = &report.traps[0];
+    assert_eq!((trap.kind, trap.pattern.as_str(), trap.crawled), (TrapKind::NumberedSelectors, "/maze/#", 5));
+    assert_eq!(trap.cut_off.iter().map(|item| item.selector.as_str()).collect::<Vec<_>>(), vec!["/maze/7"]);
+
+    let rendered = report.render();
+    assert!(rendered.contains(
+        "\tSuspected spider traps: 1\n\
+        \t\tNumbered selectors gopher.test:70 /maze/#: 5 menus crawled, 1 cut off\n\
+        \t\t\tgopher.test:70: /maze/7 (Next room)\n\n"
+    ), "{rendered}");
+}
+
+#[test]
+fn numbered_menus_listed_side_by_side_are_crawled() {
+    // Every issue is listed in the root menu, none links to another
+    let mut transport = MemoryTransport::new();
+    let mut root = builder();
+    for number in 1..=30 {
+        root = root.dir(&format!("Issue {number}"), &format!("/issues/{number}"));
+        transport = transport.with_response(ROOT, &format!("/issues/{number}"), menu_response(
+            builder().info(&format!("Issue {number}")).build()
+        ));
+    }
+    let transport = transport.with_response(ROOT, "", menu_response(root.build()));
+
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(DiscardStorage)
+        .with_trap_threshold(5);
+    crawler.start_crawl().unwrap();
+
+    assert_eq!(crawler.menus().len(), 31);
+    assert!(crawler.crawl_report().traps.is_empty());
+}
+
+#[test]
+fn folders_growing_from_their_own_menus_are_cut_off() {
+    let (crawler, report) = crawl(growing_maze, 4);
+
+    // The root menu, the first room linked from it, then 4 rooms linked from the room before
+    assert_eq!(crawler.menus().len(), 6);
+    assert_eq!(crawler.items().get(ROOT, "/maze/xxxxxx").unwrap().outcome, ItemOutcome::Trapped);
+
+    assert_eq!(report.traps.len(), 1);
+    let trap = &report.traps[0];
+    assert_eq!((trap.kind, trap.pattern.as_str(), trap.crawled), (TrapKind::GrowingFolders, "/maze/*", 4));
+    assert!(report.render().contains("\t\tGrowing folders gopher.test:70 /maze/*: 4 menus crawled, 1 cut off\n"));
+}
+
+#[test]
+fn repeated_and_deeply_nested_folders_are_cut_off() {
+    let (crawler, report) = crawl(looping_folder, 4);
+
+    let kinds = report.traps.iter().map(|trap| (trap.kind, trap.pattern.as_str())).collect::<Vec<_>>();
+    // In the order the patterns were first seen
+    assert_eq!(kinds, vec![
+        (TrapKind::RepeatedComponents, "/loop/loop"),
+        (TrapKind::DeepNesting, "/loop/loop/loop/loop/…"),
+    ]);
+    assert!(crawler.items().iter().filter(|item| item.is_requested()).all(|item| item.selector.split('/').count() <= 5));
+    assert_eq!(crawler.items().get(ROOT, "/loop/loop/loop/b").unwrap().outcome, ItemOutcome::Trapped);
+}
+
+#[test]
+fn links_of_similar_menus_are_not_followed() {
+    // Every page only differs in the number of the text file it links to
+    let page = |number: u32| menu_response(builder()
+        .info("Same page")
+        .text("Reference", &format!("/ref{number}.txt"))
+        .build());
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(builder()
+            .dir("A", "/a")
+            .dir("B", "/b")
+            .dir("C", "/c")
+            .build()))
+        .with_response(ROOT, "/a", page(1))
+        .with_response(ROOT, "/b", page(2))
+        .with_response(ROOT, "/c", page(3))
+        .with_response(ROOT, "/ref1.txt", Response::new(b"1\r\n".to_vec(), ResponseOutcome::Complete))
+        .with_response(ROOT, "/ref2.txt", Response::new(b"2\r\n".to_vec(), ResponseOutcome::Complete));
+
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(DiscardStorage)
+        .with_trap_threshold(2);
+    crawler.start_crawl().unwrap();
+    let report = crawler.crawl_report();
+
+    // /c was received, but its link was not followed
+    assert_eq!(crawler.items().get(ROOT, "/c").unwrap().outcome, ItemOutcome::Fetched(ResponseOutcome::Complete));
+    assert!(crawler.items().get(ROOT, "/ref3.txt").is_none());
+
+    assert_eq!(report.traps.len(), 1);
+    let trap = &report.traps[0];
+    assert_eq!((trap.kind, trap.pattern.as_str(), trap.crawled), (TrapKind::SimilarMenus, "/a", 2));
+    assert_eq!(trap.cut_off[0].selector, "/c");
+}
+
+#[test]
+fn crawls_without_traps_do_not_report_them() {
+    let transport = MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(builder().info("Welcome").build()));
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport)
+        .with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+    let report = crawler.crawl_report();
+
+    assert_eq!(crawler.traps().suspected().count(), 0);
+    assert!(report.traps.is_empty());
+    assert!(!report.render().contains("Suspected spider traps"));
+}
+
+#[test]
+fn reports_without_traps_still_parse() {
+    let (_, report) = crawl(numbered_maze, 5);
+    let mut json = serde_json::to_value(&report).unwrap();
+    json.as_object_mut().unwrap().remove("traps");
+
+    let parsed: CrawlReport = serde_json::from_value(json).unwrap();
+    assert!(parsed.traps.is_empty());
+}
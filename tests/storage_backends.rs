@@ -0,0 +1,85 @@
+//! Crawls into each kind of storage chosen at runtime.
+
+use std::{fs, io::Read};
+
+use gopher::{
+    crawler::Crawler,
+    storage::{MemoryStorage, StorageKind},
+    transport::MemoryTransport,
+    MANIFEST
+};
+
+mod common;
+use common::{builder, complete, menu_response, temp_file, ROOT};
+
+fn transport() -> MemoryTransport {
+    let root = builder()
+        .text("Notes", "/storage/notes.txt")
+        .binary("Image", "/storage")
+        .binary("Nested image", "/storage/image.bin")
+        .build();
+
+    MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/storage/notes.txt", complete(b"notes\r\n"))
+        .with_response(ROOT, "/storage", complete(&[1, 2, 3]))
+        .with_response(ROOT, "/storage/image.bin", complete(&[4, 5, 6]))
+}
+
+#[test]
+fn memory_storage_is_shared_with_the_application() {
+    let storage = MemoryStorage::new();
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport())
+        .with_storage(storage.clone());
+    crawler.start_crawl().unwrap();
+
+    assert_eq!(storage.len(), 3);
+    assert_eq!(storage.get(ROOT, "/storage/notes.txt"), Some(b"notes\r\n".to_vec()));
+
+    crawler.save_snapshot().unwrap();
+    assert_eq!(storage.len(), 4);
+    assert!(storage.get(ROOT, "").is_some());
+}
+
+#[test]
+fn tar_storage_holds_the_mirror_and_manifest() {
+    let path = temp_file("storage", "tar");
+
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(transport())
+        .with_storage(StorageKind::Tar.open(&path).unwrap());
+    crawler.start_crawl().unwrap();
+    crawler.save_snapshot().unwrap();
+
+    let mut archive = tar::Archive::new(fs::File::open(&path).unwrap());
+    let mut entries = Vec::new();
+    for entry in archive.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents).unwrap();
+        entries.push((entry.path().unwrap().to_string_lossy().into_owned(), contents));
+    }
+
+    // `storage` is already a directory, so the file `/storage` cannot be stored there
+    let paths = entries.iter().map(|(path, _)| path.as_str()).collect::<Vec<_>>();
+    assert_eq!(paths, [
+        "gopher.test/70/storage/notes.txt",
+        "gopher.test/70/.collisions/%2Fstorage",
+        "gopher.test/70/storage/image.bin",
+        "gopher.test/70/.gophermap",
+        MANIFEST,
+    ]);
+    assert_eq!(entries[1].1, [1, 2, 3]);
+    assert!(String::from_utf8_lossy(&entries[4].1).starts_with("1\tgopher.test:70\t\tgopher.test/70/.gophermap\n"));
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn storage_kinds_are_parsed_by_name() {
+    for kind in [StorageKind::Directory, StorageKind::Content, StorageKind::Tar, StorageKind::Memory, StorageKind::Discard] {
+        assert_eq!(kind.to_string().parse::<StorageKind>(), Ok(kind));
+    }
+    assert!("zip".parse::<StorageKind>().is_err());
+}
@@ -0,0 +1,114 @@
+//! Archives crawls into WARC files and reads the records back.
+
+use std::{fs, path::{Path, PathBuf}, rc::Rc};
+
+use gopher::{
+    archive::{self, ArchivingTransport},
+    crawler::Crawler,
+    gopher::{
+        menu::MenuItem,
+        request::Request,
+        response::{ItemType, Response, ResponseOutcome}
+    },
+    session::ReplayTransport,
+    storage::DiscardStorage,
+    transport::{MemoryTransport, Transport}
+};
+
+mod common;
+use common::{builder, menu_response, temp_file, ROOT};
+
+fn archive_file(name: &str) -> PathBuf {
+    temp_file(name, "warc")
+}
+
+fn transport() -> MemoryTransport {
+    let root = builder()
+        .text("Notes", "/archive/notes 1.txt")
+        .binary("Image", "/archive/image.bin")
+        .item(MenuItem::new(ItemType::Dir, "Elsewhere", "/", "elsewhere.test", 70))
+        .build();
+
+    MemoryTransport::new()
+        .with_response(ROOT, "", menu_response(root))
+        .with_response(ROOT, "/archive/notes 1.txt", Response::new(b"line one\r\n".to_vec(), ResponseOutcome::Complete))
+        .with_response(ROOT, "/archive/image.bin", Response::new((0..=255).collect(), ResponseOutcome::Complete))
+}
+
+fn crawl(path: &Path) {
+    let mut crawler = Crawler::new(Some(String::from("gopher.test")), Some(70))
+        .with_transport(ArchivingTransport::create(transport(), path).unwrap())
+        .with_storage(DiscardStorage);
+    crawler.start_crawl().unwrap();
+}
+
+#[test]
+fn archive_holds_every_transaction() {
+    let path = archive_file("transactions");
+    crawl(&path);
+
+    let records = archive::read_archive(&path).unwrap();
+    let types = records.iter().map(|record| record.header("WARC-Type").unwrap()).collect::<Vec<_>>();
+    assert_eq!(types, ["warcinfo", "request", "response", "request", "response", "request", "response", "metadata"]);
+
+    // Menus and text files keep their last line, and selectors are percent-encoded
+    let root = archive::find_response(&records, "gopher://gopher.test:70/1").unwrap();
+    assert!(root.block.ends_with(b"\r\n.\r\n"));
+    let notes = archive::find_response(&records, "gopher://gopher.test:70/0/archive/notes%201.txt").unwrap();
+    assert_eq!(notes.block, b"line one\r\n.\r\n");
+    assert_eq!(notes.header("WARC-Gopher-Outcome"), Some("complete"));
+
+    let image = archive::find_response(&records, "gopher://gopher.test:70/9/archive/image.bin").unwrap();
+    assert_eq!(image.block, (0..=255).collect::<Vec<u8>>());
+
+    // Every response points at the request it answers
+    for (request, response) in records.iter().zip(&records[1..]).filter(|(_, response)| response.header("WARC-Type") == Some("response")) {
+        assert_eq!(response.header("WARC-Concurrent-To"), request.header("WARC-Record-ID"));
+        assert_eq!(request.block, format!("{}\r\n", selector(request)).into_bytes());
+    }
+
+    let connect = records.last().unwrap();
+    assert_eq!(connect.header("WARC-Target-URI"), Some("gopher://elsewhere.test:70/"));
+    assert_eq!(connect.header("WARC-Gopher-Outcome"), Some("connection-failed"));
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn failed_requests_are_archived() {
+    let path = archive_file("failed");
+
+    // Nothing was recorded, so every request fails with an IO error
+    let mut transport = ArchivingTransport::create(ReplayTransport::from_transactions(Vec::new()), &path).unwrap();
+    let request = Request::new(Rc::new(String::from("/missing")), Rc::new(String::from("gopher.test")), 70, ItemType::Txt);
+    assert!(transport.send_and_recv(&request).is_err());
+
+    let records = archive::read_archive(&path).unwrap();
+    let types = records.iter().map(|record| record.header("WARC-Type").unwrap()).collect::<Vec<_>>();
+    assert_eq!(types, ["warcinfo", "request", "metadata"]);
+    let outcome = &records[2];
+    assert_eq!(outcome.header("WARC-Target-URI"), Some("gopher://gopher.test:70/0/missing"));
+    assert_eq!(outcome.header("WARC-Concurrent-To"), records[1].header("WARC-Record-ID"));
+    assert_eq!(outcome.header("WARC-Gopher-Outcome"), Some("connection-failed"));
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn truncated_archives_are_rejected() {
+    let path = archive_file("truncated");
+    crawl(&path);
+
+    let contents = fs::read(&path).unwrap();
+    fs::write(&path, &contents[..contents.len() - 5]).unwrap();
+    assert!(archive::read_archive(&path).is_err());
+
+    fs::remove_file(path).unwrap();
+}
+
+/// Returns the decoded selector of a request record
+fn selector(request: &archive::WarcRecord) -> String {
+    let uri = request.header("WARC-Target-URI").unwrap();
+    let path = uri.splitn(4, '/').nth(3).unwrap();
+    path[1..].replace("%20", " ")
+}